@@ -0,0 +1,832 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::instructions::{encode, Instruction};
+
+/// The address the assembled program is loaded at.
+const ORIGIN: u16 = 0x200;
+
+/// The maximum nesting of `INCLUDE` directives, which also stops include cycles.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// The instruction mnemonics understood by the assembler.
+const MNEMONICS: [&str; 32] = [
+    "HALT", "CLS", "RET", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JP", "CALL", "SE", "SNE", "SAVE",
+    "LOAD", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND", "DRW", "SKP", "SKNP", "PLANE",
+    "AUDIO", "DW",
+];
+
+/// An error found while assembling, with the location it was found at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// The file the error is in, or `<source>` for source given as a string.
+    pub file: String,
+    /// The 1-based line of the error, or 0 if the file could not be read at all.
+    pub line: usize,
+    /// The 1-based column of the error, or 0 if the file could not be read at all.
+    pub column: usize,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// A location in the source, used to report errors.
+#[derive(Clone, Debug)]
+struct Location {
+    file: String,
+    line: usize,
+}
+
+impl Location {
+    fn error(&self, column: usize, message: impl Into<String>) -> AsmError {
+        AsmError {
+            file: self.file.clone(),
+            line: self.line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Str(Vec<u8>),
+    Punct(&'static str),
+}
+
+#[derive(Clone, Debug)]
+enum ExprKind {
+    Number(i64),
+    Symbol(String),
+    Here,
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+/// An expression, evaluated once every label is known.
+#[derive(Clone, Debug)]
+struct Expr {
+    kind: ExprKind,
+    column: usize,
+}
+
+/// The special operands that are neither registers nor expressions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Keyword {
+    I,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Pitch,
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Reg(u8),
+    Keyword(Keyword),
+    IndirectI,
+    Long(Expr),
+    Expr(Expr),
+}
+
+#[derive(Clone, Debug)]
+enum DataItem {
+    Expr(Expr),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Clone, Debug)]
+enum StatementKind {
+    Instruction { mnemonic: String, operands: Vec<Operand> },
+    Db(Vec<DataItem>),
+    Dw(Vec<Expr>),
+}
+
+/// An instruction or data directive, placed at its address during the first pass.
+#[derive(Clone, Debug)]
+struct Statement {
+    location: Location,
+    column: usize,
+    addr: u16,
+    kind: StatementKind,
+}
+
+#[derive(Clone, Debug)]
+enum Symbol {
+    Label(u16),
+    Const { expr: Expr, location: Location, addr: u16 },
+}
+
+/// Splits a line into tokens, each with its 1-based column. Comments start with `;`.
+fn tokenize(text: &str, location: &Location) -> Result<Vec<(Token, usize)>, AsmError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        let column = pos + 1;
+        if c == ';' {
+            break;
+        } else if c.is_whitespace() {
+            pos += 1;
+        } else if c.is_ascii_digit() {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_') {
+                pos += 1;
+            }
+            let literal: String = chars[start..pos].iter().filter(|c| **c != '_').collect();
+            let lower = literal.to_ascii_lowercase();
+            let value = if let Some(hex) = lower.strip_prefix("0x") {
+                i64::from_str_radix(hex, 16)
+            } else if let Some(bin) = lower.strip_prefix("0b") {
+                i64::from_str_radix(bin, 2)
+            } else {
+                lower.parse()
+            };
+            match value {
+                Ok(value) => tokens.push((Token::Number(value), column)),
+                Err(_) => return Err(location.error(column, format!("invalid number '{}'", literal))),
+            }
+        } else if c.is_alphabetic() || c == '_' || c == '.' {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_' || chars[pos] == '.') {
+                pos += 1;
+            }
+            tokens.push((Token::Ident(chars[start..pos].iter().collect()), column));
+        } else if c == '"' {
+            pos += 1;
+            let mut bytes = Vec::new();
+            loop {
+                match chars.get(pos) {
+                    None => return Err(location.error(column, "unterminated string")),
+                    Some('"') => break,
+                    Some('\\') => {
+                        let escaped = match chars.get(pos + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('0') => '\0',
+                            Some('\\') => '\\',
+                            Some('"') => '"',
+                            _ => return Err(location.error(pos + 1, "invalid escape sequence")),
+                        };
+                        bytes.push(escaped as u8);
+                        pos += 2;
+                    }
+                    Some(c) => {
+                        let mut buffer = [0; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                        pos += 1;
+                    }
+                }
+            }
+            pos += 1;
+            tokens.push((Token::Str(bytes), column));
+        } else {
+            let two: String = chars[pos..chars.len().min(pos + 2)].iter().collect();
+            let punct = match two.as_str() {
+                "<<" => "<<",
+                ">>" => ">>",
+                _ => match c {
+                    ',' => ",",
+                    ':' => ":",
+                    '=' => "=",
+                    '(' => "(",
+                    ')' => ")",
+                    '[' => "[",
+                    ']' => "]",
+                    '+' => "+",
+                    '-' => "-",
+                    '*' => "*",
+                    '/' => "/",
+                    '%' => "%",
+                    '&' => "&",
+                    '|' => "|",
+                    '^' => "^",
+                    '~' => "~",
+                    '$' => "$",
+                    _ => return Err(location.error(column, format!("unexpected character '{}'", c))),
+                },
+            };
+            pos += punct.len();
+            tokens.push((Token::Punct(punct), column));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the binding strength of a binary operator, higher binds tighter.
+fn precedence(op: &str) -> Option<u8> {
+    match op {
+        "|" => Some(1),
+        "^" => Some(2),
+        "&" => Some(3),
+        "<<" | ">>" => Some(4),
+        "+" | "-" => Some(5),
+        "*" | "/" | "%" => Some(6),
+        _ => None,
+    }
+}
+
+/// Parses the tokens of a single line.
+struct Parser<'a> {
+    tokens: &'a [(Token, usize)],
+    pos: usize,
+    location: &'a Location,
+    end_column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&'a Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    /// Returns the column of the next token, or the end of the line.
+    fn column(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end_column, |(_, column)| *column)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn advance(&mut self) {
+        self.pos += 1;
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), AsmError> {
+        if self.is_punct(punct) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.location.error(self.column(), format!("expected '{}'", punct)))
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), AsmError> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.location.error(self.column(), "unexpected trailing input"))
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, AsmError> {
+        self.parse_binary(1)
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, AsmError> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(Token::Punct(op)) = self.peek() {
+            let op_precedence = match precedence(op) {
+                Some(p) if p >= min_precedence => p,
+                _ => break,
+            };
+            let column = self.column();
+            self.advance();
+            let rhs = self.parse_binary(op_precedence + 1)?;
+            lhs = Expr {
+                kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+                column,
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, AsmError> {
+        let column = self.column();
+        match self.peek() {
+            Some(Token::Punct(op)) if *op == "-" || *op == "~" => {
+                self.advance();
+                let operand = self.parse_unary()?;
+                Ok(Expr {
+                    kind: ExprKind::Unary(op, Box::new(operand)),
+                    column,
+                })
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, AsmError> {
+        let column = self.column();
+        let kind = match self.peek() {
+            Some(Token::Number(value)) => ExprKind::Number(*value),
+            Some(Token::Ident(name)) => ExprKind::Symbol(name.clone()),
+            Some(Token::Punct("$")) => ExprKind::Here,
+            Some(Token::Punct("(")) => {
+                self.advance();
+                let expr = self.parse_expr()?;
+                self.expect_punct(")")?;
+                return Ok(expr);
+            }
+            _ => return Err(self.location.error(column, "expected an expression")),
+        };
+        self.advance();
+        Ok(Expr { kind, column })
+    }
+
+    fn parse_register(&mut self) -> Result<u8, AsmError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => match register(name) {
+                Some(reg) => {
+                    self.advance();
+                    Ok(reg)
+                }
+                None => Err(self.location.error(self.column(), "expected a register")),
+            },
+            _ => Err(self.location.error(self.column(), "expected a register")),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, AsmError> {
+        if self.is_punct("[") {
+            self.advance();
+            match self.peek() {
+                Some(Token::Ident(name)) if name.eq_ignore_ascii_case("I") => self.advance(),
+                _ => return Err(self.location.error(self.column(), "expected 'I'")),
+            }
+            self.expect_punct("]")?;
+            return Ok(Operand::IndirectI);
+        }
+        if let Some(Token::Ident(name)) = self.peek() {
+            if let Some(reg) = register(name) {
+                self.advance();
+                return Ok(Operand::Reg(reg));
+            }
+            if name.eq_ignore_ascii_case("LONG") {
+                self.advance();
+                return Ok(Operand::Long(self.parse_expr()?));
+            }
+            // Keywords only apply when they make up the whole operand, so `F + 1` is still an expression
+            let standalone = matches!(self.peek_at(1), None | Some(Token::Punct(",")));
+            if let (Some(keyword), true) = (keyword(name), standalone) {
+                self.advance();
+                return Ok(Operand::Keyword(keyword));
+            }
+        }
+        Ok(Operand::Expr(self.parse_expr()?))
+    }
+}
+
+/// Returns the register number for a `V0` - `VF` name.
+fn register(name: &str) -> Option<u8> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('V' | 'v'), Some(digit), None) => digit.to_digit(16).map(|reg| reg as u8),
+        _ => None,
+    }
+}
+
+fn keyword(name: &str) -> Option<Keyword> {
+    match name.to_ascii_uppercase().as_str() {
+        "I" => Some(Keyword::I),
+        "DT" => Some(Keyword::Dt),
+        "ST" => Some(Keyword::St),
+        "K" => Some(Keyword::K),
+        "F" => Some(Keyword::F),
+        "HF" => Some(Keyword::Hf),
+        "B" => Some(Keyword::B),
+        "R" => Some(Keyword::R),
+        "PITCH" => Some(Keyword::Pitch),
+        _ => None,
+    }
+}
+
+/// A two-pass assembler. The first pass places every statement and label, the second
+/// evaluates the expressions and encodes the instructions.
+struct Assembler {
+    symbols: HashMap<String, Symbol>,
+    statements: Vec<Statement>,
+    addr: u32,
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Assembler {
+            symbols: HashMap::new(),
+            statements: Vec::new(),
+            addr: ORIGIN as u32,
+        }
+    }
+
+    /// Runs the first pass over a source file and everything it includes.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source text.
+    /// * `file` - The name of the file, used in errors.
+    /// * `dir` - The directory includes are resolved against.
+    /// * `depth` - The include nesting depth.
+    fn first_pass(&mut self, source: &str, file: &str, dir: &Path, depth: usize) -> Result<(), AsmError> {
+        for (index, text) in source.lines().enumerate() {
+            let location = Location {
+                file: file.to_string(),
+                line: index + 1,
+            };
+            let tokens = tokenize(text, &location)?;
+            let mut parser = Parser {
+                tokens: &tokens,
+                pos: 0,
+                location: &location,
+                end_column: text.chars().count() + 1,
+            };
+
+            if let (Some(Token::Ident(name)), Some(Token::Punct(":"))) = (parser.peek(), parser.peek_at(1)) {
+                let column = parser.column();
+                self.define(name, Symbol::Label(self.addr as u16), &location, column)?;
+                parser.advance();
+                parser.advance();
+            }
+            if parser.at_end() {
+                continue;
+            }
+
+            let column = parser.column();
+            let name = match parser.peek() {
+                Some(Token::Ident(name)) => name.clone(),
+                _ => return Err(location.error(column, "expected an instruction or directive")),
+            };
+            parser.advance();
+
+            if parser.is_punct("=") {
+                parser.advance();
+                let expr = parser.parse_expr()?;
+                parser.expect_end()?;
+                let symbol = Symbol::Const {
+                    expr,
+                    location: location.clone(),
+                    addr: self.addr as u16,
+                };
+                self.define(&name, symbol, &location, column)?;
+                continue;
+            }
+
+            let mnemonic = name.to_ascii_uppercase();
+            let (kind, size) = match mnemonic.as_str() {
+                "INCLUDE" => {
+                    let path = match parser.peek() {
+                        Some(Token::Str(path)) => String::from_utf8_lossy(path).into_owned(),
+                        _ => return Err(location.error(parser.column(), "expected a file name")),
+                    };
+                    parser.advance();
+                    parser.expect_end()?;
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(location.error(column, "includes are nested too deeply"));
+                    }
+                    let path = dir.join(path);
+                    let source = fs::read_to_string(&path).map_err(|e| {
+                        location.error(column, format!("cannot read '{}': {}", path.display(), e))
+                    })?;
+                    let include_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                    self.first_pass(&source, &path.display().to_string(), &include_dir, depth + 1)?;
+                    continue;
+                }
+                "DB" => {
+                    let mut items = Vec::new();
+                    let mut size = 0;
+                    loop {
+                        if let Some(Token::Str(bytes)) = parser.peek() {
+                            size += bytes.len();
+                            items.push(DataItem::Bytes(bytes.clone()));
+                            parser.advance();
+                        } else {
+                            size += 1;
+                            items.push(DataItem::Expr(parser.parse_expr()?));
+                        }
+                        if parser.at_end() {
+                            break;
+                        }
+                        parser.expect_punct(",")?;
+                    }
+                    (StatementKind::Db(items), size)
+                }
+                "DW" => {
+                    let mut values = vec![parser.parse_expr()?];
+                    while !parser.at_end() {
+                        parser.expect_punct(",")?;
+                        values.push(parser.parse_expr()?);
+                    }
+                    let size = values.len() * 2;
+                    (StatementKind::Dw(values), size)
+                }
+                _ if MNEMONICS.contains(&mnemonic.as_str()) => {
+                    let mut operands = Vec::new();
+                    if mnemonic == "SAVE" || mnemonic == "LOAD" {
+                        // Register ranges are written `Vx - Vy`
+                        operands.push(Operand::Reg(parser.parse_register()?));
+                        if parser.is_punct("-") || parser.is_punct(",") {
+                            parser.advance();
+                        } else {
+                            return Err(location.error(parser.column(), "expected '-'"));
+                        }
+                        operands.push(Operand::Reg(parser.parse_register()?));
+                    } else if !parser.at_end() {
+                        operands.push(parser.parse_operand()?);
+                        while !parser.at_end() {
+                            parser.expect_punct(",")?;
+                            operands.push(parser.parse_operand()?);
+                        }
+                    }
+                    parser.expect_end()?;
+                    let size = match operands.as_slice() {
+                        [_, Operand::Long(_)] => 4,
+                        _ => 2,
+                    };
+                    (StatementKind::Instruction { mnemonic, operands }, size)
+                }
+                _ => return Err(location.error(column, format!("unknown instruction '{}'", name))),
+            };
+
+            self.statements.push(Statement {
+                location: location.clone(),
+                column,
+                addr: self.addr as u16,
+                kind,
+            });
+            self.addr += size as u32;
+            if self.addr > 0x10000 {
+                return Err(location.error(column, "program exceeds the 64KB address space"));
+            }
+        }
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str, symbol: Symbol, location: &Location, column: usize) -> Result<(), AsmError> {
+        if register(name).is_some() || keyword(name).is_some() || MNEMONICS.contains(&name.to_ascii_uppercase().as_str())
+        {
+            return Err(location.error(column, format!("'{}' is a reserved word", name)));
+        }
+        if self.symbols.contains_key(name) {
+            return Err(location.error(column, format!("duplicate symbol '{}'", name)));
+        }
+        self.symbols.insert(name.to_string(), symbol);
+        Ok(())
+    }
+
+    /// Evaluates an expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `expr` - The expression to evaluate.
+    /// * `location` - The location of the expression, used in errors.
+    /// * `here` - The value of `$`, the address of the current statement.
+    /// * `resolving` - The constants being evaluated, to detect circular definitions.
+    fn eval(&self, expr: &Expr, location: &Location, here: u16, resolving: &mut Vec<String>) -> Result<i64, AsmError> {
+        match &expr.kind {
+            ExprKind::Number(value) => Ok(*value),
+            ExprKind::Here => Ok(here as i64),
+            ExprKind::Symbol(name) => match self.symbols.get(name) {
+                Some(Symbol::Label(addr)) => Ok(*addr as i64),
+                Some(Symbol::Const {
+                    expr,
+                    location: const_location,
+                    addr,
+                }) => {
+                    if resolving.contains(name) {
+                        return Err(location.error(expr.column, format!("circular definition of '{}'", name)));
+                    }
+                    resolving.push(name.clone());
+                    let value = self.eval(expr, const_location, *addr, resolving);
+                    resolving.pop();
+                    value
+                }
+                None => Err(location.error(expr.column, format!("undefined symbol '{}'", name))),
+            },
+            ExprKind::Unary(op, operand) => {
+                let value = self.eval(operand, location, here, resolving)?;
+                Ok(if *op == "-" { value.wrapping_neg() } else { !value })
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, location, here, resolving)?;
+                let rhs = self.eval(rhs, location, here, resolving)?;
+                match *op {
+                    "+" => Ok(lhs.wrapping_add(rhs)),
+                    "-" => Ok(lhs.wrapping_sub(rhs)),
+                    "*" => Ok(lhs.wrapping_mul(rhs)),
+                    "/" | "%" if rhs == 0 => Err(location.error(expr.column, "division by zero")),
+                    "/" => Ok(lhs.wrapping_div(rhs)),
+                    "%" => Ok(lhs.wrapping_rem(rhs)),
+                    "&" => Ok(lhs & rhs),
+                    "|" => Ok(lhs | rhs),
+                    "^" => Ok(lhs ^ rhs),
+                    "<<" => Ok(lhs.wrapping_shl(rhs as u32)),
+                    _ => Ok(lhs.wrapping_shr(rhs as u32)),
+                }
+            }
+        }
+    }
+
+    /// Evaluates an expression and checks that it fits in a field.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u16, AsmError>` - The value, with negative values wrapped to the field width.
+    fn value(&self, expr: &Expr, statement: &Statement, min: i64, max: i64, what: &str) -> Result<u16, AsmError> {
+        let value = self.eval(expr, &statement.location, statement.addr, &mut Vec::new())?;
+        if value < min || value > max {
+            return Err(statement
+                .location
+                .error(expr.column, format!("value {} does not fit in {}", value, what)));
+        }
+        Ok((value & 0xFFFF) as u16)
+    }
+
+    fn byte(&self, expr: &Expr, statement: &Statement) -> Result<u8, AsmError> {
+        Ok(self.value(expr, statement, -0x80, 0xFF, "a byte")? as u8)
+    }
+
+    fn nibble(&self, expr: &Expr, statement: &Statement) -> Result<u8, AsmError> {
+        Ok(self.value(expr, statement, 0, 0xF, "a nibble")? as u8)
+    }
+
+    fn address(&self, expr: &Expr, statement: &Statement) -> Result<u16, AsmError> {
+        self.value(expr, statement, 0, 0xFFF, "a 12-bit address")
+    }
+
+    /// Runs the second pass, encoding every statement.
+    fn second_pass(&self) -> Result<Vec<u8>, AsmError> {
+        let mut rom = Vec::new();
+        for statement in &self.statements {
+            match &statement.kind {
+                StatementKind::Db(items) => {
+                    for item in items {
+                        match item {
+                            DataItem::Bytes(bytes) => rom.extend_from_slice(bytes),
+                            DataItem::Expr(expr) => rom.push(self.byte(expr, statement)?),
+                        }
+                    }
+                }
+                StatementKind::Dw(values) => {
+                    for expr in values {
+                        let word = self.value(expr, statement, -0x8000, 0xFFFF, "a word")?;
+                        rom.extend_from_slice(&word.to_be_bytes());
+                    }
+                }
+                StatementKind::Instruction { mnemonic, operands } => {
+                    if let [Operand::Keyword(Keyword::I), Operand::Long(expr)] = operands.as_slice() {
+                        let addr = self.value(expr, statement, 0, 0xFFFF, "a 16-bit address")?;
+                        rom.extend_from_slice(&encode(&Instruction::LoadLongIndex).to_be_bytes());
+                        rom.extend_from_slice(&addr.to_be_bytes());
+                        continue;
+                    }
+                    let instruction = self.instruction(mnemonic, operands, statement)?;
+                    rom.extend_from_slice(&encode(&instruction).to_be_bytes());
+                }
+            }
+        }
+        Ok(rom)
+    }
+
+    /// Builds the instruction for a mnemonic and its operands.
+    fn instruction(&self, mnemonic: &str, operands: &[Operand], statement: &Statement) -> Result<Instruction, AsmError> {
+        use Keyword as Kw;
+        use Operand::{Expr as E, IndirectI, Keyword as K, Reg};
+
+        let instruction = match (mnemonic, operands) {
+            ("HALT", []) => Instruction::Halt,
+            ("CLS", []) => Instruction::ClearScreen,
+            ("RET", []) => Instruction::Return,
+            ("SCD", [E(n)]) => Instruction::ScrollDown { n: self.nibble(n, statement)? },
+            ("SCU", [E(n)]) => Instruction::ScrollUp { n: self.nibble(n, statement)? },
+            ("SCR", []) => Instruction::ScrollRight,
+            ("SCL", []) => Instruction::ScrollLeft,
+            ("EXIT", []) => Instruction::Exit,
+            ("LOW", []) => Instruction::LowRes,
+            ("HIGH", []) => Instruction::HighRes,
+            ("JP", [E(nnn)]) => Instruction::JumpToAddress { nnn: self.address(nnn, statement)? },
+            ("JP", [Reg(0), E(nnn)]) => Instruction::JumpToAddressPlusV0 { nnn: self.address(nnn, statement)? },
+            ("CALL", [E(nnn)]) => Instruction::CallAddress { nnn: self.address(nnn, statement)? },
+            ("SE", [Reg(x), Reg(y)]) => Instruction::SkipIfRegEqualsReg { x: *x, y: *y },
+            ("SE", [Reg(x), E(kk)]) => Instruction::SkipIfRegEqualsByte { x: *x, kk: self.byte(kk, statement)? },
+            ("SNE", [Reg(x), Reg(y)]) => Instruction::SkipIfRegNotEqualsReg { x: *x, y: *y },
+            ("SNE", [Reg(x), E(kk)]) => Instruction::SkipIfRegNotEqualsByte { x: *x, kk: self.byte(kk, statement)? },
+            ("SAVE", [Reg(x), Reg(y)]) => Instruction::StoreRegRangeIntoMem { x: *x, y: *y },
+            ("LOAD", [Reg(x), Reg(y)]) => Instruction::LoadRegRangeFromMem { x: *x, y: *y },
+            ("LD", [Reg(x), Reg(y)]) => Instruction::LoadRegIntoReg { x: *x, y: *y },
+            ("LD", [Reg(x), K(Kw::Dt)]) => Instruction::LoadDelayTimerIntoReg { x: *x },
+            ("LD", [Reg(x), K(Kw::K)]) => Instruction::LoadKeyIntoReg { x: *x },
+            ("LD", [Reg(x), K(Kw::R)]) => Instruction::LoadRplFlags { x: *x },
+            ("LD", [Reg(x), IndirectI]) => Instruction::LoadRegsFromMem { x: *x },
+            ("LD", [Reg(x), E(kk)]) => Instruction::LoadByteIntoReg { x: *x, kk: self.byte(kk, statement)? },
+            ("LD", [K(Kw::I), E(nnn)]) => Instruction::LoadIndex { nnn: self.address(nnn, statement)? },
+            ("LD", [K(Kw::Dt), Reg(x)]) => Instruction::LoadRegIntoDelayTimer { x: *x },
+            ("LD", [K(Kw::St), Reg(x)]) => Instruction::LoadRegIntoSoundTimer { x: *x },
+            ("LD", [K(Kw::F), Reg(x)]) => Instruction::LoadFontIntoReg { x: *x },
+            ("LD", [K(Kw::Hf), Reg(x)]) => Instruction::LoadBigFontIntoReg { x: *x },
+            ("LD", [K(Kw::B), Reg(x)]) => Instruction::LoadBCDIntoMem { x: *x },
+            ("LD", [K(Kw::R), Reg(x)]) => Instruction::StoreRplFlags { x: *x },
+            ("LD", [K(Kw::Pitch), Reg(x)]) => Instruction::LoadRegIntoPitch { x: *x },
+            ("LD", [IndirectI, Reg(x)]) => Instruction::StoreRegsIntoMem { x: *x },
+            ("ADD", [Reg(x), Reg(y)]) => Instruction::AddRegToReg { x: *x, y: *y },
+            ("ADD", [Reg(x), E(kk)]) => Instruction::AddByteToReg { x: *x, kk: self.byte(kk, statement)? },
+            ("ADD", [K(Kw::I), Reg(x)]) => Instruction::AddRegToIndex { x: *x },
+            ("OR", [Reg(x), Reg(y)]) => Instruction::OrRegWithReg { x: *x, y: *y },
+            ("AND", [Reg(x), Reg(y)]) => Instruction::AndRegWithReg { x: *x, y: *y },
+            ("XOR", [Reg(x), Reg(y)]) => Instruction::XorRegWithReg { x: *x, y: *y },
+            ("SUB", [Reg(x), Reg(y)]) => Instruction::SubtractRegFromReg { x: *x, y: *y },
+            ("SUBN", [Reg(x), Reg(y)]) => Instruction::SubtractRegFromOtherReg { x: *x, y: *y },
+            ("SHR", [Reg(x)]) => Instruction::ShiftRight { x: *x, y: *x },
+            ("SHR", [Reg(x), Reg(y)]) => Instruction::ShiftRight { x: *x, y: *y },
+            ("SHL", [Reg(x)]) => Instruction::ShiftLeft { x: *x, y: *x },
+            ("SHL", [Reg(x), Reg(y)]) => Instruction::ShiftLeft { x: *x, y: *y },
+            ("RND", [Reg(x), E(kk)]) => Instruction::RandomByte { x: *x, kk: self.byte(kk, statement)? },
+            ("DRW", [Reg(x), Reg(y), E(n)]) => Instruction::DrawSprite {
+                x: *x,
+                y: *y,
+                n: self.nibble(n, statement)?,
+            },
+            ("SKP", [Reg(x)]) => Instruction::SkipIfKeyPressed { x: *x },
+            ("SKNP", [Reg(x)]) => Instruction::SkipIfKeyNotPressed { x: *x },
+            ("PLANE", [E(n)]) => Instruction::SelectPlanes { n: self.nibble(n, statement)? },
+            ("AUDIO", []) => Instruction::LoadAudioPattern,
+            _ => {
+                return Err(statement
+                    .location
+                    .error(statement.column, format!("invalid operands for {}", mnemonic)))
+            }
+        };
+        Ok(instruction)
+    }
+}
+
+/// Assembles source text into a ROM that can be passed to `CPU::load_program`.
+///
+/// The syntax matches the mnemonics printed by `Instruction`'s `Display` implementation,
+/// so the output of the disassembler can be assembled again. On top of the instructions
+/// it supports:
+///
+/// * `name:` labels and `name = expr` constants.
+/// * `DB` and `DW` data directives, `DB` also accepting strings.
+/// * `INCLUDE "file"`, resolved against the current directory.
+/// * Expressions with `+ - * / % & | ^ << >> ~`, parentheses and `$` for the current address.
+///
+/// # Arguments
+///
+/// * `source` - The assembly source.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, AsmError>` - The ROM, or the first error found.
+///
+/// # Examples
+///
+/// ```rust
+/// use chip8::asm::assemble;
+///
+/// let rom = assemble("loop: ADD V0, 1\n JP loop").unwrap();
+/// assert_eq!(rom, [0x70, 0x01, 0x12, 0x00]);
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler::new();
+    assembler.first_pass(source, "<source>", Path::new(""), 0)?;
+    assembler.second_pass()
+}
+
+/// Assembles a source file, resolving its includes against the directory it is in.
+///
+/// # Arguments
+///
+/// * `path` - The path to the source file.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, AsmError>` - The ROM, or the first error found.
+pub fn assemble_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, AsmError> {
+    let path = path.as_ref();
+    let file = path.display().to_string();
+    let source = fs::read_to_string(path).map_err(|e| AsmError {
+        file: file.clone(),
+        line: 0,
+        column: 0,
+        message: format!("cannot read file: {}", e),
+    })?;
+    let dir: PathBuf = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    let mut assembler = Assembler::new();
+    assembler.first_pass(&source, &file, &dir, 0)?;
+    assembler.second_pass()
+}
@@ -1,8 +1,15 @@
+use crate::error::{Chip8Error, MemoryError};
 use crate::gpu::{Coordinate, GPU};
-use crate::instructions::{parse_opcode, Opcode};
-use crate::memory::Memory;
+use crate::instructions::{decode, Instruction};
+use crate::memory::{Memory, AUDIO_PATTERN_SIZE, BIG_FONT_ADDR, FONT_ADDR};
+use crate::observer::Chip8Observer;
+use crate::quirks::{IndexIncrement, Platform, Quirks};
+use crate::rng::{RandomSource, ThreadRandom};
+use crate::sound::SoundState;
+use crate::trace::{self, Category, Level, Tracer};
 
-use rand::Rng;
+/// The rate at which the delay and sound timers count down, in Hz.
+pub const TIMER_HZ: u32 = 60;
 
 /// Represents a binary-coded decimal (BCD) value.
 ///
@@ -13,7 +20,7 @@ use rand::Rng;
 /// For example:
 /// - The number 123 would be represented as `(1, 2, 3)`.
 /// - The number 45 would be represented as `(0, 4, 5)`.
-type BCD = (u8, u8, u8);
+type Bcd = (u8, u8, u8);
 
 /// Trait for types that can be represented as a binary-coded decimal (BCD).
 ///
@@ -25,11 +32,11 @@ pub trait BCDRepresentable {
     ///
     /// Returns a tuple of three `u8` values, representing the hundreds,
     /// tens, and ones places of the number, respectively.
-    fn to_bcd(&self) -> BCD;
+    fn to_bcd(&self) -> Bcd;
 }
 
 impl BCDRepresentable for u8 {
-    fn to_bcd(&self) -> BCD {
+    fn to_bcd(&self) -> Bcd {
         let hundreds = self / 100;
         let tens = (self % 100) / 10;
         let ones = self % 10;
@@ -40,143 +47,294 @@ impl BCDRepresentable for u8 {
 /// Represents the CPU of the Chip-8 virtual machine.
 pub struct CPU {
     pub memory: Memory,
-    gpu: GPU,
+    pub(crate) gpu: GPU,
     pub key_state: u16,
     pub halt: bool,
-    waiting_for_key: Option<u8>,
+    /// The interpreter behaviours used to resolve ambiguous opcodes.
+    pub quirks: Quirks,
+    pub(crate) waiting_for_key: Option<u8>,
+    pub(crate) vblank_ready: bool,
+    /// The source of the `Cxkk` random bytes.
+    pub(crate) rng: Box<dyn RandomSource>,
+    /// Where the trace records go, disabled by default.
+    pub(crate) tracer: Tracer,
+    /// The hooks notified of fetches, memory accesses, draws and other events, if any.
+    pub(crate) observer: Option<Box<dyn Chip8Observer>>,
+    /// Where programs are loaded and execution starts after a reset, 0x200 by default.
+    pub(crate) load_address: u16,
+    /// The platform the CPU was created for, None unless it came from `with_platform`.
+    pub(crate) platform: Option<Platform>,
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPU {
     pub fn new() -> CPU {
+        CPU::with_quirks(Quirks::default())
+    }
+
+    /// Creates a new CPU that resolves ambiguous opcodes using the given quirks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::cpu::CPU;
+    /// use chip8::quirks::{Platform, Quirks};
+    ///
+    /// let cpu = CPU::with_quirks(Quirks::for_platform(Platform::SuperChip));
+    /// assert!(cpu.quirks.clip_sprites);
+    /// ```
+    pub fn with_quirks(quirks: Quirks) -> CPU {
         CPU {
             memory: Memory::new(),
             gpu: GPU::new(),
             key_state: 0x0000,
             halt: false,
+            quirks,
             waiting_for_key: None,
+            vblank_ready: true,
+            rng: Box::new(ThreadRandom),
+            tracer: Tracer::disabled(),
+            observer: None,
+            load_address: 0x200,
+            platform: None,
         }
     }
+
+    /// Creates a new CPU emulating the given platform, with its quirks and memory size.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::cpu::CPU;
+    /// use chip8::memory::XO_MEM_SIZE;
+    /// use chip8::quirks::Platform;
+    ///
+    /// let cpu = CPU::with_platform(Platform::XoChip);
+    /// assert_eq!(cpu.memory.memory.len(), XO_MEM_SIZE);
+    /// ```
+    pub fn with_platform(platform: Platform) -> CPU {
+        let mut cpu = CPU::with_quirks(Quirks::for_platform(platform));
+        cpu.memory = Memory::with_size(platform.memory_size());
+        cpu.platform = Some(platform);
+        cpu
+    }
+
     pub fn get_gpu(&mut self) -> &mut GPU {
         &mut self.gpu
     }
     /// Executes the given opcode on the CPU.
-    pub fn execute(&mut self, raw_opcode: u16) {
-        let (opcode, reg_x, reg_y) = parse_opcode(raw_opcode);
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Chip8Error` if the opcode is unknown or accesses memory or the stack
+    /// out of bounds. The program counter is left on the faulting instruction.
+    pub fn execute(&mut self, raw_opcode: u16) -> Result<(), Chip8Error> {
+        let instruction = decode(raw_opcode);
+        let reg_x = ((raw_opcode & 0x0F00) >> 8) as u8;
+        let reg_y = ((raw_opcode & 0x00F0) >> 4) as u8;
+        let pc = self.memory.pc;
+        let fault = |error: MemoryError| error.at(pc, raw_opcode);
 
         let val_x = self.memory.read_reg(reg_x);
         let val_y = self.memory.read_reg(reg_y);
+        self.tracer.log(
+            Level::Trace,
+            Category::Decode,
+            format_args!("{:04X} {:04X} {:?}", pc, raw_opcode, instruction),
+        );
 
-        match opcode {
-            Opcode::Halt => {
+        match instruction {
+            Instruction::Halt => {
                 self.halt = true;
-                println!("Halt");
-                return;
+                self.tracer
+                    .log(Level::Info, Category::Execute, format_args!("halted by 0000 at 0x{:04X}", pc));
+                return Ok(());
+            }
+            Instruction::ClearScreen => self.gpu.clear(),
+            Instruction::Return => {
+                let addr = self.memory.pop_stack().map_err(fault)?;
+                let depth = self.memory.sp;
+                self.notify(|observer| observer.on_stack_pop(addr, depth));
+                self.memory.pc = addr;
+                return Ok(());
             }
-            Opcode::ClearScreen => self.gpu.reset(),
-            Opcode::Return => {
-                self.memory.pc = self.memory.pop_stack();
-                return;
+            Instruction::ScrollDown { n: rows } => self.gpu.scroll_down(rows.into()),
+            Instruction::ScrollUp { n: rows } => self.gpu.scroll_up(rows.into()),
+            Instruction::ScrollRight => self.gpu.scroll_right(),
+            Instruction::ScrollLeft => self.gpu.scroll_left(),
+            Instruction::Exit => {
+                self.halt = true;
+                self.tracer
+                    .log(Level::Info, Category::Execute, format_args!("exited at 0x{:04X}", pc));
+                return Ok(());
             }
-            Opcode::JumpToAddress(addr) => {
+            Instruction::LowRes => self.gpu.set_hires(false),
+            Instruction::HighRes => self.gpu.set_hires(true),
+            Instruction::JumpToAddress { nnn: addr } => {
                 self.memory.pc = addr;
-                return;
+                return Ok(());
             }
-            Opcode::CallAddress(addr) => {
-                self.memory.push_stack(self.memory.pc.wrapping_add(2));
+            Instruction::CallAddress { nnn: addr } => {
+                let return_addr = self.memory.pc.wrapping_add(2);
+                self.memory.push_stack(return_addr).map_err(fault)?;
+                let depth = self.memory.sp;
+                self.notify(|observer| observer.on_stack_push(return_addr, depth));
                 self.memory.pc = addr;
-                return;
+                return Ok(());
             }
-            Opcode::SkipIfRegEqualsByte(k) => {
+            Instruction::SkipIfRegEqualsByte { kk: k, .. } => {
                 if val_x == k {
-                    self.increment()
+                    self.skip()
                 }
             }
-            Opcode::SkipIfRegNotEqualsByte(k) => {
-                println!("V{:X} = 0x{:02X}\t k = {:02X}", reg_x, val_x, k);
+            Instruction::SkipIfRegNotEqualsByte { kk: k, .. } => {
                 if val_x != k {
-                    println!("Skipping");
-                    self.increment()
+                    self.skip()
                 }
             }
-            Opcode::SkipIfRegEqualsReg => {
+            Instruction::SkipIfRegEqualsReg { .. } => {
                 if val_x == val_y {
-                    self.increment()
+                    self.skip()
                 }
             }
-            Opcode::LoadByteIntoReg(k) => self.memory.write_reg(reg_x, k),
-            Opcode::AddByteToReg(k) => self.memory.write_reg(reg_x, val_x.wrapping_add(k)),
-            Opcode::LoadRegIntoReg => self.memory.write_reg(reg_x, val_y),
-            Opcode::OrRegWithReg => self.memory.write_reg(reg_x, val_x | val_y),
-            Opcode::AndRegWithReg => self.memory.write_reg(reg_x, val_x & val_y),
-            Opcode::XorRegWithReg => self.memory.write_reg(reg_x, val_x ^ val_y),
-            Opcode::AddRegToReg => {
-                let sum = self.add_overflow(val_x, val_y);
+            Instruction::StoreRegRangeIntoMem { .. } => {
+                /* SAVE Vx - Vy instruction */
+                for (offset, reg) in Self::reg_range(reg_x, reg_y).enumerate() {
+                    self.write_byte(self.memory.i.wrapping_add(offset as u16), self.memory.read_reg(reg))
+                        .map_err(fault)?;
+                }
+                self.trace_store(self.memory.i, Self::reg_range(reg_x, reg_y).count());
+            }
+            Instruction::LoadRegRangeFromMem { .. } => {
+                /* LOAD Vx - Vy instruction */
+                for (offset, reg) in Self::reg_range(reg_x, reg_y).enumerate() {
+                    let val = self.read_byte(self.memory.i.wrapping_add(offset as u16)).map_err(fault)?;
+                    self.memory.write_reg(reg, val);
+                }
+            }
+            Instruction::LoadByteIntoReg { kk: k, .. } => self.memory.write_reg(reg_x, k),
+            Instruction::AddByteToReg { kk: k, .. } => self.memory.write_reg(reg_x, val_x.wrapping_add(k)),
+            Instruction::LoadRegIntoReg { .. } => self.memory.write_reg(reg_x, val_y),
+            Instruction::OrRegWithReg { .. } => self.logic_op(reg_x, val_x | val_y),
+            Instruction::AndRegWithReg { .. } => self.logic_op(reg_x, val_x & val_y),
+            Instruction::XorRegWithReg { .. } => self.logic_op(reg_x, val_x ^ val_y),
+            Instruction::AddRegToReg { .. } => {
+                let (sum, overflow) = val_x.overflowing_add(val_y);
                 self.memory.write_reg(reg_x, sum);
+                self.handle_add_overflow(overflow);
             }
-            Opcode::SubtractRegFromReg => {
-                self.check_borrow(val_x, val_y);
+            Instruction::SubtractRegFromReg { .. } => {
                 let diff = self.perform_subtraction(val_x, val_y);
                 self.memory.write_reg(reg_x, diff);
+                self.check_borrow(val_x, val_y);
             }
 
-            Opcode::ShiftRight => {
+            Instruction::ShiftRight { .. } => {
                 /* SHR Vx {, Vy} instruction */
-                let x_ls_bit = val_x & 0x1;
-                self.memory.write_reg(0xF, x_ls_bit); /* Set VF to the least significant bit of Vx */
-                self.memory.write_reg(reg_x, val_x.wrapping_shr(1)); /* Perform a right shift on Vx */
+                let source = if self.quirks.shift_uses_vy { val_y } else { val_x };
+                let ls_bit = source & 0x1;
+                self.memory.write_reg(reg_x, source.wrapping_shr(1)); /* Perform a right shift on the source */
+                self.memory.write_reg(0xF, ls_bit); /* Set VF to the least significant bit of the source, last so it wins when Vx is VF */
             }
 
-            Opcode::SubstractRegFromOtherReg => {
-                self.check_borrow(val_y, val_x);
+            Instruction::SubtractRegFromOtherReg { .. } => {
                 let diff = self.perform_subtraction(val_y, val_x);
                 self.memory.write_reg(reg_x, diff);
+                self.check_borrow(val_y, val_x);
             }
 
-            Opcode::ShiftLeft => {
+            Instruction::ShiftLeft { .. } => {
                 /* SHL Vx {, Vy} instruction */
-                let x_ms_bit = (val_x & 0x80) >> 7;
-                self.memory.write_reg(0xF, x_ms_bit); /* Set VF to the most significant bit of Vx */
-                self.memory.write_reg(reg_x, val_x.wrapping_shl(1)); /* DIV the value of Vx by 2 and write the result to Vx */
+                let source = if self.quirks.shift_uses_vy { val_y } else { val_x };
+                let ms_bit = (source & 0x80) >> 7;
+                self.memory.write_reg(reg_x, source.wrapping_shl(1)); /* Multiply the source by 2 and write the result to Vx */
+                self.memory.write_reg(0xF, ms_bit); /* Set VF to the most significant bit of the source, last so it wins when Vx is VF */
             }
-            Opcode::SkipIfRegNotEqualsReg => {
+            Instruction::SkipIfRegNotEqualsReg { .. } => {
                 if val_x != val_y {
-                    self.increment()
+                    self.skip()
                 }
             }
-            Opcode::LoadIndex(addr) => self.memory.i = addr,
-            Opcode::JumpToAddressPlusV0(addr) => {
-                let v0 = self.memory.read_reg(0) as u16;
-                self.memory.pc = addr.wrapping_add(v0);
+            Instruction::LoadIndex { nnn: addr } => self.memory.i = addr,
+            Instruction::LoadLongIndex => {
+                /* LD I, NNNN instruction, the address is the following word */
+                // Read it before moving the PC, so that a fault leaves the PC on this instruction
+                let operand = self.memory.pc.wrapping_add(2);
+                let high = self.memory.load(operand).map_err(fault)?;
+                let low = self.memory.load(operand.wrapping_add(1)).map_err(fault)?;
+                self.memory.i = (high as u16) << 8 | low as u16;
+                self.increment();
+            }
+            Instruction::JumpToAddressPlusV0 { nnn: addr } => {
+                let offset_reg = if self.quirks.jump_uses_vx { reg_x } else { 0 };
+                let offset = self.memory.read_reg(offset_reg) as u16;
+                self.memory.pc = addr.wrapping_add(offset);
+                return Ok(());
             }
-            Opcode::RandomByte(k) => {
-                let mut rng = rand::thread_rng();
-                let rand_u8 = rng.gen::<u8>();
+            Instruction::RandomByte { kk: k, .. } => {
+                let rand_u8 = self.rng.next_byte();
                 self.memory.write_reg(reg_x, rand_u8 & k);
             }
-            Opcode::DrawSprite(nibble) => {
+            Instruction::DrawSprite { n: nibble, .. } => {
                 /* DRW Vx, Vy, nibble instruction */
+                if self.quirks.display_wait {
+                    if !self.vblank_ready {
+                        // Repeat this instruction until the next vertical blank
+                        return Ok(());
+                    }
+                    self.vblank_ready = false;
+                }
                 let coords: Coordinate = (val_x.into(), val_y.into());
-                let sprite = self.memory.load_sprite(nibble.into());
-                self.memory
-                    .write_reg(0xF, self.gpu.draw_sprite(coords, sprite));
+                let planes = self.gpu.selected_plane_count();
+                /* DRW Vx, Vy, 0 draws a SUPER-CHIP 16x16 sprite, which SUPER-CHIP 1.1 narrows to 8x16 in lores */
+                let large = nibble == 0 && (self.gpu.is_hires() || !self.quirks.lores_tall_sprites);
+                let height = if nibble == 0 { 16 } else { nibble };
+                let row_bytes = if large { 2 } else { 1 };
+                let sprite = self
+                    .memory
+                    .load_sprite(height as usize * row_bytes * planes)
+                    .map_err(fault)?;
+                if self.observer.is_some() {
+                    let i = self.memory.i;
+                    for (offset, &value) in sprite.iter().enumerate() {
+                        self.notify(|observer| observer.on_memory_read(i.wrapping_add(offset as u16), value));
+                    }
+                }
+                let collision = if large {
+                    self.gpu.draw_large_sprite(coords, sprite, self.quirks.clip_sprites)
+                } else {
+                    self.gpu.draw_sprite(coords, sprite, self.quirks.clip_sprites)
+                };
+                self.memory.write_reg(0xF, collision);
+                self.notify(|observer| observer.on_draw(val_x, val_y, height, collision != 0));
             }
-            Opcode::SkipIfKeyPressed => {
+            Instruction::SkipIfKeyPressed { .. } => {
                 /* SKP Vx instruction */
-                if self.is_key_pressed(val_x.into()) {
-                    self.increment();
+                if self.is_key_pressed((val_x & 0xF).into()) {
+                    self.skip();
                 }
             }
-            Opcode::SkipIfKeyNotPressed => {
+            Instruction::SkipIfKeyNotPressed { .. } => {
                 /* SKNP Vx instruction */
-                if !self.is_key_pressed(val_x.into()) {
-                    self.increment();
+                if !self.is_key_pressed((val_x & 0xF).into()) {
+                    self.skip();
                 }
             }
-            Opcode::LoadDelayTimerIntoReg => self.memory.write_reg(reg_x, self.memory.dt),
+            Instruction::LoadDelayTimerIntoReg { .. } => self.memory.write_reg(reg_x, self.memory.dt),
 
-            Opcode::LoadKeyIntoReg => {
+            Instruction::LoadKeyIntoReg { .. } => {
                 if self.key_state == 0 {
                     // No key is being pressed, so we wait for a key press
+                    if self.waiting_for_key.is_none() {
+                        self.tracer
+                            .log(Level::Debug, Category::Input, format_args!("waiting for a key into V{:X}", reg_x));
+                        self.notify(|observer| observer.on_key_wait(reg_x));
+                    }
                     self.waiting_for_key = Some(reg_x);
                     self.memory.pc = self.memory.pc.wrapping_sub(2); // Decrement PC to repeat this instruction until a key is pressed
                 } else {
@@ -189,55 +347,318 @@ impl CPU {
                     }
                 }
             }
-            Opcode::LoadRegIntoDelayTimer => self.memory.dt = val_x,
-            Opcode::LoadRegIntoSoundTimer => self.memory.st = val_x,
-            Opcode::AddRegToIndex => self.memory.i = self.memory.i.wrapping_add(val_x as u16),
-            Opcode::LoadFontIntoReg => self.memory.i = (val_x * 5) as u16,
-            Opcode::LoadBCDIntoMem => {
+            Instruction::LoadRegIntoDelayTimer { .. } => self.memory.dt = val_x,
+            Instruction::LoadRegIntoSoundTimer { .. } => {
+                let previous = self.memory.st;
+                self.memory.st = val_x;
+                self.notify_sound(previous);
+            }
+            Instruction::AddRegToIndex { .. } => self.memory.i = self.memory.i.wrapping_add(val_x as u16),
+            Instruction::LoadFontIntoReg { .. } => self.memory.i = FONT_ADDR + (val_x & 0xF) as u16 * 5,
+            Instruction::LoadBigFontIntoReg { .. } => self.memory.i = BIG_FONT_ADDR + (val_x & 0xF) as u16 * 10,
+            Instruction::LoadBCDIntoMem { .. } => {
                 let i = self.memory.i;
                 let bcd_representation = val_x.to_bcd();
-                self.memory.store(i, bcd_representation.0);
-                self.memory.store(i + 1, bcd_representation.1);
-                self.memory.store(i + 2, bcd_representation.2);
-            }
-            Opcode::StoreRegsIntoMem => {
-                for offset in 0..0x10 {
-                    self.memory.store(
-                        self.memory.i.wrapping_add(offset as u16),
-                        self.memory.read_reg(offset as u8),
-                    );
+                self.write_byte(i, bcd_representation.0).map_err(fault)?;
+                self.write_byte(i.wrapping_add(1), bcd_representation.1).map_err(fault)?;
+                self.write_byte(i.wrapping_add(2), bcd_representation.2).map_err(fault)?;
+                self.trace_store(i, 3);
+            }
+            Instruction::StoreRegsIntoMem { .. } => {
+                for offset in 0..=reg_x {
+                    self.write_byte(self.memory.i.wrapping_add(offset as u16), self.memory.read_reg(offset))
+                        .map_err(fault)?;
                 }
+                self.trace_store(self.memory.i, reg_x as usize + 1);
+                self.increment_index(reg_x);
             }
-            Opcode::LoadRegsFromMem => {
-                for offset in 0..0x10 {
-                    self.memory.write_reg(
-                        offset,
-                        self.memory.load(self.memory.i.wrapping_add(offset as u16)),
-                    );
+            Instruction::LoadRegsFromMem { .. } => {
+                for offset in 0..=reg_x {
+                    let val = self.read_byte(self.memory.i.wrapping_add(offset as u16)).map_err(fault)?;
+                    self.memory.write_reg(offset, val);
                 }
+                self.increment_index(reg_x);
             }
-            _ => {
-                println!("Unknown opcode: {:04X}", raw_opcode);
-                self.halt = true;
+            Instruction::StoreRplFlags { .. } => {
+                let count = reg_x as usize + 1;
+                self.memory.rpl[..count].copy_from_slice(&self.memory.v[..count]);
+            }
+            Instruction::LoadRplFlags { .. } => {
+                let count = reg_x as usize + 1;
+                self.memory.v[..count].copy_from_slice(&self.memory.rpl[..count]);
+            }
+            Instruction::SelectPlanes { .. } => self.gpu.select_planes(reg_x),
+            Instruction::LoadAudioPattern => {
+                for offset in 0..AUDIO_PATTERN_SIZE {
+                    self.memory.audio_pattern[offset] =
+                        self.read_byte(self.memory.i.wrapping_add(offset as u16)).map_err(fault)?;
+                }
+                self.memory.audio_pattern_loaded = true;
+            }
+            Instruction::LoadRegIntoPitch { .. } => self.memory.pitch = val_x,
+            Instruction::Unknown(_) => {
+                return Err(Chip8Error::UnknownOpcode {
+                    pc,
+                    opcode: raw_opcode,
+                })
             }
         }
         self.increment();
+        Ok(())
+    }
 
-        self.print_registers()
+    /// Reads a byte of memory for an instruction, notifying the observer.
+    fn read_byte(&mut self, addr: u16) -> Result<u8, MemoryError> {
+        let value = self.memory.load(addr)?;
+        self.notify(|observer| observer.on_memory_read(addr, value));
+        Ok(value)
+    }
+
+    /// Writes a byte of memory for an instruction, notifying the observer.
+    fn write_byte(&mut self, addr: u16, value: u8) -> Result<(), MemoryError> {
+        self.memory.store(addr, value)?;
+        self.notify(|observer| observer.on_memory_write(addr, value));
+        Ok(())
+    }
+
+    /// Calls the observer, if one is installed.
+    #[inline]
+    fn notify(&mut self, event: impl FnOnce(&mut dyn Chip8Observer)) {
+        if let Some(observer) = self.observer.as_deref_mut() {
+            event(observer);
+        }
+    }
+
+    /// Notifies the observer if the buzzer started or stopped since the sound timer was `previous`.
+    fn notify_sound(&mut self, previous: u8) {
+        match (previous > 0, self.memory.st > 0) {
+            (false, true) => self.notify(|observer| observer.on_sound_start()),
+            (true, false) => self.notify(|observer| observer.on_sound_stop()),
+            _ => {}
+        }
     }
 
     fn increment(&mut self) {
         self.memory.pc = self.memory.pc.wrapping_add(2);
     }
 
-    fn update_timers(&mut self) {
+    /// Skips the next instruction, including both words of an XO-CHIP `F000 NNNN`.
+    fn skip(&mut self) {
+        self.increment();
+        if self.memory.read_instr() == Ok(0xF000) {
+            self.increment();
+        }
+    }
+
+    /// Returns the registers from Vx to Vy, in descending order if x is greater than y.
+    fn reg_range(reg_x: u8, reg_y: u8) -> impl Iterator<Item = u8> {
+        (0..=reg_x.abs_diff(reg_y)).map(move |offset| {
+            if reg_x <= reg_y {
+                reg_x + offset
+            } else {
+                reg_x - offset
+            }
+        })
+    }
+
+    /// Advances I after `Fx55`/`Fx65` according to the `index_increment` quirk.
+    fn increment_index(&mut self, reg_x: u8) {
+        let step = match self.quirks.index_increment {
+            IndexIncrement::None => return,
+            IndexIncrement::ByX => reg_x as u16,
+            IndexIncrement::ByXPlusOne => reg_x as u16 + 1,
+        };
+        self.memory.i = self.memory.i.wrapping_add(step);
+    }
+
+    /// Writes the result of `8xy1`/`8xy2`/`8xy3`, resetting VF if the `logic_resets_vf` quirk is set.
+    fn logic_op(&mut self, reg_x: u8, result: u8) {
+        self.memory.write_reg(reg_x, result);
+        if self.quirks.logic_resets_vf {
+            self.memory.write_reg(0xF, 0);
+        }
+    }
+
+    /// Returns whether the buzzer is sounding, which is while the sound timer is non-zero.
+    pub fn buzzer_active(&self) -> bool {
+        self.memory.st > 0
+    }
+
+    /// Returns what the buzzer should be playing, to be passed to a `ToneGenerator`.
+    pub fn sound_state(&self) -> SoundState {
+        SoundState {
+            active: self.buzzer_active(),
+            pattern: if self.memory.audio_pattern_loaded {
+                Some(self.memory.audio_pattern)
+            } else {
+                None
+            },
+            pitch: self.memory.pitch,
+        }
+    }
+
+    /// Signals a vertical blank, allowing a `Dxyn` waiting on the `display_wait` quirk to draw.
+    ///
+    /// Frontends should call this once per displayed frame.
+    pub fn vblank(&mut self) {
+        self.vblank_ready = true;
+    }
+
+    /// Decrements the delay and sound timers.
+    ///
+    /// The timers count down at `TIMER_HZ` regardless of the CPU clock, so frontends
+    /// that do not use `run_frame` should call this 60 times per second.
+    pub fn tick_timers(&mut self) {
+        let previous = self.memory.st;
         self.memory.update_timers();
+        self.notify_sound(previous);
+        self.rng.interrupt();
+    }
+
+    /// Replaces the source of the `Cxkk` random bytes, which is `ThreadRandom` by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::cpu::CPU;
+    /// use chip8::rng::SeededRandom;
+    ///
+    /// let mut cpu = CPU::new();
+    /// cpu.set_rng(Box::new(SeededRandom::new(42)));
+    /// ```
+    pub fn set_rng(&mut self, rng: Box<dyn RandomSource>) {
+        self.rng = rng;
+    }
+
+    /// Returns the source of the `Cxkk` random bytes.
+    pub fn rng(&self) -> &dyn RandomSource {
+        self.rng.as_ref()
+    }
+
+    /// Fetches and executes the instruction at the program counter.
+    ///
+    /// The timers are not updated, see `tick_timers` and `run_frame`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Chip8Error` if the instruction cannot be fetched or executed.
+    pub fn cycle(&mut self) -> Result<(), Chip8Error> {
+        let pc = self.memory.pc;
+        let opcode = self.memory.read_instr();
+        if let Ok(opcode) = opcode {
+            self.notify(|observer| observer.on_fetch(pc, opcode));
+        }
+        let result = match opcode {
+            Ok(opcode) if self.tracer.enabled(Level::Trace, Category::Execute) => {
+                let before = trace::RegisterSnapshot::capture(&self.memory);
+                let result = self.execute(opcode);
+                let line = trace::instruction_line(pc, opcode, &before, &self.memory);
+                self.tracer.log(Level::Trace, Category::Execute, format_args!("{}", line));
+                result
+            }
+            Ok(opcode) => self.execute(opcode),
+            Err(_) => Err(Chip8Error::PcOutOfBounds { pc }),
+        };
+        match (&result, opcode) {
+            (Ok(()), Ok(opcode)) => self.notify(|observer| observer.on_retire(pc, opcode)),
+            (Err(e), _) => self.tracer.log(Level::Error, Category::Execute, format_args!("{}", e)),
+            _ => {}
+        }
+        result
+    }
+
+    /// Traces a write of `len` bytes starting at `addr`.
+    fn trace_store(&mut self, addr: u16, len: usize) {
+        self.tracer.log(
+            Level::Trace,
+            Category::Memory,
+            format_args!("write {} bytes at 0x{:04X}", len, addr),
+        );
+    }
+
+    /// Replaces the tracer, which is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::cpu::CPU;
+    /// use chip8::trace::{Categories, Level, RingBufferSink, Tracer};
+    ///
+    /// let ring = RingBufferSink::new(100);
+    /// let mut cpu = CPU::new();
+    /// cpu.set_tracer(Tracer::new(Box::new(ring.clone()), Level::Trace, Categories::ALL));
+    /// cpu.load_program(&[0x60, 0x2A]).unwrap();
+    /// cpu.cycle().unwrap();
+    /// assert_eq!(ring.lines().last().unwrap(), "[execute] 0200 602A LD V0, 0x2A        V0=2A");
+    /// ```
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = tracer;
     }
 
-    pub fn cycle(&mut self) {
-        self.execute(self.memory.read_instr());
-        self.update_timers();
-        //self.poll();
+    /// Returns the tracer, for frontends to log their own records or flush it.
+    pub fn tracer_mut(&mut self) -> &mut Tracer {
+        &mut self.tracer
+    }
+
+    /// Installs an observer, replacing the previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use chip8::cpu::CPU;
+    /// use chip8::observer::Chip8Observer;
+    ///
+    /// struct Writes(Arc<Mutex<Vec<u16>>>);
+    ///
+    /// impl Chip8Observer for Writes {
+    ///     fn on_memory_write(&mut self, addr: u16, _value: u8) {
+    ///         self.0.lock().unwrap().push(addr);
+    ///     }
+    /// }
+    ///
+    /// let writes = Arc::new(Mutex::new(Vec::new()));
+    /// let mut cpu = CPU::new();
+    /// cpu.set_observer(Box::new(Writes(writes.clone())));
+    /// cpu.load_program(&[0xA3, 0x00, 0xF0, 0x33]).unwrap(); /* LD I, 0x300; LD B, V0 */
+    /// cpu.cycle().unwrap();
+    /// cpu.cycle().unwrap();
+    /// assert_eq!(*writes.lock().unwrap(), vec![0x300, 0x301, 0x302]);
+    /// ```
+    pub fn set_observer(&mut self, observer: Box<dyn Chip8Observer>) {
+        self.observer = Some(observer);
+    }
+
+    /// Removes the observer and returns it.
+    pub fn take_observer(&mut self) -> Option<Box<dyn Chip8Observer>> {
+        self.observer.take()
+    }
+
+    /// Runs one 60 Hz frame: up to `cycles_per_frame` instructions, then a timer tick
+    /// and a vertical blank.
+    ///
+    /// Nothing happens while the CPU is halted, so pausing also stops the timers.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles_per_frame` - The number of instructions to execute, the clock speed divided by `TIMER_HZ`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Chip8Error` if an instruction faults, in which case the rest of the frame is skipped.
+    pub fn run_frame(&mut self, cycles_per_frame: u32) -> Result<(), Chip8Error> {
+        if self.halt {
+            return Ok(());
+        }
+        for _ in 0..cycles_per_frame {
+            if self.halt {
+                break;
+            }
+            self.cycle()?;
+        }
+        self.tick_timers();
+        self.vblank();
+        Ok(())
     }
 
     /// Prints the values of all the CPU registers.
@@ -273,17 +694,20 @@ impl CPU {
     }
 
     /// Prints the memory region from `start` to `end` (inclusive) with the specified number of tabs.
+    ///
+    /// Addresses past the end of the memory are not printed.
     pub fn print_memory_region(&self, start: u16, end: u16, tabs_count: usize) {
+        let end = (end as usize).min(self.memory.memory.len());
         let mut tabs = 0;
-        for i in start..end {
+        for i in start as usize..end {
             if tabs == 0 {
                 print!("0x{:04X}: ", i);
             }
             if tabs < tabs_count - 1 {
-                print!("{:02X} ", self.memory.load(i));
+                print!("{:02X} ", self.memory.memory[i]);
                 tabs += 1;
             } else {
-                println!("{:02X}", self.memory.load(i));
+                println!("{:02X}", self.memory.memory[i]);
                 tabs = 0;
             }
         }
@@ -296,11 +720,20 @@ impl CPU {
             self.memory.write_reg(reg, index as u8);
             self.waiting_for_key = None;
         }
-        println!("key_state: {:04X}", self.key_state);
+        self.tracer.log(
+            Level::Debug,
+            Category::Input,
+            format_args!("key {:X} pressed, key_state: {:04X}", index, self.key_state),
+        );
     }
 
     pub fn key_released(&mut self, index: usize) {
         self.key_state &= !(1 << index);
+        self.tracer.log(
+            Level::Debug,
+            Category::Input,
+            format_args!("key {:X} released, key_state: {:04X}", index, self.key_state),
+        );
     }
 
     pub fn is_key_pressed(&self, index: usize) -> bool {
@@ -308,20 +741,18 @@ impl CPU {
     }
 
     pub fn reset(&mut self) {
-        println!("Resetting CPU");
+        self.tracer.log(Level::Info, Category::Execute, format_args!("reset"));
         self.memory.reset();
+        self.memory.pc = self.load_address;
         self.gpu.reset();
+        self.key_state = 0;
+        self.waiting_for_key = None;
         self.halt = false;
+        self.vblank_ready = true;
     }
 
-    fn handle_add_overflow(&mut self, result: u8, overflow: bool) -> u8 {
+    fn handle_add_overflow(&mut self, overflow: bool) {
         self.memory.write_reg(0xF, if overflow { 1 } else { 0 });
-        result
-    }
-
-    fn add_overflow(&mut self, x: u8, y: u8) -> u8 {
-        let (sum, overflow) = x.overflowing_add(y);
-        self.handle_add_overflow(sum, overflow)
     }
 
     fn check_borrow(&mut self, val1: u8, val2: u8) {
@@ -338,11 +769,69 @@ impl CPU {
         val1.wrapping_sub(val2)
     }
 
-    pub fn load_program(&mut self, program: &[u8]) {
-        println!("Loading program");
-        for i in 0..program.len() {
-            self.memory.store((i + 0x200) as u16, program[i]);
+    /// Loads a program into memory at the load address, 0x200 unless `load_program_at` changed it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::ProgramTooLarge` if the program does not fit in memory.
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), Chip8Error> {
+        self.copy_program(program, self.load_address)
+    }
+
+    fn copy_program(&mut self, program: &[u8], addr: u16) -> Result<(), Chip8Error> {
+        let start = addr as usize;
+        let max = self.memory.memory.len().saturating_sub(start);
+        if program.len() > max {
+            return Err(Chip8Error::ProgramTooLarge {
+                size: program.len(),
+                max,
+            });
         }
-        self.print_memory_region(0x200, 0x400, 16);
+        self.memory.memory[start..start + program.len()].copy_from_slice(program);
+        self.tracer.log(
+            Level::Info,
+            Category::Memory,
+            format_args!("loaded {} bytes at 0x{:04X}", program.len(), start),
+        );
+        Ok(())
+    }
+
+    /// Loads a program at another address than 0x200 and jumps to it, such as 0x600 for
+    /// ETI 660 programs.
+    ///
+    /// The address is kept, so that `reset` and later loads use it too.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::ProgramTooLarge` if the program does not fit in memory after the address,
+    /// in which case the load address and the PC are left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::cpu::CPU;
+    ///
+    /// let mut cpu = CPU::new();
+    /// cpu.load_program_at(&[0x60, 0x2A], 0x600).unwrap();
+    /// cpu.cycle().unwrap();
+    /// assert_eq!(cpu.memory.v[0], 0x2A);
+    /// cpu.reset();
+    /// assert_eq!(cpu.memory.pc, 0x600);
+    /// ```
+    pub fn load_program_at(&mut self, program: &[u8], addr: u16) -> Result<(), Chip8Error> {
+        self.copy_program(program, addr)?;
+        self.load_address = addr;
+        self.memory.pc = addr;
+        Ok(())
+    }
+
+    /// Returns where programs are loaded and execution starts after a reset.
+    pub fn load_address(&self) -> u16 {
+        self.load_address
+    }
+
+    /// Returns the platform the CPU was created for with `with_platform`, if any.
+    pub fn platform(&self) -> Option<Platform> {
+        self.platform
     }
 }
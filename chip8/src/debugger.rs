@@ -0,0 +1,491 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt::{self, Write};
+
+use crate::cpu::CPU;
+use crate::error::Chip8Error;
+use crate::headless::register_dump;
+use crate::instructions::{decode, Instruction};
+
+/// The number of instructions `continue` runs before giving control back, so that a program
+/// stuck in a loop does not hang the debugger.
+pub const CONTINUE_LIMIT: u64 = 10_000_000;
+
+/// The number of instructions `list` shows by default.
+const LIST_LENGTH: u16 = 10;
+
+/// The number of bytes `x` shows by default.
+const EXAMINE_LENGTH: usize = 32;
+
+const HELP: &str = "\
+Execution:
+  s, step [n]          Execute n instructions (default 1)
+  n, next              Step over a CALL
+  finish, out          Run until the current subroutine returns
+  c, continue          Run until a breakpoint, halt or fault
+  u, until <addr>      Run until the PC reaches an address
+Breakpoints:
+  b, break <addr>      Set a breakpoint
+  d, delete [addr]     Delete a breakpoint, or all of them
+  bl, breakpoints      List the breakpoints
+Inspection:
+  r, regs              Show the registers, timers and stack
+  set <reg> <value>    Set V0-VF, I, PC, SP, DT or ST
+  x <addr> [len]       Examine memory
+  w <addr> <byte>...   Write memory
+  l, list [addr] [n]   Disassemble around the PC or an address
+  bt, stack            Show the call stack
+Machine:
+  key <k>, release <k> Press or release a keypad key
+  reset                Reset the CPU
+  h, help              Show this help
+  q, quit              Exit the debugger
+An empty line repeats the last command.";
+
+/// The reason execution stopped and control returned to the debugger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The requested number of instructions was executed.
+    Step,
+    /// The PC reached a breakpoint.
+    Breakpoint(u16),
+    /// The PC reached the target of `until`, `next` or `finish`.
+    Reached(u16),
+    /// The program halted.
+    Halted,
+    /// An `Fx0A` instruction is waiting for a key.
+    WaitingForKey,
+    /// The program faulted, the PC is left on the faulting instruction.
+    Fault(Chip8Error),
+    /// `CONTINUE_LIMIT` instructions were executed without stopping.
+    Limit,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Step => Ok(()),
+            StopReason::Breakpoint(addr) => write!(f, "Breakpoint at 0x{:04X}", addr),
+            StopReason::Reached(addr) => write!(f, "Stopped at 0x{:04X}", addr),
+            StopReason::Halted => write!(f, "Program halted"),
+            StopReason::WaitingForKey => write!(f, "Waiting for a key, press one with 'key <k>'"),
+            StopReason::Fault(e) => write!(f, "Fault: {}", e),
+            StopReason::Limit => write!(f, "Still running after {} instructions", CONTINUE_LIMIT),
+        }
+    }
+}
+
+/// A command that could not be executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandError {
+    pub message: String,
+}
+
+impl CommandError {
+    fn new(message: impl Into<String>) -> Self {
+        CommandError { message: message.into() }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CommandError {}
+
+/// What the frontend should do after a command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reply {
+    /// Print the text and read the next command.
+    Output(String),
+    /// Exit the debugger.
+    Quit,
+}
+
+/// An interactive debugger driving a `CPU` one instruction at a time.
+///
+/// Commands are given as text lines to `execute`, so the same debugger can be driven from a
+/// terminal REPL or from tests. The timers tick every `cycles_per_frame` instructions, as they
+/// would at full speed.
+pub struct Debugger {
+    /// The addresses execution stops at.
+    breakpoints: BTreeSet<u16>,
+    /// The number of instructions per 60 Hz frame.
+    cycles_per_frame: u32,
+    /// The number of instructions executed in the current frame.
+    frame_cycles: u32,
+    /// The command repeated by an empty line.
+    last_command: Option<String>,
+}
+
+impl Debugger {
+    /// Creates a debugger with no breakpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles_per_frame` - The number of instructions between two timer ticks.
+    pub fn new(cycles_per_frame: u32) -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            cycles_per_frame: cycles_per_frame.max(1),
+            frame_cycles: 0,
+            last_command: None,
+        }
+    }
+
+    /// Returns the addresses of the breakpoints, in ascending order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Sets a breakpoint, returning false if there already was one at the address.
+    pub fn add_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    /// Deletes a breakpoint, returning false if there was none at the address.
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Executes one instruction, ticking the timers at the end of each frame.
+    fn cycle(&mut self, cpu: &mut CPU) -> Option<StopReason> {
+        if cpu.halt {
+            return Some(StopReason::Halted);
+        }
+        if let Err(e) = cpu.cycle() {
+            return Some(StopReason::Fault(e));
+        }
+        self.frame_cycles += 1;
+        if self.frame_cycles >= self.cycles_per_frame {
+            self.frame_cycles = 0;
+            cpu.tick_timers();
+            cpu.vblank();
+        }
+        if cpu.halt {
+            Some(StopReason::Halted)
+        } else if cpu.waiting_for_key.is_some() {
+            Some(StopReason::WaitingForKey)
+        } else {
+            None
+        }
+    }
+
+    /// Runs until `done` returns true after an instruction, a breakpoint is reached or
+    /// `limit` instructions were executed.
+    ///
+    /// The instruction at the PC is always executed, even if it has a breakpoint, so that
+    /// execution can resume from one.
+    fn run_until<F>(&mut self, cpu: &mut CPU, limit: u64, done: F) -> StopReason
+    where
+        F: Fn(&CPU) -> Option<StopReason>,
+    {
+        for _ in 0..limit {
+            if let Some(reason) = self.cycle(cpu) {
+                return reason;
+            }
+            if let Some(reason) = done(cpu) {
+                return reason;
+            }
+            if self.breakpoints.contains(&cpu.memory.pc) {
+                return StopReason::Breakpoint(cpu.memory.pc);
+            }
+        }
+        StopReason::Limit
+    }
+
+    /// Executes `count` instructions, stopping early at breakpoints.
+    pub fn step(&mut self, cpu: &mut CPU, count: u64) -> StopReason {
+        match self.run_until(cpu, count, |_| None) {
+            StopReason::Limit => StopReason::Step,
+            reason => reason,
+        }
+    }
+
+    /// Executes one instruction, running a `CALL` until it returns.
+    pub fn step_over(&mut self, cpu: &mut CPU) -> StopReason {
+        let Some(Instruction::CallAddress { .. }) = self.instruction_at(cpu, cpu.memory.pc) else {
+            return self.step(cpu, 1);
+        };
+        let return_addr = cpu.memory.pc.wrapping_add(2);
+        let depth = cpu.memory.sp;
+        self.run_until(cpu, CONTINUE_LIMIT, |cpu| {
+            (cpu.memory.pc == return_addr && cpu.memory.sp <= depth).then_some(StopReason::Reached(return_addr))
+        })
+    }
+
+    /// Runs until the current subroutine returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CommandError` if the CPU is not in a subroutine.
+    pub fn step_out(&mut self, cpu: &mut CPU) -> Result<StopReason, CommandError> {
+        let depth = cpu.memory.sp;
+        if depth == 0 {
+            return Err(CommandError::new("not in a subroutine"));
+        }
+        Ok(self.run_until(cpu, CONTINUE_LIMIT, |cpu| {
+            (cpu.memory.sp < depth).then_some(StopReason::Reached(cpu.memory.pc))
+        }))
+    }
+
+    /// Runs until the PC reaches an address.
+    pub fn run_to(&mut self, cpu: &mut CPU, addr: u16) -> StopReason {
+        self.run_until(cpu, CONTINUE_LIMIT, |cpu| {
+            (cpu.memory.pc == addr).then_some(StopReason::Reached(addr))
+        })
+    }
+
+    /// Runs until a breakpoint, a halt or a fault.
+    pub fn resume(&mut self, cpu: &mut CPU) -> StopReason {
+        self.run_until(cpu, CONTINUE_LIMIT, |_| None)
+    }
+
+    /// Decodes the instruction at an address, None if it is past the end of the memory.
+    fn instruction_at(&self, cpu: &CPU, addr: u16) -> Option<Instruction> {
+        let memory = &cpu.memory.memory;
+        let high = *memory.get(addr as usize)?;
+        let low = *memory.get(addr as usize + 1)?;
+        Some(decode(u16::from_be_bytes([high, low])))
+    }
+
+    /// Disassembles `count` instructions from an address, marking the PC and the breakpoints.
+    pub fn list(&self, cpu: &CPU, start: u16, count: u16) -> String {
+        let mut text = String::new();
+        let mut addr = start;
+        for _ in 0..count {
+            let Some(instruction) = self.instruction_at(cpu, addr) else {
+                break;
+            };
+            let marker = if addr == cpu.memory.pc { "=>" } else { "  " };
+            let breakpoint = if self.breakpoints.contains(&addr) { '*' } else { ' ' };
+            let opcode = u16::from_be_bytes([cpu.memory.memory[addr as usize], cpu.memory.memory[addr as usize + 1]]);
+            writeln!(text, "{}{} 0x{:04X}: {:04X}  {}", marker, breakpoint, addr, opcode, instruction).unwrap();
+            addr = addr.wrapping_add(2);
+        }
+        text
+    }
+
+    /// Formats the call stack, innermost first.
+    pub fn backtrace(&self, cpu: &CPU) -> String {
+        let memory = &cpu.memory;
+        let mut text = format!("#0  0x{:04X}\n", memory.pc);
+        for (frame, depth) in (1..=memory.sp as usize).rev().enumerate() {
+            // The stack holds return addresses, the call is the instruction before
+            let ret = memory.stack[depth];
+            writeln!(text, "#{}  0x{:04X} (returns to 0x{:04X})", frame + 1, ret.wrapping_sub(2), ret).unwrap();
+        }
+        text
+    }
+
+    /// Executes a command line.
+    ///
+    /// An empty line repeats the previous command. See the `help` command for the syntax.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CommandError` if the command is unknown or its arguments are invalid.
+    pub fn execute(&mut self, cpu: &mut CPU, line: &str) -> Result<Reply, CommandError> {
+        let line = match line.trim() {
+            "" => match &self.last_command {
+                Some(last) => last.clone(),
+                None => return Ok(Reply::Output(String::new())),
+            },
+            line => line.to_string(),
+        };
+        self.last_command = Some(line.clone());
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let args = &words[1..];
+        let output = match words[0] {
+            "s" | "step" => {
+                let count = match args.first() {
+                    Some(count) => parse_number(count)?,
+                    None => 1,
+                };
+                let reason = self.step(cpu, count as u64);
+                self.stopped(cpu, reason)
+            }
+            "n" | "next" => {
+                let reason = self.step_over(cpu);
+                self.stopped(cpu, reason)
+            }
+            "finish" | "out" => {
+                let reason = self.step_out(cpu)?;
+                self.stopped(cpu, reason)
+            }
+            "c" | "continue" => {
+                let reason = self.resume(cpu);
+                self.stopped(cpu, reason)
+            }
+            "u" | "until" => {
+                let addr = parse_addr(args.first())?;
+                let reason = self.run_to(cpu, addr);
+                self.stopped(cpu, reason)
+            }
+            "b" | "break" => {
+                let addr = parse_addr(args.first())?;
+                if self.add_breakpoint(addr) {
+                    format!("Breakpoint set at 0x{:04X}\n", addr)
+                } else {
+                    format!("Breakpoint already set at 0x{:04X}\n", addr)
+                }
+            }
+            "d" | "delete" => match args.first() {
+                Some(addr) => {
+                    let addr = parse_addr(Some(addr))?;
+                    if !self.remove_breakpoint(addr) {
+                        return Err(CommandError::new(format!("no breakpoint at 0x{:04X}", addr)));
+                    }
+                    format!("Deleted breakpoint at 0x{:04X}\n", addr)
+                }
+                None => {
+                    self.breakpoints.clear();
+                    "Deleted all breakpoints\n".to_string()
+                }
+            },
+            "bl" | "breakpoints" => {
+                if self.breakpoints.is_empty() {
+                    "No breakpoints\n".to_string()
+                } else {
+                    self.breakpoints.iter().map(|addr| format!("0x{:04X}\n", addr)).collect()
+                }
+            }
+            "r" | "regs" | "registers" => register_dump(cpu),
+            "set" => {
+                let [register, value] = args else {
+                    return Err(CommandError::new("usage: set <reg> <value>"));
+                };
+                self.set_register(cpu, register, parse_number(value)?)?;
+                String::new()
+            }
+            "x" => {
+                let addr = parse_addr(args.first())?;
+                let len = match args.get(1) {
+                    Some(len) => parse_number(len)? as usize,
+                    None => EXAMINE_LENGTH,
+                };
+                examine(cpu, addr, len)
+            }
+            "w" => {
+                let addr = parse_addr(args.first())?;
+                if args.len() < 2 {
+                    return Err(CommandError::new("usage: w <addr> <byte>..."));
+                }
+                let bytes = args[1..]
+                    .iter()
+                    .map(|byte| parse_number(byte).and_then(|value| to_u8(value, "byte")))
+                    .collect::<Result<Vec<u8>, _>>()?;
+                let end = addr as usize + bytes.len();
+                let memory = &mut cpu.memory.memory;
+                if end > memory.len() {
+                    return Err(CommandError::new("write past the end of memory"));
+                }
+                memory[addr as usize..end].copy_from_slice(&bytes);
+                String::new()
+            }
+            "l" | "list" => {
+                let start = match args.first() {
+                    Some(addr) => parse_addr(Some(addr))?,
+                    // Half of the instructions before the PC, at 2 bytes each
+                    None => cpu.memory.pc.saturating_sub(LIST_LENGTH),
+                };
+                let count = match args.get(1) {
+                    Some(count) => parse_number(count)? as u16,
+                    None => LIST_LENGTH,
+                };
+                self.list(cpu, start, count)
+            }
+            "bt" | "stack" | "backtrace" => self.backtrace(cpu),
+            "key" | "release" => {
+                let key = parse_number(args.first().ok_or_else(|| CommandError::new("missing key"))?)?;
+                if key > 0xF {
+                    return Err(CommandError::new("keys go from 0 to F"));
+                }
+                if words[0] == "key" {
+                    cpu.key_pressed(key as usize);
+                } else {
+                    cpu.key_released(key as usize);
+                }
+                String::new()
+            }
+            "reset" => {
+                cpu.reset();
+                self.frame_cycles = 0;
+                String::new()
+            }
+            "h" | "help" => format!("{}\n", HELP),
+            "q" | "quit" => return Ok(Reply::Quit),
+            command => return Err(CommandError::new(format!("unknown command '{}', try 'help'", command))),
+        };
+        Ok(Reply::Output(output))
+    }
+
+    /// Formats the reason execution stopped, followed by the instruction at the PC.
+    fn stopped(&self, cpu: &CPU, reason: StopReason) -> String {
+        let mut text = reason.to_string();
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text + &self.list(cpu, cpu.memory.pc, 1)
+    }
+
+    /// Sets a register by name.
+    fn set_register(&self, cpu: &mut CPU, name: &str, value: u32) -> Result<(), CommandError> {
+        let memory = &mut cpu.memory;
+        match name.to_ascii_uppercase().as_str() {
+            "PC" => memory.pc = to_u16(value, "PC")?,
+            "I" => memory.i = to_u16(value, "I")?,
+            "SP" => memory.sp = to_u8(value, "SP")?,
+            "DT" => memory.dt = to_u8(value, "DT")?,
+            "ST" => memory.st = to_u8(value, "ST")?,
+            register => {
+                let index = register
+                    .strip_prefix('V')
+                    .filter(|digit| digit.len() == 1)
+                    .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+                    .ok_or_else(|| CommandError::new(format!("unknown register '{}'", name)))?;
+                memory.v[index as usize] = to_u8(value, register)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats `len` bytes of memory from an address, 16 per line.
+fn examine(cpu: &CPU, addr: u16, len: usize) -> String {
+    let memory = &cpu.memory.memory;
+    let start = (addr as usize).min(memory.len());
+    let end = (start + len).min(memory.len());
+    let mut text = String::new();
+    for (line, bytes) in memory[start..end].chunks(16).enumerate() {
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        writeln!(text, "0x{:04X}: {}", start + line * 16, hex.join(" ")).unwrap();
+    }
+    text
+}
+
+/// Parses a number in decimal, or in hexadecimal with a `0x` prefix.
+fn parse_number(value: &str) -> Result<u32, CommandError> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| CommandError::new(format!("invalid number '{}'", value)))
+}
+
+/// Parses a required address.
+fn parse_addr(value: Option<&&str>) -> Result<u16, CommandError> {
+    let value = value.ok_or_else(|| CommandError::new("missing address"))?;
+    to_u16(parse_number(value)?, "address")
+}
+
+fn to_u8(value: u32, what: &str) -> Result<u8, CommandError> {
+    u8::try_from(value).map_err(|_| CommandError::new(format!("{} must fit in a byte", what)))
+}
+
+fn to_u16(value: u32, what: &str) -> Result<u16, CommandError> {
+    u16::try_from(value).map_err(|_| CommandError::new(format!("{} must fit in 16 bits", what)))
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::instructions::{decode, Instruction};
+
+/// The number of data bytes emitted on a single `DB` line.
+const BYTES_PER_DATA_LINE: usize = 8;
+
+/// A single line of a disassembly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// An instruction reached by following the control flow.
+    Code {
+        /// The address of the instruction.
+        addr: u16,
+        /// The raw bytes of the instruction, four for `F000 NNNN` and two otherwise.
+        bytes: Vec<u8>,
+        /// The instruction in mnemonic syntax, with its target replaced by a label if it has one.
+        text: String,
+    },
+    /// Bytes never reached by the control flow, such as sprites.
+    Data {
+        /// The address of the first byte.
+        addr: u16,
+        /// The raw bytes.
+        bytes: Vec<u8>,
+    },
+}
+
+impl Line {
+    /// Returns the address of the first byte of the line.
+    pub fn addr(&self) -> u16 {
+        match self {
+            Line::Code { addr, .. } | Line::Data { addr, .. } => *addr,
+        }
+    }
+}
+
+/// An annotated disassembly of a ROM, with code separated from data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disassembly {
+    /// The address the ROM is loaded at.
+    pub origin: u16,
+    /// The lines of the disassembly, in address order.
+    pub lines: Vec<Line>,
+    /// The auto-generated labels, by address.
+    pub labels: BTreeMap<u16, String>,
+}
+
+/// What a byte of the ROM has been identified as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ByteKind {
+    Data,
+    InstructionStart,
+    InstructionContinuation,
+}
+
+/// The kinds of labels, in increasing order of precedence when an address has several.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum LabelKind {
+    Data,
+    Jump,
+    Subroutine,
+    Start,
+}
+
+impl LabelKind {
+    fn name(&self, addr: u16) -> String {
+        match self {
+            LabelKind::Data => format!("data_{:03X}", addr),
+            LabelKind::Jump => format!("label_{:03X}", addr),
+            LabelKind::Subroutine => format!("sub_{:03X}", addr),
+            LabelKind::Start => "start".to_string(),
+        }
+    }
+}
+
+/// Disassembles a ROM loaded at `origin`, following the control flow from it.
+///
+/// Jumps, calls and skips are followed recursively so that only reachable bytes are
+/// decoded as instructions, everything else is emitted as `DB` data. Every address
+/// targeted by a jump, call or `LD I` inside the ROM gets a label. A target inside an
+/// instruction, such as the operand of a self-modifying `LD I, LONG`, is written as an
+/// offset from the label of that instruction, since it does not start a line.
+///
+/// # Examples
+///
+/// ```rust
+/// use chip8::disasm::disassemble;
+///
+/// let rom = [0xA2, 0x04, 0x12, 0x02, 0xF0, 0x90];
+/// let listing = disassemble(&rom, 0x200).to_string();
+/// assert!(listing.contains("LD I, data_204"));
+/// assert!(listing.contains("DB 0xF0, 0x90"));
+/// ```
+pub fn disassemble(rom: &[u8], origin: u16) -> Disassembly {
+    let end = origin as usize + rom.len();
+    let in_rom = |addr: usize| addr >= origin as usize && addr < end;
+    let byte_at = |addr: usize| rom[addr - origin as usize];
+    let word_at = |addr: usize| (byte_at(addr) as u16) << 8 | byte_at(addr + 1) as u16;
+
+    let mut kinds = vec![ByteKind::Data; rom.len()];
+    let mut label_kinds: BTreeMap<u16, LabelKind> = BTreeMap::new();
+    let mut add_label = |addr: u16, kind: LabelKind| {
+        if in_rom(addr as usize) {
+            let entry = label_kinds.entry(addr).or_insert(kind);
+            *entry = (*entry).max(kind);
+        }
+    };
+    add_label(origin, LabelKind::Start);
+
+    let mut worklist = vec![origin as usize];
+    while let Some(addr) = worklist.pop() {
+        if !in_rom(addr) || !in_rom(addr + 1) || kinds[addr - origin as usize] != ByteKind::Data {
+            continue;
+        }
+        let instruction = decode(word_at(addr));
+        let len = match instruction {
+            Instruction::LoadLongIndex => 4,
+            _ => 2,
+        };
+        if !in_rom(addr + len - 1)
+            || kinds[addr - origin as usize..addr - origin as usize + len]
+                .iter()
+                .any(|kind| *kind != ByteKind::Data)
+        {
+            // The instruction would overlap with the end of the ROM or another instruction
+            continue;
+        }
+        if let Instruction::Unknown(_) = instruction {
+            // Execution would fault here, so this is most likely data
+            continue;
+        }
+
+        kinds[addr - origin as usize] = ByteKind::InstructionStart;
+        for kind in &mut kinds[addr - origin as usize + 1..addr - origin as usize + len] {
+            *kind = ByteKind::InstructionContinuation;
+        }
+
+        let next = addr + len;
+        match instruction {
+            Instruction::Halt | Instruction::Exit | Instruction::Return => {}
+            Instruction::JumpToAddress { nnn } => {
+                add_label(nnn, LabelKind::Jump);
+                worklist.push(nnn as usize);
+            }
+            Instruction::JumpToAddressPlusV0 { nnn } => {
+                // The target depends on V0, so only the base of the jump table can be followed
+                add_label(nnn, LabelKind::Jump);
+                worklist.push(nnn as usize);
+            }
+            Instruction::CallAddress { nnn } => {
+                add_label(nnn, LabelKind::Subroutine);
+                worklist.push(nnn as usize);
+                worklist.push(next);
+            }
+            Instruction::SkipIfRegEqualsByte { .. }
+            | Instruction::SkipIfRegNotEqualsByte { .. }
+            | Instruction::SkipIfRegEqualsReg { .. }
+            | Instruction::SkipIfRegNotEqualsReg { .. }
+            | Instruction::SkipIfKeyPressed { .. }
+            | Instruction::SkipIfKeyNotPressed { .. } => {
+                let skipped_len = if in_rom(next + 1) && word_at(next) == 0xF000 { 4 } else { 2 };
+                worklist.push(next);
+                worklist.push(next + skipped_len);
+            }
+            Instruction::LoadIndex { nnn } => {
+                add_label(nnn, LabelKind::Data);
+                worklist.push(next);
+            }
+            Instruction::LoadLongIndex => {
+                add_label(word_at(addr + 2), LabelKind::Data);
+                worklist.push(next);
+            }
+            _ => worklist.push(next),
+        }
+    }
+
+    // Labels can only be defined where a line starts, so targets inside an instruction move to its start
+    let instruction_start = |addr: u16| {
+        let mut offset = (addr - origin) as usize;
+        while kinds[offset] == ByteKind::InstructionContinuation {
+            offset -= 1;
+        }
+        origin + offset as u16
+    };
+    let mut line_kinds: BTreeMap<u16, LabelKind> = BTreeMap::new();
+    for (&addr, &kind) in &label_kinds {
+        let entry = line_kinds.entry(instruction_start(addr)).or_insert(kind);
+        *entry = (*entry).max(kind);
+    }
+    let labels: BTreeMap<u16, String> = line_kinds
+        .iter()
+        .map(|(addr, kind)| (*addr, kind.name(*addr)))
+        .collect();
+    let target = |nnn: u16, width: usize| {
+        if label_kinds.contains_key(&nnn) {
+            let start = instruction_start(nnn);
+            if start == nnn {
+                return labels[&nnn].clone();
+            }
+            return format!("{}+{}", labels[&start], nnn - start);
+        }
+        format!("0x{:0width$X}", nnn, width = width)
+    };
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let addr = origin as usize + offset;
+        if kinds[offset] == ByteKind::InstructionStart {
+            let len = match decode(word_at(addr)) {
+                Instruction::LoadLongIndex => 4,
+                _ => 2,
+            };
+            lines.push(Line::Code {
+                addr: addr as u16,
+                bytes: rom[offset..offset + len].to_vec(),
+                text: format_instruction(decode(word_at(addr)), addr, &word_at, &target),
+            });
+            offset += len;
+        } else {
+            // Data runs until the next instruction, label or line length limit
+            let mut len = 1;
+            while offset + len < rom.len()
+                && len < BYTES_PER_DATA_LINE
+                && kinds[offset + len] == ByteKind::Data
+                && !labels.contains_key(&((addr + len) as u16))
+            {
+                len += 1;
+            }
+            lines.push(Line::Data {
+                addr: addr as u16,
+                bytes: rom[offset..offset + len].to_vec(),
+            });
+            offset += len;
+        }
+    }
+
+    Disassembly {
+        origin,
+        lines,
+        labels,
+    }
+}
+
+/// Formats an instruction, writing its target address with `target`, given the address
+/// and the number of hexadecimal digits to use when it has no label.
+fn format_instruction(
+    instruction: Instruction,
+    addr: usize,
+    word_at: &dyn Fn(usize) -> u16,
+    target: &dyn Fn(u16, usize) -> String,
+) -> String {
+    match instruction {
+        Instruction::JumpToAddress { nnn } => format!("JP {}", target(nnn, 3)),
+        Instruction::CallAddress { nnn } => format!("CALL {}", target(nnn, 3)),
+        Instruction::LoadIndex { nnn } => format!("LD I, {}", target(nnn, 3)),
+        Instruction::JumpToAddressPlusV0 { nnn } => format!("JP V0, {}", target(nnn, 3)),
+        Instruction::LoadLongIndex => format!("LD I, LONG {}", target(word_at(addr + 2), 4)),
+        _ => instruction.to_string(),
+    }
+}
+
+impl fmt::Display for Disassembly {
+    /// Formats the disassembly as assembly source, with the address and raw bytes of
+    /// every line in a trailing comment.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.labels.get(&line.addr()) {
+                writeln!(f, "{}:", label)?;
+            }
+            let (text, bytes) = match line {
+                Line::Code { text, bytes, .. } => (text.clone(), bytes),
+                Line::Data { bytes, .. } => {
+                    let values: Vec<String> = bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+                    (format!("DB {}", values.join(", ")), bytes)
+                }
+            };
+            let raw: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            writeln!(f, "    {:<40} ; 0x{:03X}: {}", text, line.addr(), raw.join(""))?;
+        }
+        Ok(())
+    }
+}
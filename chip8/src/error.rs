@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::fmt;
+
+/// A fault raised by `Memory` when an access falls outside of its bounds.
+///
+/// The memory has no knowledge of the instruction being executed, so the CPU turns
+/// these into a `Chip8Error` carrying the program counter and opcode with `at`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryError {
+    /// The address is past the end of the memory.
+    OutOfBounds(u16),
+    /// A value was pushed onto a full stack.
+    StackOverflow,
+    /// A value was popped from an empty stack.
+    StackUnderflow,
+}
+
+impl MemoryError {
+    /// Attaches the program counter and opcode of the faulting instruction.
+    pub fn at(self, pc: u16, opcode: u16) -> Chip8Error {
+        match self {
+            MemoryError::OutOfBounds(addr) => Chip8Error::MemoryOutOfBounds { pc, opcode, addr },
+            MemoryError::StackOverflow => Chip8Error::StackOverflow { pc, opcode },
+            MemoryError::StackUnderflow => Chip8Error::StackUnderflow { pc, opcode },
+        }
+    }
+}
+
+/// The faults that can occur while loading or executing a Chip-8 program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// The opcode does not decode to any known instruction.
+    UnknownOpcode { pc: u16, opcode: u16 },
+    /// A `2nnn` call was made with a full stack.
+    StackOverflow { pc: u16, opcode: u16 },
+    /// A `00EE` return was made with an empty stack.
+    StackUnderflow { pc: u16, opcode: u16 },
+    /// The instruction accessed memory past the end of the RAM.
+    MemoryOutOfBounds { pc: u16, opcode: u16, addr: u16 },
+    /// The program counter points past the end of the RAM, so no opcode could be fetched.
+    PcOutOfBounds { pc: u16 },
+    /// The program does not fit in the memory after the load address.
+    ProgramTooLarge { size: usize, max: usize },
+}
+
+impl Chip8Error {
+    /// Returns the program counter of the faulting instruction, if any.
+    pub fn pc(&self) -> Option<u16> {
+        match *self {
+            Chip8Error::UnknownOpcode { pc, .. }
+            | Chip8Error::StackOverflow { pc, .. }
+            | Chip8Error::StackUnderflow { pc, .. }
+            | Chip8Error::MemoryOutOfBounds { pc, .. }
+            | Chip8Error::PcOutOfBounds { pc } => Some(pc),
+            Chip8Error::ProgramTooLarge { .. } => None,
+        }
+    }
+
+    /// Returns the opcode of the faulting instruction, if any.
+    pub fn opcode(&self) -> Option<u16> {
+        match *self {
+            Chip8Error::UnknownOpcode { opcode, .. }
+            | Chip8Error::StackOverflow { opcode, .. }
+            | Chip8Error::StackUnderflow { opcode, .. }
+            | Chip8Error::MemoryOutOfBounds { opcode, .. } => Some(opcode),
+            Chip8Error::PcOutOfBounds { .. } | Chip8Error::ProgramTooLarge { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Chip8Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:04X} at 0x{:04X}", opcode, pc)
+            }
+            Chip8Error::StackOverflow { pc, opcode } => {
+                write!(f, "stack overflow executing {:04X} at 0x{:04X}", opcode, pc)
+            }
+            Chip8Error::StackUnderflow { pc, opcode } => {
+                write!(f, "stack underflow executing {:04X} at 0x{:04X}", opcode, pc)
+            }
+            Chip8Error::MemoryOutOfBounds { pc, opcode, addr } => write!(
+                f,
+                "out of bounds memory access at 0x{:04X} executing {:04X} at 0x{:04X}",
+                addr, opcode, pc
+            ),
+            Chip8Error::PcOutOfBounds { pc } => {
+                write!(f, "program counter 0x{:04X} is out of bounds", pc)
+            }
+            Chip8Error::ProgramTooLarge { size, max } => {
+                write!(f, "program of {} bytes does not fit in {} bytes", size, max)
+            }
+        }
+    }
+}
+
+impl Error for Chip8Error {}
@@ -0,0 +1,554 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::cpu::{CPU, TIMER_HZ};
+use crate::gpu::{GPU, HIRES_HEIGHT, HIRES_WIDTH};
+use crate::movie::{Movie, MoviePlayer, MovieRecorder};
+use crate::rewind::{RewindBuffer, RewindConfig};
+use crate::sound::SoundState;
+
+/// The number of quick-save slots.
+pub const SAVE_SLOTS: u8 = 10;
+/// The default CPU clock speed in Hz.
+pub const DEFAULT_CLOCK_HZ: u32 = 1500;
+
+/// Shows the display of the machine.
+pub trait Display {
+    /// Draws a frame, called once per emulated frame.
+    fn present(&mut self, gpu: &GPU);
+
+    /// Shows a status message, such as the result of a quick save.
+    fn message(&mut self, text: &str) {
+        println!("{}", text);
+    }
+
+    /// Switches between windowed and fullscreen, for backends that have a window.
+    fn toggle_fullscreen(&mut self) {}
+}
+
+/// Reads the user's input.
+pub trait Input {
+    /// Returns the commands received since the last call, without blocking.
+    fn poll(&mut self) -> Vec<Command>;
+}
+
+/// Plays the buzzer.
+pub trait Audio {
+    /// Passes what the buzzer should be playing, called once per emulated frame.
+    fn update(&mut self, state: SoundState);
+}
+
+/// An `Audio` backend that plays nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullAudio;
+
+impl Audio for NullAudio {
+    fn update(&mut self, _state: SoundState) {}
+}
+
+/// What an `Input` backend asks the run loop to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// A keypad key, from 0x0 to 0xF, was pressed or released.
+    Key { key: u8, pressed: bool },
+    /// Stop the run loop.
+    Quit,
+    /// Pause or resume execution.
+    TogglePause,
+    /// Execute a single instruction.
+    Step,
+    /// Reset the CPU.
+    Reset,
+    /// Mute or unmute the buzzer.
+    ToggleMute,
+    /// Print the registers to stdout.
+    PrintRegisters,
+    /// Write the machine state to the selected slot.
+    SaveState,
+    /// Restore the machine state from the selected slot.
+    LoadState,
+    /// Select the next quick-save slot.
+    NextSlot,
+    /// Select the previous quick-save slot.
+    PreviousSlot,
+    /// Start or stop playing execution backwards.
+    Rewind(bool),
+    /// Start recording a movie, or stop and write it.
+    ToggleRecording,
+    /// Start playing back the movie, or stop playback.
+    TogglePlayback,
+    /// Switch between windowed and fullscreen.
+    ToggleFullscreen,
+}
+
+/// How the screen is scaled to fill a window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    /// The largest whole number of window pixels per high-resolution pixel, so that every
+    /// pixel has the same size. Windows too small for one fall back to `Fit`.
+    #[default]
+    Integer,
+    /// The largest size that fits the window, keeping the aspect ratio.
+    Fit,
+}
+
+impl ScaleMode {
+    /// Returns the scale mode with the given name, `integer` or `fit`.
+    pub fn from_name(name: &str) -> Option<ScaleMode> {
+        match name {
+            "integer" => Some(ScaleMode::Integer),
+            "fit" => Some(ScaleMode::Fit),
+            _ => None,
+        }
+    }
+}
+
+/// A clockwise rotation of the screen, for ROMs written for displays mounted sideways or
+/// upside down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Rotation {
+    /// Returns the rotation of the given angle in degrees: 0, 90, 180 or 270.
+    pub fn from_degrees(degrees: u32) -> Option<Rotation> {
+        match degrees {
+            0 => Some(Rotation::None),
+            90 => Some(Rotation::Quarter),
+            180 => Some(Rotation::Half),
+            270 => Some(Rotation::ThreeQuarters),
+            _ => None,
+        }
+    }
+
+    /// Returns the angle in degrees.
+    pub fn degrees(&self) -> u32 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Quarter => 90,
+            Rotation::Half => 180,
+            Rotation::ThreeQuarters => 270,
+        }
+    }
+
+    /// Returns true if the screen is turned sideways, swapping its width and height.
+    pub fn is_sideways(&self) -> bool {
+        matches!(self, Rotation::Quarter | Rotation::ThreeQuarters)
+    }
+}
+
+/// The area of a window the screen is drawn to, centred with borders around it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    /// The width on screen, after the rotation.
+    pub width: u32,
+    /// The height on screen, after the rotation.
+    pub height: u32,
+}
+
+impl Viewport {
+    /// Places the screen in a window, letterboxed to keep its aspect ratio.
+    ///
+    /// The low and high resolutions share the 2:1 aspect ratio and are scaled to the same
+    /// size, so switching modes never resizes the picture.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The width and height of the window in pixels.
+    /// * `mode` - How the screen is scaled.
+    /// * `rotation` - How the screen is turned, a quarter turn making it 1:2.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::frontend::{Rotation, ScaleMode, Viewport};
+    ///
+    /// let viewport = Viewport::new((1000, 400), ScaleMode::Integer, Rotation::None);
+    /// assert_eq!(viewport, Viewport { x: 116, y: 8, width: 768, height: 384 });
+    /// ```
+    pub fn new(window: (u32, u32), mode: ScaleMode, rotation: Rotation) -> Viewport {
+        let (width, height) = window;
+        let (grid_width, grid_height) = if rotation.is_sideways() {
+            (HIRES_HEIGHT as u32, HIRES_WIDTH as u32)
+        } else {
+            (HIRES_WIDTH as u32, HIRES_HEIGHT as u32)
+        };
+        let scale = (width / grid_width).min(height / grid_height);
+        let (screen_width, screen_height) = if mode == ScaleMode::Integer && scale > 0 {
+            (grid_width * scale, grid_height * scale)
+        } else if width as u64 * grid_height as u64 <= height as u64 * grid_width as u64 {
+            (width, (width as u64 * grid_height as u64 / grid_width as u64) as u32)
+        } else {
+            ((height as u64 * grid_width as u64 / grid_height as u64) as u32, height)
+        };
+        Viewport {
+            x: (width - screen_width) / 2,
+            y: (height - screen_height) / 2,
+            width: screen_width,
+            height: screen_height,
+        }
+    }
+}
+
+/// The timing and history settings of a `Session`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// The number of instructions run per frame, the timers ticking once per frame.
+    pub cycles_per_frame: u32,
+    /// The real time a frame lasts, zero to run as fast as possible.
+    pub frame_duration: Duration,
+    /// The number of frames and bytes the rewind history may hold.
+    pub rewind: RewindConfig,
+}
+
+impl Default for SessionConfig {
+    /// A 1500 Hz clock paced at 60 frames per second.
+    fn default() -> Self {
+        SessionConfig {
+            cycles_per_frame: DEFAULT_CLOCK_HZ / TIMER_HZ,
+            frame_duration: Duration::from_secs(1) / TIMER_HZ,
+            rewind: RewindConfig::default(),
+        }
+    }
+}
+
+/// The movie being recorded or played back.
+enum MovieMode {
+    Off,
+    Recording(MovieRecorder),
+    Playing(MoviePlayer),
+}
+
+/// The frontend-independent part of an interactive emulator: the run loop, frame pacing,
+/// save states, movies and rewind.
+///
+/// A frontend only implements `Display`, `Input` and `Audio` and passes them to `run`.
+pub struct Session {
+    config: SessionConfig,
+    /// The path of the running ROM, next to which the save states and movies are written.
+    rom_path: Option<PathBuf>,
+    /// The running ROM, needed to boot it when a movie starts.
+    rom: Vec<u8>,
+    /// The movie being recorded or played back.
+    movie: MovieMode,
+    /// The selected quick-save slot.
+    slot: u8,
+    /// The snapshots recorded every frame.
+    rewind: RewindBuffer,
+    /// Whether execution is played backwards.
+    rewinding: bool,
+    /// Whether the user paused execution, unlike `CPU::halt` which the program or a fault sets.
+    paused: bool,
+    /// Whether the buzzer is muted.
+    muted: bool,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new(SessionConfig::default())
+    }
+}
+
+impl Session {
+    /// Creates a session with no ROM set.
+    pub fn new(config: SessionConfig) -> Self {
+        Session {
+            config,
+            rom_path: None,
+            rom: Vec::new(),
+            movie: MovieMode::Off,
+            slot: 0,
+            rewind: RewindBuffer::new(config.rewind),
+            rewinding: false,
+            paused: false,
+            muted: false,
+        }
+    }
+
+    /// Returns the settings of the session.
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Replaces the settings, emptying the rewind history.
+    pub fn set_config(&mut self, config: SessionConfig) {
+        self.config = config;
+        self.rewind = RewindBuffer::new(config.rewind);
+    }
+
+    /// Sets the running ROM, enabling the quick-save and movie commands.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the ROM. Save states are written next to it as `<rom>.state<slot>`
+    ///   and movies as `<rom>.movie`.
+    /// * `rom` - The program, reloaded when a movie starts.
+    pub fn set_rom(&mut self, path: &Path, rom: &[u8]) {
+        self.rom_path = Some(path.to_path_buf());
+        self.rom = rom.to_vec();
+    }
+
+    /// Returns the selected quick-save slot.
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    /// Returns true while execution is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Pauses or resumes execution.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Returns true while the buzzer is muted.
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Runs the machine until the input asks to quit.
+    ///
+    /// Every frame, the commands from the input are handled, then the CPU runs one frame
+    /// unless it is halted or paused, or is restored to the previous snapshot while rewinding.
+    /// A fault shows the error and halts the CPU until it is reset. The buzzer state and the display are then
+    /// passed to the backends, and the loop sleeps until the frame's deadline. A frame that
+    /// runs late does not make the following ones run faster.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The machine to run, with its program loaded.
+    /// * `display` - Where the frames and messages are shown.
+    /// * `input` - Where the commands come from.
+    /// * `audio` - Where the buzzer is played.
+    pub fn run(
+        &mut self,
+        cpu: &mut CPU,
+        display: &mut dyn Display,
+        input: &mut dyn Input,
+        audio: &mut dyn Audio,
+    ) {
+        let mut deadline = Instant::now();
+        loop {
+            for command in input.poll() {
+                if !self.handle(cpu, command, display) {
+                    return;
+                }
+            }
+            self.frame(cpu, display);
+
+            let mut state = cpu.sound_state();
+            state.active &= !self.muted && !self.paused && !cpu.halt;
+            audio.update(state);
+            display.present(cpu.get_gpu());
+
+            deadline += self.config.frame_duration;
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            } else {
+                deadline = now;
+            }
+        }
+    }
+
+    /// Handles one command from the input.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - False if the command asks to quit.
+    pub fn handle(&mut self, cpu: &mut CPU, command: Command, display: &mut dyn Display) -> bool {
+        match command {
+            Command::Quit => return false,
+            Command::Key { key, pressed } => {
+                let index = key as usize & 0xF;
+                match (&mut self.movie, pressed) {
+                    (MovieMode::Off, true) => cpu.key_pressed(index),
+                    (MovieMode::Off, false) => cpu.key_released(index),
+                    (MovieMode::Recording(recorder), true) => recorder.key_pressed(cpu, index),
+                    (MovieMode::Recording(recorder), false) => recorder.key_released(cpu, index),
+                    // The movie presses the keys
+                    (MovieMode::Playing(_), _) => {}
+                }
+            }
+            Command::TogglePause => self.paused = !self.paused,
+            Command::Step if !self.movie_locked(display) => {
+                if cpu.halt {
+                    display.message("The program has halted, reset to run it again");
+                } else if let Err(e) = cpu.cycle() {
+                    display.message(&e.to_string());
+                    cpu.halt = true;
+                }
+            }
+            Command::Reset if !self.movie_locked(display) => cpu.reset(),
+            Command::ToggleMute => self.muted = !self.muted,
+            Command::PrintRegisters => cpu.print_registers(),
+            Command::SaveState => self.quick_save(cpu, display),
+            Command::LoadState if !self.movie_locked(display) => self.quick_load(cpu, display),
+            Command::NextSlot => {
+                self.slot = (self.slot + 1) % SAVE_SLOTS;
+                display.message(&format!("Save slot {}", self.slot));
+            }
+            Command::PreviousSlot => {
+                self.slot = (self.slot + SAVE_SLOTS - 1) % SAVE_SLOTS;
+                display.message(&format!("Save slot {}", self.slot));
+            }
+            Command::Rewind(true) if !self.movie_locked(display) => self.rewinding = true,
+            Command::Rewind(false) => self.rewinding = false,
+            Command::ToggleRecording => self.toggle_recording(cpu, display),
+            Command::TogglePlayback => self.toggle_playback(cpu, display),
+            Command::ToggleFullscreen => display.toggle_fullscreen(),
+            Command::Step | Command::Reset | Command::LoadState | Command::Rewind(true) => {}
+        }
+        true
+    }
+
+    /// Advances the machine by one frame, or restores the previous snapshot while rewinding.
+    ///
+    /// Frames run through the active movie, and a snapshot is recorded after each one.
+    pub fn frame(&mut self, cpu: &mut CPU, display: &mut dyn Display) {
+        if self.rewinding {
+            if let Err(e) = self.rewind.rewind(cpu) {
+                display.message(&format!("Cannot rewind: {}", e));
+                self.rewind.clear();
+            }
+            return;
+        }
+        if cpu.halt || self.paused {
+            return;
+        }
+        let result = match &mut self.movie {
+            MovieMode::Off => cpu.run_frame(self.config.cycles_per_frame).map_err(|e| e.to_string()),
+            MovieMode::Recording(recorder) => recorder.run_frame(cpu).map_err(|e| e.to_string()),
+            MovieMode::Playing(player) => match player.run_frame(cpu) {
+                Ok(true) => Ok(()),
+                Ok(false) => {
+                    display.message(&format!("Movie finished after {} frames", player.frame()));
+                    self.movie = MovieMode::Off;
+                    Ok(())
+                }
+                Err(e) => Err(e.to_string()),
+            },
+        };
+        if let Err(e) = result {
+            display.message(&e);
+            cpu.halt = true;
+        }
+        self.rewind.record(cpu);
+    }
+
+    /// Returns the path of the movie of the running ROM.
+    fn movie_path(&self) -> Option<PathBuf> {
+        self.rom_path
+            .as_ref()
+            .map(|path| PathBuf::from(format!("{}.movie", path.display())))
+    }
+
+    /// Starts recording a movie from a fresh boot of the ROM, or stops and writes it.
+    fn toggle_recording(&mut self, cpu: &mut CPU, display: &mut dyn Display) {
+        let Some(path) = self.movie_path() else {
+            display.message("Cannot record: no ROM path set");
+            return;
+        };
+        match std::mem::replace(&mut self.movie, MovieMode::Off) {
+            MovieMode::Recording(recorder) => {
+                let movie = recorder.finish(cpu);
+                match fs::write(&path, movie.to_string()) {
+                    Ok(()) => display.message(&format!("Wrote {} frames to {}", movie.frames, path.display())),
+                    Err(e) => display.message(&format!("Error writing {}: {}", path.display(), e)),
+                }
+            }
+            _ => {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_nanos() as u64);
+                match MovieRecorder::start(cpu, &self.rom, seed, self.config.cycles_per_frame) {
+                    Ok(recorder) => {
+                        display.message("Recording movie");
+                        self.rewind.clear();
+                        self.movie = MovieMode::Recording(recorder);
+                    }
+                    Err(e) => display.message(&format!("Cannot record: {}", e)),
+                }
+            }
+        }
+    }
+
+    /// Starts playing back the movie of the ROM, or stops playback.
+    fn toggle_playback(&mut self, cpu: &mut CPU, display: &mut dyn Display) {
+        let Some(path) = self.movie_path() else {
+            display.message("Cannot play: no ROM path set");
+            return;
+        };
+        if let MovieMode::Playing(_) = self.movie {
+            display.message("Movie stopped");
+            self.movie = MovieMode::Off;
+            return;
+        }
+        let result = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Movie::parse(&text).map_err(|e| e.to_string()))
+            .and_then(|movie| MoviePlayer::start(movie, cpu, &self.rom).map_err(|e| e.to_string()));
+        match result {
+            Ok(player) => {
+                display.message(&format!("Playing {}", path.display()));
+                self.rewind.clear();
+                self.movie = MovieMode::Playing(player);
+            }
+            Err(e) => display.message(&format!("Error playing {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Returns true, with a message, if a movie is active and the machine must not be altered.
+    fn movie_locked(&self, display: &mut dyn Display) -> bool {
+        let locked = !matches!(self.movie, MovieMode::Off);
+        if locked {
+            display.message("Not available while a movie is recording or playing");
+        }
+        locked
+    }
+
+    /// Returns the path of the save state in the selected slot.
+    fn slot_path(&self) -> Option<PathBuf> {
+        self.rom_path
+            .as_ref()
+            .map(|path| PathBuf::from(format!("{}.state{}", path.display(), self.slot)))
+    }
+
+    /// Writes the machine state to the selected slot.
+    fn quick_save(&self, cpu: &CPU, display: &mut dyn Display) {
+        let Some(path) = self.slot_path() else {
+            display.message("Cannot save: no ROM path set");
+            return;
+        };
+        match fs::write(&path, cpu.save_state()) {
+            Ok(()) => display.message(&format!("Saved state to slot {}", self.slot)),
+            Err(e) => display.message(&format!("Error saving {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Restores the machine state from the selected slot.
+    fn quick_load(&self, cpu: &mut CPU, display: &mut dyn Display) {
+        let Some(path) = self.slot_path() else {
+            display.message("Cannot load: no ROM path set");
+            return;
+        };
+        let result = fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| cpu.load_state(&data).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => display.message(&format!("Loaded state from slot {}", self.slot)),
+            Err(e) => display.message(&format!("Error loading {}: {}", path.display(), e)),
+        }
+    }
+}
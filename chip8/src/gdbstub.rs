@@ -0,0 +1,376 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+use crate::cpu::CPU;
+use crate::debugger::{Debugger, Reply, StopReason};
+use crate::error::Chip8Error;
+use crate::hash::{from_hex, to_hex};
+
+/// The port the `gdb` subcommand listens on by default.
+pub const DEFAULT_GDB_PORT: u16 = 1234;
+
+/// The number of instructions run between two checks for an interrupt from the client.
+const RUN_CHUNK: u64 = 1000;
+
+/// The number of registers: V0-VF, I, PC, SP, DT and ST.
+const REGISTER_COUNT: usize = 21;
+
+/// The target description sent to the client, naming the CHIP-8 registers.
+///
+/// Registers 0 to 15 are V0 to VF, followed by I, PC, SP, DT and ST. The 16-bit
+/// registers are little-endian.
+pub const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+/// What to do after a packet was handled.
+enum Action {
+    /// Send a reply and wait for the next packet.
+    Reply(String),
+    /// Run until a stop, then send the stop reply.
+    Continue,
+    /// Acknowledge and end the session.
+    Detach,
+    /// End the session without a reply.
+    Kill,
+}
+
+/// A GDB remote serial protocol server exposing a `CPU` to gdb, lldb and IDEs.
+///
+/// The V registers, I, PC, SP and the timers are described by `TARGET_XML`, and the memory
+/// is the whole RAM. Breakpoints and stepping are handled by a `Debugger`, whose commands are
+/// also available with `monitor`, for example `monitor key 5` to press a keypad key.
+pub struct GdbStub {
+    debugger: Debugger,
+}
+
+/// A client connection, reading and writing framed packets.
+struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.stream.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    /// Reads the next packet, acknowledging it, or None once the client disconnects.
+    ///
+    /// An interrupt (`0x03`) is returned as a packet of its own.
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.read_byte()? {
+                None => return Ok(None),
+                Some(0x03) => return Ok(Some("\x03".to_string())),
+                Some(b'$') => {}
+                // Acknowledgements and noise between packets
+                Some(_) => continue,
+            }
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    None => return Ok(None),
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                }
+            }
+            let mut checksum = [0; 2];
+            self.stream.read_exact(&mut checksum)?;
+            let expected = std::str::from_utf8(&checksum).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if expected == Some(checksum_of(&data)) {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.stream.write_all(packet.as_bytes())?;
+        self.stream.flush()
+    }
+
+    /// Returns true if the client sent an interrupt, without blocking.
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let result = match self.read_byte() {
+            Ok(Some(byte)) => Ok(byte == 0x03),
+            Ok(None) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        };
+        self.stream.set_nonblocking(false)?;
+        result
+    }
+}
+
+/// Returns the checksum of a packet: the sum of its bytes modulo 256.
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Returns the stop reply for the reason execution stopped.
+fn stop_reply(reason: StopReason) -> String {
+    match reason {
+        StopReason::Halted => "W00".to_string(),
+        StopReason::Fault(Chip8Error::UnknownOpcode { .. }) => "S04".to_string(),
+        StopReason::Fault(_) => "S0b".to_string(),
+        _ => "S05".to_string(),
+    }
+}
+
+/// Parses a hexadecimal number of a packet.
+fn parse_hex(value: &str) -> Option<usize> {
+    usize::from_str_radix(value, 16).ok()
+}
+
+impl GdbStub {
+    /// Creates a stub with no breakpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles_per_frame` - The number of instructions between two timer ticks.
+    pub fn new(cycles_per_frame: u32) -> Self {
+        GdbStub {
+            debugger: Debugger::new(cycles_per_frame),
+        }
+    }
+
+    /// Serves a client until it detaches, kills the target or disconnects.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the connection fails.
+    pub fn serve(&mut self, cpu: &mut CPU, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Connection { stream };
+        while let Some(packet) = connection.read_packet()? {
+            match self.handle_packet(cpu, &packet) {
+                Action::Reply(reply) => connection.write_packet(&reply)?,
+                Action::Continue => {
+                    let reply = self.resume(cpu, &mut connection)?;
+                    connection.write_packet(&reply)?;
+                }
+                Action::Detach => {
+                    connection.write_packet("OK")?;
+                    break;
+                }
+                Action::Kill => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs until a stop or an interrupt from the client, returning the stop reply.
+    ///
+    /// A program waiting for a key keeps waiting until the client interrupts it.
+    fn resume(&mut self, cpu: &mut CPU, connection: &mut Connection) -> io::Result<String> {
+        loop {
+            match self.debugger.step(cpu, RUN_CHUNK) {
+                StopReason::Step => {}
+                StopReason::WaitingForKey => thread::sleep(Duration::from_millis(10)),
+                reason => return Ok(stop_reply(reason)),
+            }
+            if connection.interrupted()? {
+                return Ok("S02".to_string());
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, cpu: &mut CPU, packet: &str) -> Action {
+        let reply = match packet.as_bytes().first() {
+            Some(0x03) => "S02".to_string(),
+            Some(b'?') => "S05".to_string(),
+            Some(b'g') => self.read_registers(cpu),
+            Some(b'G') => self.write_registers(cpu, &packet[1..]),
+            Some(b'p') => match parse_hex(&packet[1..]).and_then(|reg| self.read_register(cpu, reg)) {
+                Some(value) => value,
+                None => "E01".to_string(),
+            },
+            Some(b'P') => self.write_register_packet(cpu, &packet[1..]),
+            Some(b'm') => self.read_memory(cpu, &packet[1..]),
+            Some(b'M') => self.write_memory(cpu, &packet[1..]),
+            Some(b'c') => return Action::Continue,
+            Some(b's') => stop_reply(self.debugger.step(cpu, 1)),
+            Some(b'Z') | Some(b'z') => self.breakpoint(packet),
+            Some(b'D') => return Action::Detach,
+            Some(b'k') => return Action::Kill,
+            Some(b'H') => "OK".to_string(),
+            _ => self.query(cpu, packet),
+        };
+        Action::Reply(reply)
+    }
+
+    /// Answers the `q` packets, and an empty reply to anything unsupported.
+    fn query(&mut self, cpu: &mut CPU, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return "PacketSize=1000;qXfer:features:read+".to_string();
+        }
+        if packet == "qAttached" {
+            return "1".to_string();
+        }
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let Some((offset, length)) = range.split_once(',') else {
+                return "E01".to_string();
+            };
+            let (Some(offset), Some(length)) = (parse_hex(offset), parse_hex(length)) else {
+                return "E01".to_string();
+            };
+            let start = offset.min(TARGET_XML.len());
+            let end = (start + length).min(TARGET_XML.len());
+            let prefix = if end < TARGET_XML.len() { 'm' } else { 'l' };
+            return format!("{}{}", prefix, &TARGET_XML[start..end]);
+        }
+        if let Some(command) = packet.strip_prefix("qRcmd,") {
+            let Some(command) = from_hex(command).and_then(|bytes| String::from_utf8(bytes).ok()) else {
+                return "E01".to_string();
+            };
+            return match self.debugger.execute(cpu, &command) {
+                Ok(Reply::Output(text)) if text.is_empty() => "OK".to_string(),
+                Ok(Reply::Output(text)) => to_hex(text.as_bytes()),
+                Ok(Reply::Quit) => "OK".to_string(),
+                Err(e) => to_hex(format!("Error: {}\n", e).as_bytes()),
+            };
+        }
+        String::new()
+    }
+
+    /// Encodes a register in target byte order, None if there is no such register.
+    fn read_register(&self, cpu: &CPU, reg: usize) -> Option<String> {
+        let memory = &cpu.memory;
+        let bytes = match reg {
+            0..=15 => vec![memory.v[reg]],
+            16 => memory.i.to_le_bytes().to_vec(),
+            17 => memory.pc.to_le_bytes().to_vec(),
+            18 => vec![memory.sp],
+            19 => vec![memory.dt],
+            20 => vec![memory.st],
+            _ => return None,
+        };
+        Some(to_hex(&bytes))
+    }
+
+    /// Decodes and sets a register, returning the number of hex digits it used.
+    fn write_register(&self, cpu: &mut CPU, reg: usize, hex: &str) -> Option<usize> {
+        let width = match reg {
+            16 | 17 => 4,
+            0..=20 => 2,
+            _ => return None,
+        };
+        let bytes = from_hex(hex.get(..width)?)?;
+        let memory = &mut cpu.memory;
+        match reg {
+            0..=15 => memory.v[reg] = bytes[0],
+            16 => memory.i = u16::from_le_bytes([bytes[0], bytes[1]]),
+            17 => memory.pc = u16::from_le_bytes([bytes[0], bytes[1]]),
+            18 => memory.sp = bytes[0],
+            19 => memory.dt = bytes[0],
+            _ => memory.st = bytes[0],
+        }
+        Some(width)
+    }
+
+    fn read_registers(&self, cpu: &CPU) -> String {
+        (0..REGISTER_COUNT).filter_map(|reg| self.read_register(cpu, reg)).collect()
+    }
+
+    fn write_registers(&self, cpu: &mut CPU, mut hex: &str) -> String {
+        for reg in 0..REGISTER_COUNT {
+            match self.write_register(cpu, reg, hex) {
+                Some(width) => hex = &hex[width..],
+                None => return "E01".to_string(),
+            }
+        }
+        "OK".to_string()
+    }
+
+    fn write_register_packet(&self, cpu: &mut CPU, args: &str) -> String {
+        let written = args
+            .split_once('=')
+            .and_then(|(reg, value)| self.write_register(cpu, parse_hex(reg)?, value));
+        match written {
+            Some(_) => "OK".to_string(),
+            None => "E01".to_string(),
+        }
+    }
+
+    /// Parses the `addr,length` of a memory packet, None if it is malformed or out of bounds.
+    fn memory_range(&self, cpu: &CPU, args: &str) -> Option<(usize, usize)> {
+        let (addr, length) = args.split_once(',')?;
+        let (addr, length) = (parse_hex(addr)?, parse_hex(length)?);
+        let end = addr.checked_add(length)?;
+        (end <= cpu.memory.memory.len()).then_some((addr, end))
+    }
+
+    fn read_memory(&self, cpu: &CPU, args: &str) -> String {
+        match self.memory_range(cpu, args) {
+            Some((start, end)) => to_hex(&cpu.memory.memory[start..end]),
+            None => "E01".to_string(),
+        }
+    }
+
+    fn write_memory(&self, cpu: &mut CPU, args: &str) -> String {
+        let Some((range, data)) = args.split_once(':') else {
+            return "E01".to_string();
+        };
+        match (self.memory_range(cpu, range), from_hex(data)) {
+            (Some((start, end)), Some(bytes)) if bytes.len() == end - start => {
+                cpu.memory.memory[start..end].copy_from_slice(&bytes);
+                "OK".to_string()
+            }
+            _ => "E01".to_string(),
+        }
+    }
+
+    /// Sets or clears a software (`Z0`) or hardware (`Z1`) breakpoint.
+    fn breakpoint(&mut self, packet: &str) -> String {
+        let mut fields = packet[1..].split(',');
+        let (Some(kind), Some(addr)) = (fields.next(), fields.next()) else {
+            return "E01".to_string();
+        };
+        let Some(addr) = parse_hex(addr).and_then(|addr| u16::try_from(addr).ok()) else {
+            return "E01".to_string();
+        };
+        if kind != "0" && kind != "1" {
+            return String::new();
+        }
+        if packet.starts_with('Z') {
+            self.debugger.add_breakpoint(addr);
+        } else {
+            self.debugger.remove_breakpoint(addr);
+        }
+        "OK".to_string()
+    }
+}
@@ -4,29 +4,105 @@ pub const VRAM_WIDTH: usize = 64;
 /// The height of the video buffer in pixels.
 pub const VRAM_HEIGHT: usize = 32;
 
+/// The width of the video buffer in pixels in SUPER-CHIP high resolution mode.
+pub const HIRES_WIDTH: usize = 128;
+
+/// The height of the video buffer in pixels in SUPER-CHIP high resolution mode.
+pub const HIRES_HEIGHT: usize = 64;
+
+/// The number of pixels `00FB`/`00FC` scroll the screen horizontally.
+const HORIZONTAL_SCROLL: usize = 4;
+
+/// The number of XO-CHIP bitplanes.
+pub const PLANES: usize = 2;
+
 /// A type representing a coordinate on the video buffer.
 pub type Coordinate = (usize, usize);
 
 /// The graphics processing unit (GPU) responsible for rendering graphics to the screen.
 pub struct GPU {
     /// The video buffer, represented as a 2D array of pixels.
-    pub video_buffer: [[u8; VRAM_WIDTH]; VRAM_HEIGHT],
+    ///
+    /// The buffer is sized for high resolution mode. In low resolution mode only the
+    /// top-left `VRAM_WIDTH` x `VRAM_HEIGHT` pixels are used.
+    ///
+    /// Each pixel holds one bit per XO-CHIP bitplane, so its value is a colour index
+    /// between 0 and 3. Programs that never select the second plane only use 0 and 1.
+    pub video_buffer: [[u8; HIRES_WIDTH]; HIRES_HEIGHT],
+
+    /// Whether the SUPER-CHIP 128x64 high resolution mode is enabled.
+    hires: bool,
+
+    /// The bitplanes affected by drawing, clearing and scrolling, as selected by `Fn01`.
+    plane_mask: u8,
+}
+
+impl Default for GPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GPU {
     /// Creates a new GPU instance.
     pub fn new() -> GPU {
         GPU {
-            video_buffer: [[0x0; 64]; 32],
+            video_buffer: [[0x0; HIRES_WIDTH]; HIRES_HEIGHT],
+            hires: false,
+            plane_mask: 0x1,
         }
     }
 
+    /// Returns whether the high resolution mode is enabled.
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Returns the width in pixels of the current display mode.
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            VRAM_WIDTH
+        }
+    }
+
+    /// Returns the height in pixels of the current display mode.
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            VRAM_HEIGHT
+        }
+    }
+
+    /// Switches between the low and high resolution modes, clearing the video buffer.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.video_buffer = [[0x0; HIRES_WIDTH]; HIRES_HEIGHT];
+    }
+
+    /// Returns the bitplanes currently selected for drawing.
+    pub fn plane_mask(&self) -> u8 {
+        self.plane_mask
+    }
+
+    /// Selects the bitplanes affected by drawing, clearing and scrolling (`Fn01`).
+    pub fn select_planes(&mut self, mask: u8) {
+        self.plane_mask = mask & 0x3;
+    }
+
+    /// Returns the number of bitplanes currently selected for drawing.
+    pub fn selected_plane_count(&self) -> usize {
+        self.plane_mask.count_ones() as usize
+    }
+
     /// Performs a bitwise XOR operation on a pixel in the video buffer, returning the collision flag.
     ///
     /// # Arguments
     ///
     /// * `coords` - The coordinates of the pixel to XOR.
-    /// * `value` - The value to XOR with the pixel.
+    /// * `value` - The value to XOR with the pixel, with one bit per bitplane.
     ///
     /// # Returns
     ///
@@ -34,7 +110,7 @@ impl GPU {
     fn xor_pixel(&mut self, coords: Coordinate, value: u8) -> u8 {
         let collision = self.video_buffer[coords.1][coords.0] & value;
         self.video_buffer[coords.1][coords.0] ^= value;
-        collision
+        (collision != 0) as u8
     }
 
     /// Draws a single row of a sprite onto the screen buffer.
@@ -43,6 +119,8 @@ impl GPU {
     ///
     /// * `coords` - The (x, y) coordinates where the row should start.
     /// * `row` - The row data as an 8-bit unsigned integer, where each bit represents a pixel (1 is on, 0 is off).
+    /// * `plane` - The bitplane mask the row is drawn to.
+    /// * `clip` - Whether pixels past the right edge are discarded instead of wrapped.
     ///
     /// # Returns
     ///
@@ -60,20 +138,45 @@ impl GPU {
     /// * The second pixel will wrap around and be drawn at (0, 31)
     /// * The third pixel will be drawn at (1, 31)
     /// * And so on, until the eighth pixel is drawn at (6, 31)
-    fn draw_sprite_row(&mut self, coords: Coordinate, row: u8) -> u8 {
+    ///
+    /// With `clip` set, the same sprite only draws its first pixel at (63, 31) and discards the rest.
+    fn draw_sprite_row(&mut self, coords: Coordinate, row: u8, plane: u8, clip: bool) -> u8 {
+        let (width, height) = (self.width(), self.height());
         let mut collision = 0;
-        let wrapped_y = coords.1 % VRAM_HEIGHT;
+        let wrapped_y = coords.1 % height;
         for offset_col in 0..8 {
-            let wrapped_x = (coords.0 + offset_col) % VRAM_WIDTH;
+            if clip && coords.0 + offset_col >= width {
+                break;
+            }
+            let wrapped_x = (coords.0 + offset_col) % width;
             let pixel = (row >> (7 - offset_col)) & 0x1; /* Get the offset bit from the sprite */
-            collision |= self.xor_pixel((wrapped_x, wrapped_y), pixel);
+            collision |= self.xor_pixel((wrapped_x, wrapped_y), pixel * plane);
         }
         collision
     }
 
-    /// Resets the GPU, clearing the video buffer.
+    /// Resets the GPU, clearing the video buffer and restoring the low resolution mode
+    /// with only the first bitplane selected.
     pub fn reset(&mut self) {
-        self.video_buffer = [[0x0; 64]; 32];
+        self.video_buffer = [[0x0; HIRES_WIDTH]; HIRES_HEIGHT];
+        self.hires = false;
+        self.plane_mask = 0x1;
+    }
+
+    /// Clears the selected bitplanes of the video buffer (`00E0`).
+    pub fn clear(&mut self) {
+        let keep = !self.plane_mask;
+        for row in self.video_buffer.iter_mut() {
+            for pixel in row.iter_mut() {
+                *pixel &= keep;
+            }
+        }
+    }
+
+    /// Returns the masks of the selected bitplanes, in the order their sprite data is laid out.
+    fn selected_planes(&self) -> impl Iterator<Item = u8> {
+        let mask = self.plane_mask;
+        (0..PLANES).map(|plane| 1 << plane).filter(move |plane| mask & plane != 0)
     }
 
     /// Draws a sprite onto the video buffer.
@@ -82,15 +185,99 @@ impl GPU {
     ///
     /// * `coords` - The (x, y) coordinates of the top-left corner of the sprite.
     /// * `sprite` - A vector of bytes representing the sprite data, where each byte represents a row of the sprite.
+    ///   When several bitplanes are selected, the rows of each plane follow one another.
+    /// * `clip` - Whether the parts of the sprite past the screen edges are discarded instead of wrapped.
     ///
     /// # Returns
     ///
     /// A collision flag indicating whether the sprite was drawn successfully. A value of 1 indicates that at least one pixel in the sprite collided with an existing pixel on the screen.
-    pub(crate) fn draw_sprite(&mut self, coords: Coordinate, sprite: Vec<u8>) -> u8 {
+    ///
+    /// The starting coordinates always wrap around the screen, only the pixels that
+    /// extend past an edge are affected by `clip`.
+    pub(crate) fn draw_sprite(&mut self, coords: Coordinate, sprite: Vec<u8>, clip: bool) -> u8 {
+        let origin: Coordinate = (coords.0 % self.width(), coords.1 % self.height());
+        let rows_per_plane = sprite.len() / self.selected_plane_count().max(1);
+        let planes: Vec<u8> = self.selected_planes().collect();
         let mut collision = 0;
-        for (offset_row, row) in sprite.iter().enumerate() {
-            collision |= self.draw_sprite_row((coords.0, coords.1 + offset_row), *row);
+        for (plane, rows) in planes.into_iter().zip(sprite.chunks(rows_per_plane.max(1))) {
+            for (offset_row, row) in rows.iter().enumerate() {
+                let y = origin.1 + offset_row;
+                if clip && y >= self.height() {
+                    break;
+                }
+                collision |= self.draw_sprite_row((origin.0, y), *row, plane, clip);
+            }
         }
         collision
     }
+
+    /// Draws a SUPER-CHIP 16x16 sprite onto the video buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `coords` - The (x, y) coordinates of the top-left corner of the sprite.
+    /// * `sprite` - The sprite data, where each pair of bytes represents a 16 pixel row of the sprite.
+    ///   When several bitplanes are selected, the 32 bytes of each plane follow one another.
+    /// * `clip` - Whether the parts of the sprite past the screen edges are discarded instead of wrapped.
+    ///
+    /// # Returns
+    ///
+    /// A collision flag, with the same meaning as for `draw_sprite`.
+    pub(crate) fn draw_large_sprite(&mut self, coords: Coordinate, sprite: Vec<u8>, clip: bool) -> u8 {
+        let origin: Coordinate = (coords.0 % self.width(), coords.1 % self.height());
+        let planes: Vec<u8> = self.selected_planes().collect();
+        let mut collision = 0;
+        for (plane, data) in planes.into_iter().zip(sprite.chunks(32)) {
+            for (offset_row, row) in data.chunks(2).enumerate() {
+                let y = origin.1 + offset_row;
+                if clip && y >= self.height() {
+                    break;
+                }
+                for (half, byte) in row.iter().enumerate() {
+                    collision |= self.draw_sprite_row((origin.0 + half * 8, y), *byte, plane, clip);
+                }
+            }
+        }
+        collision
+    }
+
+    /// Moves the selected bitplanes of every pixel by the given offset, clearing the
+    /// pixels scrolled in from outside the screen.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let mask = self.plane_mask;
+        let source = self.video_buffer;
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&src_x) && (0..height).contains(&src_y) {
+                    source[src_y as usize][src_x as usize] & mask
+                } else {
+                    0
+                };
+                let pixel = &mut self.video_buffer[y as usize][x as usize];
+                *pixel = (*pixel & !mask) | moved;
+            }
+        }
+    }
+
+    /// Scrolls the display down by `rows` pixels (`00Cn`).
+    pub(crate) fn scroll_down(&mut self, rows: usize) {
+        self.scroll(0, rows as isize);
+    }
+
+    /// Scrolls the display up by `rows` pixels (XO-CHIP `00Dn`).
+    pub(crate) fn scroll_up(&mut self, rows: usize) {
+        self.scroll(0, -(rows as isize));
+    }
+
+    /// Scrolls the display right by 4 pixels (`00FB`).
+    pub(crate) fn scroll_right(&mut self) {
+        self.scroll(HORIZONTAL_SCROLL as isize, 0);
+    }
+
+    /// Scrolls the display left by 4 pixels (`00FC`).
+    pub(crate) fn scroll_left(&mut self) {
+        self.scroll(-(HORIZONTAL_SCROLL as isize), 0);
+    }
 }
@@ -0,0 +1,134 @@
+use std::fmt::Write;
+
+use crate::cpu::CPU;
+use crate::error::Chip8Error;
+
+/// When a headless run stops if the program does not halt first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunLimit {
+    /// Stop after this many frames.
+    Frames(u64),
+    /// Stop after this many instructions.
+    Cycles(u64),
+}
+
+/// A keypad key held down for some frames during a headless run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScriptedKey {
+    /// The frame at which the key is pressed.
+    pub frame: u64,
+    /// The keypad key, from 0x0 to 0xF.
+    pub key: u8,
+    /// The number of frames the key is held for.
+    pub duration: u64,
+}
+
+/// How a headless run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// The program halted with `00FD` or `0000`.
+    Halted { frames: u64 },
+    /// The limit was reached before the program halted.
+    Timeout { frames: u64 },
+    /// The program faulted.
+    Fault { frames: u64, error: Chip8Error },
+}
+
+impl RunOutcome {
+    /// Returns the number of frames run, including a partial last frame.
+    pub fn frames(&self) -> u64 {
+        match *self {
+            RunOutcome::Halted { frames } | RunOutcome::Timeout { frames } | RunOutcome::Fault { frames, .. } => {
+                frames
+            }
+        }
+    }
+}
+
+/// Runs a program as fast as possible, without a display or real-time pacing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeadlessRunner {
+    /// The number of instructions run per frame.
+    pub cycles_per_frame: u32,
+    /// When to stop if the program does not halt.
+    pub limit: RunLimit,
+    /// The keys to press, in any order.
+    pub keys: Vec<ScriptedKey>,
+}
+
+impl HeadlessRunner {
+    /// Creates a runner with no scripted keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles_per_frame` - The number of instructions run per frame.
+    /// * `limit` - When to stop if the program does not halt.
+    pub fn new(cycles_per_frame: u32, limit: RunLimit) -> Self {
+        HeadlessRunner {
+            cycles_per_frame,
+            limit,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Runs the CPU until it halts, faults or reaches the limit.
+    ///
+    /// The scripted keys are pressed and released at the start of their frames. With a cycle
+    /// limit the last frame may be shorter, its timers still ticking.
+    pub fn run(&self, cpu: &mut CPU) -> RunOutcome {
+        let mut frames = 0;
+        let mut cycles = 0;
+        loop {
+            if cpu.halt {
+                return RunOutcome::Halted { frames };
+            }
+            let budget = match self.limit {
+                RunLimit::Frames(limit) if frames >= limit => return RunOutcome::Timeout { frames },
+                RunLimit::Cycles(limit) if cycles >= limit => return RunOutcome::Timeout { frames },
+                RunLimit::Frames(_) => self.cycles_per_frame as u64,
+                RunLimit::Cycles(limit) => (limit - cycles).min(self.cycles_per_frame as u64),
+            };
+
+            for key in &self.keys {
+                if key.frame == frames {
+                    cpu.key_pressed(key.key as usize);
+                } else if key.frame + key.duration == frames {
+                    cpu.key_released(key.key as usize);
+                }
+            }
+
+            let result = cpu.run_frame(budget as u32);
+            frames += 1;
+            cycles += budget;
+            if let Err(error) = result {
+                return RunOutcome::Fault { frames, error };
+            }
+        }
+    }
+}
+
+/// Formats the registers, timers and stack of the CPU as text.
+pub fn register_dump(cpu: &CPU) -> String {
+    let memory = &cpu.memory;
+    let mut dump = String::new();
+    writeln!(
+        dump,
+        "PC: 0x{:04X}  SP: 0x{:02X}  I: 0x{:04X}  DT: 0x{:02X}  ST: 0x{:02X}",
+        memory.pc, memory.sp, memory.i, memory.dt, memory.st
+    )
+    .unwrap();
+    for (row, values) in memory.v.chunks(4).enumerate() {
+        let line: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(col, value)| format!("V{:X}: 0x{:02X}", row * 4 + col, value))
+            .collect();
+        writeln!(dump, "{}", line.join("  ")).unwrap();
+    }
+    let stack: Vec<String> = memory.stack[1..=memory.sp as usize]
+        .iter()
+        .map(|addr| format!("0x{:04X}", addr))
+        .collect();
+    writeln!(dump, "Stack: [{}]", stack.join(", ")).unwrap();
+    dump
+}
@@ -1,102 +1,339 @@
-pub(crate) enum Opcode {
+use std::fmt;
+
+/// A decoded Chip-8, SUPER-CHIP or XO-CHIP instruction, with all its operands.
+///
+/// Operands use the conventional names: `x` and `y` are register indices, `n` is a
+/// 4-bit nibble, `kk` is an 8-bit byte and `nnn` is a 12-bit address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// `0000` - Halts the CPU.
     Halt,
+    /// `00E0` - CLS
     ClearScreen,
+    /// `00EE` - RET
     Return,
-    JumpToAddress(u16),
-    CallAddress(u16),
-    SkipIfRegEqualsByte(u8),
-    SkipIfRegNotEqualsByte(u8),
-    SkipIfRegEqualsReg,
-    LoadByteIntoReg(u8),
-    AddByteToReg(u8),
-    LoadRegIntoReg,
-    OrRegWithReg,
-    AndRegWithReg,
-    XorRegWithReg,
-    AddRegToReg,
-    SubtractRegFromReg,
-    ShiftRight,
-    SubstractRegFromOtherReg,
-    ShiftLeft,
-    SkipIfRegNotEqualsReg,
-    LoadIndex(u16),
-    JumpToAddressPlusV0(u16),
-    RandomByte(u8),
-    DrawSprite(u8),
-    SkipIfKeyPressed,
-    SkipIfKeyNotPressed,
-    LoadDelayTimerIntoReg,
-    LoadKeyIntoReg,
-    LoadRegIntoDelayTimer,
-    LoadRegIntoSoundTimer,
-    AddRegToIndex,
-    LoadFontIntoReg,
-    LoadBCDIntoMem,
-    StoreRegsIntoMem,
-    LoadRegsFromMem,
-    Unknown,
+    /// `00Cn` - SCD n
+    ScrollDown { n: u8 },
+    /// `00Dn` - SCU n
+    ScrollUp { n: u8 },
+    /// `00FB` - SCR
+    ScrollRight,
+    /// `00FC` - SCL
+    ScrollLeft,
+    /// `00FD` - EXIT
+    Exit,
+    /// `00FE` - LOW
+    LowRes,
+    /// `00FF` - HIGH
+    HighRes,
+    /// `1nnn` - JP nnn
+    JumpToAddress { nnn: u16 },
+    /// `2nnn` - CALL nnn
+    CallAddress { nnn: u16 },
+    /// `3xkk` - SE Vx, kk
+    SkipIfRegEqualsByte { x: u8, kk: u8 },
+    /// `4xkk` - SNE Vx, kk
+    SkipIfRegNotEqualsByte { x: u8, kk: u8 },
+    /// `5xy0` - SE Vx, Vy
+    SkipIfRegEqualsReg { x: u8, y: u8 },
+    /// `5xy2` - SAVE Vx - Vy
+    StoreRegRangeIntoMem { x: u8, y: u8 },
+    /// `5xy3` - LOAD Vx - Vy
+    LoadRegRangeFromMem { x: u8, y: u8 },
+    /// `6xkk` - LD Vx, kk
+    LoadByteIntoReg { x: u8, kk: u8 },
+    /// `7xkk` - ADD Vx, kk
+    AddByteToReg { x: u8, kk: u8 },
+    /// `8xy0` - LD Vx, Vy
+    LoadRegIntoReg { x: u8, y: u8 },
+    /// `8xy1` - OR Vx, Vy
+    OrRegWithReg { x: u8, y: u8 },
+    /// `8xy2` - AND Vx, Vy
+    AndRegWithReg { x: u8, y: u8 },
+    /// `8xy3` - XOR Vx, Vy
+    XorRegWithReg { x: u8, y: u8 },
+    /// `8xy4` - ADD Vx, Vy
+    AddRegToReg { x: u8, y: u8 },
+    /// `8xy5` - SUB Vx, Vy
+    SubtractRegFromReg { x: u8, y: u8 },
+    /// `8xy6` - SHR Vx, Vy
+    ShiftRight { x: u8, y: u8 },
+    /// `8xy7` - SUBN Vx, Vy
+    SubtractRegFromOtherReg { x: u8, y: u8 },
+    /// `8xyE` - SHL Vx, Vy
+    ShiftLeft { x: u8, y: u8 },
+    /// `9xy0` - SNE Vx, Vy
+    SkipIfRegNotEqualsReg { x: u8, y: u8 },
+    /// `Annn` - LD I, nnn
+    LoadIndex { nnn: u16 },
+    /// `F000 nnnn` - LD I, LONG nnnn. The address is the word following the instruction.
+    LoadLongIndex,
+    /// `Bnnn` - JP V0, nnn
+    JumpToAddressPlusV0 { nnn: u16 },
+    /// `Cxkk` - RND Vx, kk
+    RandomByte { x: u8, kk: u8 },
+    /// `Dxyn` - DRW Vx, Vy, n
+    DrawSprite { x: u8, y: u8, n: u8 },
+    /// `Ex9E` - SKP Vx
+    SkipIfKeyPressed { x: u8 },
+    /// `ExA1` - SKNP Vx
+    SkipIfKeyNotPressed { x: u8 },
+    /// `Fx07` - LD Vx, DT
+    LoadDelayTimerIntoReg { x: u8 },
+    /// `Fx0A` - LD Vx, K
+    LoadKeyIntoReg { x: u8 },
+    /// `Fx15` - LD DT, Vx
+    LoadRegIntoDelayTimer { x: u8 },
+    /// `Fx18` - LD ST, Vx
+    LoadRegIntoSoundTimer { x: u8 },
+    /// `Fx1E` - ADD I, Vx
+    AddRegToIndex { x: u8 },
+    /// `Fx29` - LD F, Vx
+    LoadFontIntoReg { x: u8 },
+    /// `Fx30` - LD HF, Vx
+    LoadBigFontIntoReg { x: u8 },
+    /// `Fx33` - LD B, Vx
+    LoadBCDIntoMem { x: u8 },
+    /// `Fx55` - LD [I], Vx
+    StoreRegsIntoMem { x: u8 },
+    /// `Fx65` - LD Vx, [I]
+    LoadRegsFromMem { x: u8 },
+    /// `Fx75` - LD R, Vx
+    StoreRplFlags { x: u8 },
+    /// `Fx85` - LD Vx, R
+    LoadRplFlags { x: u8 },
+    /// `Fn01` - PLANE n
+    SelectPlanes { n: u8 },
+    /// `F002` - AUDIO
+    LoadAudioPattern,
+    /// `Fx3A` - LD PITCH, Vx
+    LoadRegIntoPitch { x: u8 },
+    /// Any opcode that does not decode to a known instruction.
+    Unknown(u16),
 }
 
-pub(crate) fn parse_opcode(opcode: u16) -> (Opcode, u8, u8) {
-    let reg_x = ((opcode & 0x0F00) >> 8) as u8;
-    let reg_y = ((opcode & 0x00F0) >> 4) as u8;
-    let k = (opcode & 0x00FF) as u8;
+/// Decodes an opcode into an `Instruction`.
+///
+/// Every opcode decodes to exactly one instruction, using `Instruction::Unknown` for the
+/// ones that have no meaning, so that `encode(&decode(opcode)) == opcode` always holds.
+///
+/// # Examples
+///
+/// ```rust
+/// use chip8::instructions::{decode, Instruction};
+///
+/// assert_eq!(decode(0x8124), Instruction::AddRegToReg { x: 1, y: 2 });
+/// ```
+pub fn decode(opcode: u16) -> Instruction {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let kk = (opcode & 0x00FF) as u8;
     let n = (opcode & 0x000F) as u8;
-    let addr = opcode & 0x0FFF;
-    println!("Executing opcode: {:04X}", opcode);
-    println!(
-        "addr: 0x{:03X}\treg_x: V{:01X}\treg_y: V{:01X}\tkk: 0x{:02X}\tnnn: 0x{:03X}",
-        addr, reg_x, reg_y, k, addr
-    );
-    let parsed_opcode = match opcode & 0xF000 {
-        0x0000 => match opcode & 0x00FF {
-            0x0000 => Opcode::Halt,
-            0x00E0 => Opcode::ClearScreen,
-            0x00EE => Opcode::Return,
-            _ => Opcode::Unknown,
+    let nnn = opcode & 0x0FFF;
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x0000 => Instruction::Halt,
+            0x00E0 => Instruction::ClearScreen,
+            0x00EE => Instruction::Return,
+            0x00C0..=0x00CF => Instruction::ScrollDown { n },
+            0x00D0..=0x00DF => Instruction::ScrollUp { n },
+            0x00FB => Instruction::ScrollRight,
+            0x00FC => Instruction::ScrollLeft,
+            0x00FD => Instruction::Exit,
+            0x00FE => Instruction::LowRes,
+            0x00FF => Instruction::HighRes,
+            _ => Instruction::Unknown(opcode),
+        },
+        0x1000 => Instruction::JumpToAddress { nnn },
+        0x2000 => Instruction::CallAddress { nnn },
+        0x3000 => Instruction::SkipIfRegEqualsByte { x, kk },
+        0x4000 => Instruction::SkipIfRegNotEqualsByte { x, kk },
+        0x5000 => match n {
+            0x0 => Instruction::SkipIfRegEqualsReg { x, y },
+            0x2 => Instruction::StoreRegRangeIntoMem { x, y },
+            0x3 => Instruction::LoadRegRangeFromMem { x, y },
+            _ => Instruction::Unknown(opcode),
+        },
+        0x6000 => Instruction::LoadByteIntoReg { x, kk },
+        0x7000 => Instruction::AddByteToReg { x, kk },
+        0x8000 => match n {
+            0x0 => Instruction::LoadRegIntoReg { x, y },
+            0x1 => Instruction::OrRegWithReg { x, y },
+            0x2 => Instruction::AndRegWithReg { x, y },
+            0x3 => Instruction::XorRegWithReg { x, y },
+            0x4 => Instruction::AddRegToReg { x, y },
+            0x5 => Instruction::SubtractRegFromReg { x, y },
+            0x6 => Instruction::ShiftRight { x, y },
+            0x7 => Instruction::SubtractRegFromOtherReg { x, y },
+            0xE => Instruction::ShiftLeft { x, y },
+            _ => Instruction::Unknown(opcode),
+        },
+        0x9000 => match n {
+            0x0 => Instruction::SkipIfRegNotEqualsReg { x, y },
+            _ => Instruction::Unknown(opcode),
         },
-        0x1000 => Opcode::JumpToAddress(addr),
-        0x2000 => Opcode::CallAddress(addr),
-        0x3000 => Opcode::SkipIfRegEqualsByte(k),
-        0x4000 => Opcode::SkipIfRegNotEqualsByte(k),
-        0x5000 => Opcode::SkipIfRegEqualsReg,
-        0x6000 => Opcode::LoadByteIntoReg(k),
-        0x7000 => Opcode::AddByteToReg(k),
-        0x8000 => match opcode & 0x000F {
-            0x0000 => Opcode::LoadRegIntoReg,
-            0x0001 => Opcode::OrRegWithReg,
-            0x0002 => Opcode::AndRegWithReg,
-            0x0003 => Opcode::XorRegWithReg,
-            0x0004 => Opcode::AddRegToReg,
-            0x0005 => Opcode::SubtractRegFromReg,
-            0x0006 => Opcode::ShiftRight,
-            0x0007 => Opcode::SubstractRegFromOtherReg,
-            0x000E => Opcode::ShiftLeft,
-            _ => Opcode::Unknown,
+        0xA000 => Instruction::LoadIndex { nnn },
+        0xB000 => Instruction::JumpToAddressPlusV0 { nnn },
+        0xC000 => Instruction::RandomByte { x, kk },
+        0xD000 => Instruction::DrawSprite { x, y, n },
+        0xE000 => match kk {
+            0x9E => Instruction::SkipIfKeyPressed { x },
+            0xA1 => Instruction::SkipIfKeyNotPressed { x },
+            _ => Instruction::Unknown(opcode),
         },
-        0x9000 => Opcode::SkipIfRegNotEqualsReg,
-        0xA000 => Opcode::LoadIndex(addr),
-        0xB000 => Opcode::JumpToAddressPlusV0(addr),
-        0xC000 => Opcode::RandomByte(k),
-        0xD000 => Opcode::DrawSprite(n),
-        0xE000 => match opcode & 0x00FF {
-            0x009E => Opcode::SkipIfKeyPressed,
-            0x00A1 => Opcode::SkipIfKeyNotPressed,
-            _ => Opcode::Unknown,
+        0xF000 => match kk {
+            0x00 if x == 0 => Instruction::LoadLongIndex,
+            0x01 => Instruction::SelectPlanes { n: x },
+            0x02 if x == 0 => Instruction::LoadAudioPattern,
+            0x07 => Instruction::LoadDelayTimerIntoReg { x },
+            0x0A => Instruction::LoadKeyIntoReg { x },
+            0x15 => Instruction::LoadRegIntoDelayTimer { x },
+            0x18 => Instruction::LoadRegIntoSoundTimer { x },
+            0x1E => Instruction::AddRegToIndex { x },
+            0x29 => Instruction::LoadFontIntoReg { x },
+            0x30 => Instruction::LoadBigFontIntoReg { x },
+            0x33 => Instruction::LoadBCDIntoMem { x },
+            0x3A => Instruction::LoadRegIntoPitch { x },
+            0x55 => Instruction::StoreRegsIntoMem { x },
+            0x65 => Instruction::LoadRegsFromMem { x },
+            0x75 => Instruction::StoreRplFlags { x },
+            0x85 => Instruction::LoadRplFlags { x },
+            _ => Instruction::Unknown(opcode),
         },
-        0xF000 => match opcode & 0x00FF {
-            0x0007 => Opcode::LoadDelayTimerIntoReg,
-            0x000A => Opcode::LoadKeyIntoReg,
-            0x0015 => Opcode::LoadRegIntoDelayTimer,
-            0x0018 => Opcode::LoadRegIntoSoundTimer,
-            0x001E => Opcode::AddRegToIndex,
-            0x0029 => Opcode::LoadFontIntoReg,
-            0x0033 => Opcode::LoadBCDIntoMem,
-            0x0055 => Opcode::StoreRegsIntoMem,
-            0x0065 => Opcode::LoadRegsFromMem,
-            _ => Opcode::Unknown,
+        _ => Instruction::Unknown(opcode),
+    }
+}
+
+/// Encodes an `Instruction` into its opcode.
+///
+/// Operands wider than their field are truncated, e.g. an `x` of `0x12` encodes as `0x2`.
+///
+/// # Examples
+///
+/// ```rust
+/// use chip8::instructions::{encode, Instruction};
+///
+/// assert_eq!(encode(&Instruction::DrawSprite { x: 0, y: 1, n: 5 }), 0xD015);
+/// ```
+pub fn encode(instruction: &Instruction) -> u16 {
+    let x = |x: u8| ((x & 0xF) as u16) << 8;
+    let y = |y: u8| ((y & 0xF) as u16) << 4;
+    let n = |n: u8| (n & 0xF) as u16;
+    let nnn = |nnn: u16| nnn & 0x0FFF;
+    match *instruction {
+        Instruction::Halt => 0x0000,
+        Instruction::ClearScreen => 0x00E0,
+        Instruction::Return => 0x00EE,
+        Instruction::ScrollDown { n: rows } => 0x00C0 | n(rows),
+        Instruction::ScrollUp { n: rows } => 0x00D0 | n(rows),
+        Instruction::ScrollRight => 0x00FB,
+        Instruction::ScrollLeft => 0x00FC,
+        Instruction::Exit => 0x00FD,
+        Instruction::LowRes => 0x00FE,
+        Instruction::HighRes => 0x00FF,
+        Instruction::JumpToAddress { nnn: addr } => 0x1000 | nnn(addr),
+        Instruction::CallAddress { nnn: addr } => 0x2000 | nnn(addr),
+        Instruction::SkipIfRegEqualsByte { x: vx, kk } => 0x3000 | x(vx) | kk as u16,
+        Instruction::SkipIfRegNotEqualsByte { x: vx, kk } => 0x4000 | x(vx) | kk as u16,
+        Instruction::SkipIfRegEqualsReg { x: vx, y: vy } => 0x5000 | x(vx) | y(vy),
+        Instruction::StoreRegRangeIntoMem { x: vx, y: vy } => 0x5002 | x(vx) | y(vy),
+        Instruction::LoadRegRangeFromMem { x: vx, y: vy } => 0x5003 | x(vx) | y(vy),
+        Instruction::LoadByteIntoReg { x: vx, kk } => 0x6000 | x(vx) | kk as u16,
+        Instruction::AddByteToReg { x: vx, kk } => 0x7000 | x(vx) | kk as u16,
+        Instruction::LoadRegIntoReg { x: vx, y: vy } => 0x8000 | x(vx) | y(vy),
+        Instruction::OrRegWithReg { x: vx, y: vy } => 0x8001 | x(vx) | y(vy),
+        Instruction::AndRegWithReg { x: vx, y: vy } => 0x8002 | x(vx) | y(vy),
+        Instruction::XorRegWithReg { x: vx, y: vy } => 0x8003 | x(vx) | y(vy),
+        Instruction::AddRegToReg { x: vx, y: vy } => 0x8004 | x(vx) | y(vy),
+        Instruction::SubtractRegFromReg { x: vx, y: vy } => 0x8005 | x(vx) | y(vy),
+        Instruction::ShiftRight { x: vx, y: vy } => 0x8006 | x(vx) | y(vy),
+        Instruction::SubtractRegFromOtherReg { x: vx, y: vy } => 0x8007 | x(vx) | y(vy),
+        Instruction::ShiftLeft { x: vx, y: vy } => 0x800E | x(vx) | y(vy),
+        Instruction::SkipIfRegNotEqualsReg { x: vx, y: vy } => 0x9000 | x(vx) | y(vy),
+        Instruction::LoadIndex { nnn: addr } => 0xA000 | nnn(addr),
+        Instruction::LoadLongIndex => 0xF000,
+        Instruction::JumpToAddressPlusV0 { nnn: addr } => 0xB000 | nnn(addr),
+        Instruction::RandomByte { x: vx, kk } => 0xC000 | x(vx) | kk as u16,
+        Instruction::DrawSprite { x: vx, y: vy, n: rows } => 0xD000 | x(vx) | y(vy) | n(rows),
+        Instruction::SkipIfKeyPressed { x: vx } => 0xE09E | x(vx),
+        Instruction::SkipIfKeyNotPressed { x: vx } => 0xE0A1 | x(vx),
+        Instruction::LoadDelayTimerIntoReg { x: vx } => 0xF007 | x(vx),
+        Instruction::LoadKeyIntoReg { x: vx } => 0xF00A | x(vx),
+        Instruction::LoadRegIntoDelayTimer { x: vx } => 0xF015 | x(vx),
+        Instruction::LoadRegIntoSoundTimer { x: vx } => 0xF018 | x(vx),
+        Instruction::AddRegToIndex { x: vx } => 0xF01E | x(vx),
+        Instruction::LoadFontIntoReg { x: vx } => 0xF029 | x(vx),
+        Instruction::LoadBigFontIntoReg { x: vx } => 0xF030 | x(vx),
+        Instruction::LoadBCDIntoMem { x: vx } => 0xF033 | x(vx),
+        Instruction::StoreRegsIntoMem { x: vx } => 0xF055 | x(vx),
+        Instruction::LoadRegsFromMem { x: vx } => 0xF065 | x(vx),
+        Instruction::StoreRplFlags { x: vx } => 0xF075 | x(vx),
+        Instruction::LoadRplFlags { x: vx } => 0xF085 | x(vx),
+        Instruction::SelectPlanes { n: planes } => 0xF001 | x(planes),
+        Instruction::LoadAudioPattern => 0xF002,
+        Instruction::LoadRegIntoPitch { x: vx } => 0xF03A | x(vx),
+        Instruction::Unknown(opcode) => opcode,
+    }
+}
+
+impl fmt::Display for Instruction {
+    /// Formats the instruction in the conventional mnemonic syntax, e.g. `LD V0, 0x02`.
+    ///
+    /// Opcodes without a meaning are formatted as a `DW` data word.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::Halt => write!(f, "HALT"),
+            Instruction::ClearScreen => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::ScrollDown { n } => write!(f, "SCD 0x{:X}", n),
+            Instruction::ScrollUp { n } => write!(f, "SCU 0x{:X}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::JumpToAddress { nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::CallAddress { nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::SkipIfRegEqualsByte { x, kk } => write!(f, "SE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipIfRegNotEqualsByte { x, kk } => write!(f, "SNE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipIfRegEqualsReg { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::StoreRegRangeIntoMem { x, y } => write!(f, "SAVE V{:X} - V{:X}", x, y),
+            Instruction::LoadRegRangeFromMem { x, y } => write!(f, "LOAD V{:X} - V{:X}", x, y),
+            Instruction::LoadByteIntoReg { x, kk } => write!(f, "LD V{:X}, 0x{:02X}", x, kk),
+            Instruction::AddByteToReg { x, kk } => write!(f, "ADD V{:X}, 0x{:02X}", x, kk),
+            Instruction::LoadRegIntoReg { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::OrRegWithReg { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::AndRegWithReg { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::XorRegWithReg { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddRegToReg { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::SubtractRegFromReg { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubtractRegFromOtherReg { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipIfRegNotEqualsReg { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadIndex { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::LoadLongIndex => write!(f, "LD I, LONG"),
+            Instruction::JumpToAddressPlusV0 { nnn } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::RandomByte { x, kk } => write!(f, "RND V{:X}, 0x{:02X}", x, kk),
+            Instruction::DrawSprite { x, y, n } => write!(f, "DRW V{:X}, V{:X}, 0x{:X}", x, y, n),
+            Instruction::SkipIfKeyPressed { x } => write!(f, "SKP V{:X}", x),
+            Instruction::SkipIfKeyNotPressed { x } => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadDelayTimerIntoReg { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::LoadKeyIntoReg { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::LoadRegIntoDelayTimer { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::LoadRegIntoSoundTimer { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddRegToIndex { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFontIntoReg { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::LoadBigFontIntoReg { x } => write!(f, "LD HF, V{:X}", x),
+            Instruction::LoadBCDIntoMem { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::StoreRegsIntoMem { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegsFromMem { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::StoreRplFlags { x } => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadRplFlags { x } => write!(f, "LD V{:X}, R", x),
+            Instruction::SelectPlanes { n } => write!(f, "PLANE 0x{:X}", n),
+            Instruction::LoadAudioPattern => write!(f, "AUDIO"),
+            Instruction::LoadRegIntoPitch { x } => write!(f, "LD PITCH, V{:X}", x),
+            Instruction::Unknown(opcode) => write!(f, "DW 0x{:04X}", opcode),
         }
-        _ => Opcode::Unknown,
-    };
-    (parsed_opcode, reg_x, reg_y)
+    }
 }
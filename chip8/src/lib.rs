@@ -1,4 +1,24 @@
+pub mod asm;
 pub mod cpu;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod frontend;
 pub mod memory;
+pub mod movie;
+pub mod observer;
+pub mod octo;
+pub mod gdbstub;
 pub mod gpu;
+pub mod hash;
+pub mod headless;
 pub mod instructions;
+pub mod json;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod romdb;
+pub mod savestate;
+pub mod screenshot;
+pub mod sound;
+pub mod trace;
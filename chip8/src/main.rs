@@ -1,7 +1,71 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::net::TcpListener;
+use std::process;
 use std::time::{Instant, Duration};
+use std::path::Path;
+use chip8::asm::{assemble, assemble_file};
 use chip8::cpu::CPU;
+use chip8::debugger::{Debugger, Reply};
+use chip8::disasm::disassemble;
+use chip8::hash::{sha1, to_hex};
+use chip8::gdbstub::{GdbStub, DEFAULT_GDB_PORT};
+use chip8::headless::{register_dump, HeadlessRunner, RunLimit, RunOutcome, ScriptedKey};
+use chip8::movie::{Movie, MoviePlayer};
+use chip8::quirks::Platform;
+use chip8::romdb::{default_config_dir, local_settings_path, RomDatabase, DATABASE_FILE};
+use chip8::rng::SeededRandom;
+use chip8::screenshot::{ImageFormat, Screenshot};
+use chip8::trace::{Categories, FileSink, Level, StderrSink, TraceSink, Tracer};
 
 const TARGET_CLK: u64 = 16;
+const USAGE: &str = "Usage: chip8 [disasm <rom> [--origin <addr>] | asm <source> [-o <rom>] | play <rom> <movie> | run <rom> [options] | debug <rom> [--platform <name>] | gdb <rom> [--port <n>] [--platform <name>] | romdb import <programs.json> | romdb lookup <rom>]
+
+Options of run:
+    --frames <n>            Stop after n frames (default 600)
+    --cycles <n>            Stop after n instructions instead
+    --cycles-per-frame <n>  Instructions per 60 Hz frame (default 25)
+    --platform <name>       vip, chip48, schip or xochip (default: the CPU defaults)
+    --seed <n>              Seed the random generator for reproducible runs
+    --key <frame>:<key>[:<frames>]
+                            Hold a keypad key (hex) from a frame, for 1 frame by default
+    --screen <file>         Write the final display as .txt, .pbm or .png
+    --scale <n>             Pixel size of PNG screenshots (default 1)
+    --ascii                 Print the final display to stdout
+    --registers <file|->    Write a register dump
+    --trace <file|->        Write a trace, to stderr for -
+    --trace-level <level>   error, warn, info, debug or trace (default trace)
+    --trace-categories <list>
+                            Comma-separated decode, execute, memory, input (default all)
+
+Exit codes of run: 0 halted, 1 error, 2 timeout, 3 fault
+
+romdb import copies the chip-8-database programs.json into the config directory
+($CHIP8_CONFIG_DIR, else $XDG_CONFIG_HOME/chip8, else ~/.config/chip8).
+romdb lookup prints the SHA-1 of a ROM and the settings the frontends run it with.";
+
+/// The exit code of `run` when the limit is reached before the program halts.
+const EXIT_TIMEOUT: i32 = 2;
+/// The exit code of `run` when the program faults.
+const EXIT_FAULT: i32 = 3;
+/// The default number of frames `run` stops after.
+const DEFAULT_RUN_FRAMES: u64 = 600;
+/// The default number of instructions `run` executes per frame, a 1500 Hz clock.
+const DEFAULT_CYCLES_PER_FRAME: u32 = 25;
+
+/// The program run when no subcommand is given.
+const DEMO_PROGRAM: &str = "
+    LD I, 0xFF0
+    LD V0, 0x02
+    LD V1, 0x0A
+    ADD V0, V1
+    LD V2, V0
+    ADD V2, V1
+    LD V3, 0x16
+    XOR V3, V2
+    LD [I], V0
+";
 
 /// Wait for the next cycle until the target clock is reached.
 ///
@@ -18,27 +82,480 @@ fn wait_for_next_cycle(target_clock: u64, last_timestamp: &mut Instant) {
     *last_timestamp = Instant::now();
 }
 
+/// Parses an address given either in decimal or as hexadecimal with a `0x` prefix.
+///
+/// # Arguments
+///
+/// * `value` - The address to parse.
+///
+/// # Returns
+///
+/// * `Option<u16>` - The address, or None if it is not a valid number.
+fn parse_addr(value: &str) -> Option<u16> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Prints the disassembly of a ROM file.
+///
+/// # Arguments
+///
+/// * `args` - The arguments following the `disasm` subcommand.
+fn run_disasm(args: &[String]) {
+    let mut rom_path = None;
+    let mut origin = 0x200;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--origin" => match args.next().and_then(|value| parse_addr(value)) {
+                Some(addr) => origin = addr,
+                None => usage_error("--origin expects an address"),
+            },
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| usage_error("missing ROM path"));
+
+    match fs::read(rom_path) {
+        Ok(rom) => print!("{}", disassemble(&rom, origin)),
+        Err(e) => {
+            eprintln!("Error reading {}: {}", rom_path, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Assembles a source file and writes the ROM next to it, or to the path given with `-o`.
+///
+/// # Arguments
+///
+/// * `args` - The arguments following the `asm` subcommand.
+fn run_asm(args: &[String]) {
+    let mut source_path = None;
+    let mut output_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => match args.next() {
+                Some(path) => output_path = Some(path.clone()),
+                None => usage_error("-o expects a path"),
+            },
+            _ if source_path.is_none() => source_path = Some(arg),
+            _ => usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    let source_path = source_path.unwrap_or_else(|| usage_error("missing source path"));
+    let output_path = output_path.unwrap_or_else(|| {
+        Path::new(source_path).with_extension("ch8").display().to_string()
+    });
+
+    let rom = match assemble_file(source_path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = fs::write(&output_path, &rom) {
+        eprintln!("Error writing {}: {}", output_path, e);
+        process::exit(1);
+    }
+    println!("Wrote {} bytes to {}", rom.len(), output_path);
+}
+
+/// Plays a movie back without a display and reports whether it stayed in sync.
+///
+/// # Arguments
+///
+/// * `args` - The arguments following the `play` subcommand.
+fn run_play(args: &[String]) {
+    let [rom_path, movie_path] = args else {
+        usage_error("play expects a ROM and a movie");
+    };
+    let rom = fs::read(rom_path).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", rom_path, e);
+        process::exit(1);
+    });
+    let movie = fs::read_to_string(movie_path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| Movie::parse(&text).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            eprintln!("Error reading {}: {}", movie_path, e);
+            process::exit(1);
+        });
+
+    // Booting the movie sets the platform, quirks and load address it was recorded with
+    let mut cpu = CPU::new();
+    let result = MoviePlayer::start(movie, &mut cpu, &rom).and_then(|mut player| {
+        while player.run_frame(&mut cpu)? {}
+        Ok(player.frame())
+    });
+    match result {
+        Ok(frames) => println!("Played {} frames, in sync", frames),
+        Err(e) => {
+            eprintln!("{}: {}", movie_path, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Parses a number given either in decimal or as hexadecimal with a `0x` prefix.
+///
+/// # Arguments
+///
+/// * `value` - The number to parse.
+/// * `option` - The option the value belongs to, for the error message.
+fn parse_number<T: TryFrom<u64>>(value: Option<&String>, option: &str) -> T {
+    value
+        .and_then(|value| match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => value.parse().ok(),
+        })
+        .and_then(|value| T::try_from(value).ok())
+        .unwrap_or_else(|| usage_error(&format!("{} expects a number", option)))
+}
+
+/// Parses a scripted key given as `<frame>:<key>[:<frames>]`, the key in hexadecimal.
+///
+/// # Returns
+///
+/// * `Option<ScriptedKey>` - The key, or None if the value is malformed.
+fn parse_key(value: &str) -> Option<ScriptedKey> {
+    let mut fields = value.split(':');
+    let frame = fields.next()?.parse().ok()?;
+    let key = u8::from_str_radix(fields.next()?, 16).ok().filter(|&key| key < 16)?;
+    let duration = match fields.next() {
+        Some(duration) => duration.parse().ok().filter(|&duration| duration > 0)?,
+        None => 1,
+    };
+    fields.next().is_none().then_some(ScriptedKey { frame, key, duration })
+}
+
+/// Writes output of the `run` subcommand to a file, or to stdout for `-`.
+fn write_output(path: &str, data: &[u8]) {
+    let result = if path == "-" {
+        io::stdout().write_all(data)
+    } else {
+        fs::write(path, data)
+    };
+    if let Err(e) = result {
+        eprintln!("Error writing {}: {}", path, e);
+        process::exit(1);
+    }
+}
+
+/// Runs a ROM without a display, then writes the requested screenshot and register dump.
+///
+/// Exits with 0 if the program halted, `EXIT_TIMEOUT` if it was still running at the limit
+/// and `EXIT_FAULT` if it faulted.
+///
+/// # Arguments
+///
+/// * `args` - The arguments following the `run` subcommand.
+fn run_headless(args: &[String]) {
+    let mut rom_path = None;
+    let mut runner = HeadlessRunner::new(DEFAULT_CYCLES_PER_FRAME, RunLimit::Frames(DEFAULT_RUN_FRAMES));
+    let mut platform = None;
+    let mut seed = None;
+    let mut screen_path = None;
+    let mut scale = 1;
+    let mut ascii = false;
+    let mut registers_path = None;
+    let mut trace_path = None;
+    let mut trace_level = Level::Trace;
+    let mut trace_categories = Categories::ALL;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => runner.limit = RunLimit::Frames(parse_number(args.next(), arg)),
+            "--cycles" => runner.limit = RunLimit::Cycles(parse_number(args.next(), arg)),
+            "--cycles-per-frame" => runner.cycles_per_frame = parse_number(args.next(), arg),
+            "--platform" => match args.next().and_then(|name| Platform::from_name(name)) {
+                Some(value) => platform = Some(value),
+                None => usage_error("--platform expects vip, chip48, schip or xochip"),
+            },
+            "--seed" => seed = Some(parse_number::<u64>(args.next(), arg)),
+            "--key" => match args.next().and_then(|value| parse_key(value)) {
+                Some(key) => runner.keys.push(key),
+                None => usage_error("--key expects <frame>:<key>[:<frames>]"),
+            },
+            "--screen" => match args.next() {
+                Some(path) if ImageFormat::from_path(path).is_some() => screen_path = Some(path.clone()),
+                _ => usage_error("--screen expects a .txt, .pbm or .png path"),
+            },
+            "--scale" => scale = parse_number(args.next(), arg),
+            "--ascii" => ascii = true,
+            "--registers" => match args.next() {
+                Some(path) => registers_path = Some(path.clone()),
+                None => usage_error("--registers expects a path"),
+            },
+            "--trace" => match args.next() {
+                Some(path) => trace_path = Some(path.clone()),
+                None => usage_error("--trace expects a path"),
+            },
+            "--trace-level" => match args.next().and_then(|name| Level::from_name(name)) {
+                Some(level) => trace_level = level,
+                None => usage_error("--trace-level expects error, warn, info, debug or trace"),
+            },
+            "--trace-categories" => match args.next().and_then(|names| Categories::from_names(names)) {
+                Some(categories) => trace_categories = categories,
+                None => usage_error("--trace-categories expects a list of decode, execute, memory, input"),
+            },
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| usage_error("missing ROM path"));
+    let rom = fs::read(rom_path).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", rom_path, e);
+        process::exit(1);
+    });
+
+    let mut cpu = platform.map_or_else(CPU::new, CPU::with_platform);
+    if let Some(seed) = seed {
+        cpu.set_rng(Box::new(SeededRandom::new(seed)));
+    }
+    if let Some(path) = trace_path {
+        let sink: Box<dyn TraceSink> = if path == "-" {
+            Box::new(StderrSink)
+        } else {
+            Box::new(FileSink::create(&path).unwrap_or_else(|e| {
+                eprintln!("Error creating {}: {}", path, e);
+                process::exit(1);
+            }))
+        };
+        cpu.set_tracer(Tracer::new(sink, trace_level, trace_categories));
+    }
+    if let Err(e) = cpu.load_program(&rom) {
+        eprintln!("Error loading {}: {}", rom_path, e);
+        process::exit(1);
+    }
+
+    let outcome = runner.run(&mut cpu);
+    // The exit codes below skip destructors, so the trace file is flushed here
+    cpu.tracer_mut().flush();
+    let screenshot = Screenshot::capture(cpu.get_gpu());
+    if ascii {
+        print!("{}", screenshot.to_ascii());
+    }
+    if let Some(path) = screen_path {
+        let format = ImageFormat::from_path(&path).unwrap();
+        write_output(&path, &screenshot.encode(format, scale));
+    }
+    if let Some(path) = registers_path {
+        write_output(&path, register_dump(&cpu).as_bytes());
+    }
+
+    match outcome {
+        RunOutcome::Halted { frames } => eprintln!("Halted after {} frames", frames),
+        RunOutcome::Timeout { frames } => {
+            eprintln!("Still running after {} frames", frames);
+            process::exit(EXIT_TIMEOUT);
+        }
+        RunOutcome::Fault { frames, error } => {
+            eprintln!("Fault in frame {}: {}", frames, error);
+            process::exit(EXIT_FAULT);
+        }
+    }
+}
+
+/// Runs a ROM under the interactive debugger, reading commands from stdin.
+///
+/// # Arguments
+///
+/// * `args` - The arguments following the `debug` subcommand.
+fn run_debugger(args: &[String]) {
+    let mut rom_path = None;
+    let mut platform = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => match args.next().and_then(|name| Platform::from_name(name)) {
+                Some(value) => platform = Some(value),
+                None => usage_error("--platform expects vip, chip48, schip or xochip"),
+            },
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| usage_error("missing ROM path"));
+    let rom = fs::read(rom_path).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", rom_path, e);
+        process::exit(1);
+    });
+    let mut cpu = platform.map_or_else(CPU::new, CPU::with_platform);
+    if let Err(e) = cpu.load_program(&rom) {
+        eprintln!("Error loading {}: {}", rom_path, e);
+        process::exit(1);
+    }
+
+    let mut debugger = Debugger::new(DEFAULT_CYCLES_PER_FRAME);
+    println!("Debugging {}, type 'help' for the commands", rom_path);
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("(chip8) ");
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        match debugger.execute(&mut cpu, &line) {
+            Ok(Reply::Output(text)) => print!("{}", text),
+            Ok(Reply::Quit) => break,
+            Err(e) => println!("Error: {}", e),
+        }
+    }
+}
+
+/// Waits for a GDB client on a local port and serves it the ROM.
+///
+/// # Arguments
+///
+/// * `args` - The arguments following the `gdb` subcommand.
+fn run_gdb(args: &[String]) {
+    let mut rom_path = None;
+    let mut port = DEFAULT_GDB_PORT;
+    let mut platform = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => port = parse_number(args.next(), arg),
+            "--platform" => match args.next().and_then(|name| Platform::from_name(name)) {
+                Some(value) => platform = Some(value),
+                None => usage_error("--platform expects vip, chip48, schip or xochip"),
+            },
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| usage_error("missing ROM path"));
+    let rom = fs::read(rom_path).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", rom_path, e);
+        process::exit(1);
+    });
+    let mut cpu = platform.map_or_else(CPU::new, CPU::with_platform);
+    if let Err(e) = cpu.load_program(&rom) {
+        eprintln!("Error loading {}: {}", rom_path, e);
+        process::exit(1);
+    }
+
+    let result = TcpListener::bind(("127.0.0.1", port)).and_then(|listener| {
+        println!("Waiting for a GDB client on 127.0.0.1:{}", port);
+        let (stream, client) = listener.accept()?;
+        println!("Client connected from {}", client);
+        GdbStub::new(DEFAULT_CYCLES_PER_FRAME).serve(&mut cpu, stream)
+    });
+    if let Err(e) = result {
+        eprintln!("GDB server error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Imports the community ROM database, or prints the settings of a ROM.
+///
+/// # Arguments
+///
+/// * `args` - The arguments following the `romdb` subcommand.
+fn run_romdb(args: &[String]) {
+    let (command, path) = match args {
+        [command, path] => (command.as_str(), path),
+        _ => usage_error("romdb expects import <programs.json> or lookup <rom>"),
+    };
+    let config_dir = default_config_dir().unwrap_or_else(|| {
+        eprintln!("Error: no config directory, set CHIP8_CONFIG_DIR");
+        process::exit(1);
+    });
+    match command {
+        "import" => {
+            let text = fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("Error reading {}: {}", path, e);
+                process::exit(1);
+            });
+            let count = RomDatabase::new().import_chip8_database(&text).unwrap_or_else(|e| {
+                eprintln!("Error importing {}: {}", path, e);
+                process::exit(1);
+            });
+            let destination = config_dir.join(DATABASE_FILE);
+            if let Err(e) = fs::create_dir_all(&config_dir).and_then(|_| fs::write(&destination, text)) {
+                eprintln!("Error writing {}: {}", destination.display(), e);
+                process::exit(1);
+            }
+            println!("Imported {} ROMs into {}", count, destination.display());
+        }
+        "lookup" => {
+            let rom = fs::read(path).unwrap_or_else(|e| {
+                eprintln!("Error reading {}: {}", path, e);
+                process::exit(1);
+            });
+            let mut database = RomDatabase::new();
+            if let Err(e) = database.load_config_dir(&config_dir) {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+            let hash = sha1(&rom);
+            println!("sha1 = {}", to_hex(&hash));
+            match database.lookup(&hash) {
+                Some(settings) => print!("{}", settings),
+                None => println!("No settings, add them to {}", local_settings_path(&config_dir, &hash).display()),
+            }
+        }
+        _ => usage_error(&format!("unknown romdb command '{}'", command)),
+    }
+}
+
+/// Prints an error followed by the usage and exits.
+///
+/// # Arguments
+///
+/// * `message` - The error to print.
+fn usage_error(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => run_demo(),
+        Some("disasm") => run_disasm(&args[1..]),
+        Some("asm") => run_asm(&args[1..]),
+        Some("play") => run_play(&args[1..]),
+        Some("run") => run_headless(&args[1..]),
+        Some("debug") => run_debugger(&args[1..]),
+        Some("gdb") => run_gdb(&args[1..]),
+        Some("romdb") => run_romdb(&args[1..]),
+        Some("help" | "--help" | "-h") => println!("{}", USAGE),
+        Some(command) => usage_error(&format!("unknown command '{}'", command)),
+    }
+}
+
+/// Runs a small built-in program and dumps the registers and memory once it halts.
+fn run_demo() {
     let mut cpu = CPU::new();
-    let program = [
-        0xAF, 0xF0, /* LD I, 0xFF0 */
-        0x60, 0x02, /* LD V0, 0x02 */
-        0x61, 0x0A, /* LD V1, 0x0A */
-        0x80, 0x14, /* ADD V0, V1 */
-        0x82, 0x00, /* LD V2, V0 */
-        0x82, 0x14, /* ADD V2, V1 */
-        0x63, 0x16, /* LD V3, 0x16 */
-        0x83, 0x23, /* XOR V3, V2 */
-        0xF0, 0x55, /* LD [I], Vx */
-    ];
-
-    cpu.load_program(&program);
+    let program = match assemble(DEMO_PROGRAM) {
+        Ok(program) => program,
+        Err(e) => {
+            println!("Error assembling program: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = cpu.load_program(&program) {
+        println!("Error loading program: {}", e);
+        return;
+    }
     let mut counter = 0;
     println!("Sprites:");
     cpu.print_memory_region(0x0, 0x50, 5);
     'main: loop {
         if !cpu.halt {
-            cpu.cycle();
+            if let Err(e) = cpu.cycle() {
+                println!("{}", e);
+                cpu.halt = true;
+            }
         } else if counter < 0 {
             cpu.reset();
             counter += 1;
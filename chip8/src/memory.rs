@@ -1,10 +1,31 @@
+use crate::error::MemoryError;
+
 /// The size of the memory in bytes.
 pub const MEM_SIZE: usize = 0x1000;
 
-/// The memory struct, representing the 4KB of RAM available to the Chip-8.
+/// The size of the XO-CHIP memory in bytes.
+pub const XO_MEM_SIZE: usize = 0x10000;
+
+/// The size in bytes of the XO-CHIP audio pattern buffer.
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+
+/// The XO-CHIP pitch register value that plays the audio pattern at 4000 Hz.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// The address of the small 4x5 hexadecimal font.
+pub const FONT_ADDR: u16 = 0x0;
+
+/// The address of the SUPER-CHIP 8x10 hexadecimal font.
+pub const BIG_FONT_ADDR: u16 = 0x50;
+
+/// The number of SUPER-CHIP RPL user flags.
+pub const RPL_FLAGS: usize = 16;
+
+/// The memory struct, representing the 4KB of RAM available to the Chip-8,
+/// or the 64KB available to XO-CHIP.
 pub struct Memory {
     /// The memory, represented as an array of bytes.
-    pub memory: [u8; MEM_SIZE],
+    pub memory: Vec<u8>,
 
     /// The stack, represented as an array of 16-bit words.
     pub stack: [u16; 0x100],
@@ -26,13 +47,34 @@ pub struct Memory {
 
     /// The sound timer register, ST.
     pub st: u8,
+
+    /// The SUPER-CHIP RPL user flags, persisted across resets.
+    pub rpl: [u8; RPL_FLAGS],
+
+    /// The XO-CHIP audio pattern buffer, a 128 sample 1-bit waveform loaded by `F002`.
+    pub audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+
+    /// Whether `F002` has loaded an audio pattern. Until then the buzzer plays a plain tone.
+    pub audio_pattern_loaded: bool,
+
+    /// The XO-CHIP pitch register, set by `Fx3A`.
+    pub pitch: u8,
 }
 
 impl Memory {
     /// Creates a new `Memory` instance.
     pub(crate) fn new() -> Memory {
+        Memory::with_size(MEM_SIZE)
+    }
+
+    /// Creates a new `Memory` instance with `size` bytes of RAM.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size of the memory, usually `MEM_SIZE` or `XO_MEM_SIZE`.
+    pub(crate) fn with_size(size: usize) -> Memory {
         let mut mem = Memory {
-            memory: [0; MEM_SIZE],
+            memory: vec![0; size],
             stack: [0; 0x100],
             pc: 0x200,
             sp: 0,
@@ -40,6 +82,10 @@ impl Memory {
             i: 0,
             dt: 0,
             st: 0,
+            rpl: [0; RPL_FLAGS],
+            audio_pattern: [0; AUDIO_PATTERN_SIZE],
+            audio_pattern_loaded: false,
+            pitch: DEFAULT_PITCH,
         };
         mem.load_sprites();
         mem
@@ -53,9 +99,12 @@ impl Memory {
     ///
     /// # Returns
     ///
-    /// The byte at the specified address.
-    pub(crate) fn load(&self, addr: u16) -> u8 {
-        self.memory[addr as usize]
+    /// The byte at the specified address, or `MemoryError::OutOfBounds` if it is past the end of the memory.
+    pub(crate) fn load(&self, addr: u16) -> Result<u8, MemoryError> {
+        self.memory
+            .get(addr as usize)
+            .copied()
+            .ok_or(MemoryError::OutOfBounds(addr))
     }
 
     /// Stores a byte to memory at the specified address.
@@ -64,8 +113,17 @@ impl Memory {
     ///
     /// * `addr` - The address to store to.
     /// * `val` - The byte to store.
-    pub(crate) fn store(&mut self, addr: u16, val: u8) {
-        self.memory[addr as usize] = val;
+    ///
+    /// # Returns
+    ///
+    /// `MemoryError::OutOfBounds` if the address is past the end of the memory.
+    pub(crate) fn store(&mut self, addr: u16, val: u8) -> Result<(), MemoryError> {
+        let byte = self
+            .memory
+            .get_mut(addr as usize)
+            .ok_or(MemoryError::OutOfBounds(addr))?;
+        *byte = val;
+        Ok(())
     }
 
     /// Reads a register value.
@@ -100,19 +158,20 @@ impl Memory {
         if self.st > 0 {
             self.st -= 1;
         }
-
-        // TODO: Play sound when sound timer is 0
     }
 
     /// Pops a value from the stack.
     ///
     /// # Returns
     ///
-    /// The value that was popped from the stack.
-    pub fn pop_stack(&mut self) -> u16 {
+    /// The value that was popped from the stack, or `MemoryError::StackUnderflow` if the stack is empty.
+    pub fn pop_stack(&mut self) -> Result<u16, MemoryError> {
+        if self.sp == 0 {
+            return Err(MemoryError::StackUnderflow);
+        }
         let val = self.stack[self.sp as usize];
         self.sp -= 1;
-        val
+        Ok(val)
     }
 
     /// Pushes a value onto the stack.
@@ -120,9 +179,17 @@ impl Memory {
     /// # Arguments
     ///
     /// * `val` - The value to push onto the stack.
-    pub fn push_stack(&mut self, val: u16) {
+    ///
+    /// # Returns
+    ///
+    /// `MemoryError::StackOverflow` if the stack is full.
+    pub fn push_stack(&mut self, val: u16) -> Result<(), MemoryError> {
+        if self.sp as usize == self.stack.len() - 1 {
+            return Err(MemoryError::StackOverflow);
+        }
         self.sp += 1;
         self.stack[self.sp as usize] = val;
+        Ok(())
     }
 
     /// Resets the memory to its initial state.
@@ -133,6 +200,9 @@ impl Memory {
         self.i = 0;
         self.dt = 0;
         self.st = 0;
+        self.audio_pattern = [0; AUDIO_PATTERN_SIZE];
+        self.audio_pattern_loaded = false;
+        self.pitch = DEFAULT_PITCH;
     }
 
     /// Reads the next instruction from memory.
@@ -140,8 +210,8 @@ impl Memory {
     /// # Returns
     ///
     /// The next instruction as a 16-bit value.
-    pub(crate) fn read_instr(&self) -> u16 {
-        (self.load(self.pc) as u16) << 8 | self.load(self.pc + 1) as u16
+    pub(crate) fn read_instr(&self) -> Result<u16, MemoryError> {
+        Ok((self.load(self.pc)? as u16) << 8 | self.load(self.pc.wrapping_add(1))? as u16)
     }
 
     /// Loads the sprites into memory.
@@ -165,7 +235,29 @@ impl Memory {
             0xF0, 0x80, 0xF0, 0x80, 0x80, // F
         ];
 
-        self.memory[0..0x50].copy_from_slice(&sprites);
+        let big_sprites: [u8; 0xA0] = [
+            0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+            0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+            0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+            0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+            0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+            0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+            0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+            0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+        ];
+
+        let font_addr = FONT_ADDR as usize;
+        let big_font_addr = BIG_FONT_ADDR as usize;
+        self.memory[font_addr..font_addr + sprites.len()].copy_from_slice(&sprites);
+        self.memory[big_font_addr..big_font_addr + big_sprites.len()].copy_from_slice(&big_sprites);
     }
 
     /// Loads a sprite from memory.
@@ -176,20 +268,13 @@ impl Memory {
     ///
     /// # Returns
     ///
-    /// The loaded sprite as a vector of bytes.
-    pub(crate) fn load_sprite(&self, nibble: usize) -> Vec<u8> {
-        let mut sprite = Vec::new();
+    /// The loaded sprite as a vector of bytes, or `MemoryError::OutOfBounds` if it extends past the end of the memory.
+    pub(crate) fn load_sprite(&self, nibble: usize) -> Result<Vec<u8>, MemoryError> {
         let start_addr = self.i as usize;
 
-        if start_addr + nibble > MEM_SIZE {
-            return sprite;
+        match self.memory.get(start_addr..start_addr + nibble) {
+            Some(sprite) => Ok(sprite.to_vec()),
+            None => Err(MemoryError::OutOfBounds(self.i)),
         }
-
-        for offset in 0..nibble {
-            let byte = self.load((start_addr + offset) as u16);
-            sprite.push(byte);
-        }
-
-        sprite
     }
 }
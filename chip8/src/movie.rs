@@ -0,0 +1,456 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::cpu::CPU;
+use crate::error::Chip8Error;
+use crate::hash::{crc32, from_hex, sha1, to_hex};
+use crate::memory::{Memory, MEM_SIZE};
+use crate::quirks::{IndexIncrement, Platform, Quirks};
+use crate::rng::SeededRandom;
+
+/// The first word of every movie file.
+pub const MOVIE_MAGIC: &str = "chip8-movie";
+
+/// The version of the movie format written by this crate.
+pub const MOVIE_VERSION: u32 = 1;
+
+/// The number of frames between two state hash checks.
+pub const CHECK_INTERVAL: u64 = 60;
+
+/// A keypad transition, applied at the start of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The number of frames run before the transition.
+    pub frame: u64,
+    /// The keypad key, from 0x0 to 0xF.
+    pub key: u8,
+    /// True if the key was pressed, false if it was released.
+    pub pressed: bool,
+}
+
+/// A recorded session: the ROM, the machine it ran on, the random seed and every keypad transition.
+///
+/// Movies are stored as text, one record per line:
+///
+/// ```text
+/// chip8-movie 1
+/// rom <sha1 of the ROM>
+/// platform <vip, chip48, schip or xochip, if the CPU was created for one>
+/// load-address <address the ROM is loaded at>
+/// quirk.<field of Quirks> <value, for every quirk>
+/// seed <seed of the SeededRandom generator>
+/// cycles <instructions per frame>
+/// frames <number of frames>
+/// key <frame> down|up <key>
+/// check <frame> <crc32 of the machine state>
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    /// The SHA-1 of the ROM the movie was recorded with.
+    pub rom_sha1: [u8; 20],
+    /// The platform of the CPU, giving its memory size.
+    pub platform: Option<Platform>,
+    /// The quirks the movie was recorded with.
+    pub quirks: Quirks,
+    /// The address the ROM is loaded and started at.
+    pub load_address: u16,
+    /// The seed of the `SeededRandom` generator used for `Cxkk`.
+    pub seed: u64,
+    /// The number of instructions run per frame.
+    pub cycles_per_frame: u32,
+    /// The length of the movie in frames.
+    pub frames: u64,
+    /// The keypad transitions, in order.
+    pub events: Vec<KeyEvent>,
+    /// The expected `state_hash` after some frames, as pairs of a frame count and a hash.
+    pub checks: Vec<(u64, u32)>,
+}
+
+/// Represents the reasons a movie cannot be loaded or played back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MovieError {
+    /// The movie file is malformed.
+    Parse { line: usize, message: String },
+    /// The movie was recorded with another ROM.
+    RomMismatch { expected: [u8; 20], found: [u8; 20] },
+    /// The program faulted during playback.
+    Fault(Chip8Error),
+    /// The machine state differs from the one recorded, playback no longer matches the movie.
+    Desync { frame: u64, expected: u32, found: u32 },
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "movie was recorded with ROM {} but the ROM is {}",
+                to_hex(expected),
+                to_hex(found)
+            ),
+            MovieError::Fault(e) => write!(f, "{}", e),
+            MovieError::Desync { frame, expected, found } => write!(
+                f,
+                "desync at frame {}: state hash {:08x} does not match {:08x}",
+                frame, found, expected
+            ),
+        }
+    }
+}
+
+impl Error for MovieError {}
+
+/// Returns a hash of the complete machine state, used to detect desyncs.
+pub fn state_hash(cpu: &CPU) -> u32 {
+    let state = cpu.save_state();
+    // The state ends with its own CRC-32, over which a CRC-32 is always the same residue
+    crc32(&state[..state.len() - 4])
+}
+
+impl Movie {
+    /// Creates an empty movie for a ROM, on a machine set up as `cpu`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The machine whose platform, quirks and load address are recorded.
+    /// * `rom` - The program the movie is recorded with.
+    /// * `seed` - The seed of the random generator.
+    /// * `cycles_per_frame` - The number of instructions run per frame.
+    pub fn new(cpu: &CPU, rom: &[u8], seed: u64, cycles_per_frame: u32) -> Self {
+        Movie {
+            rom_sha1: sha1(rom),
+            platform: cpu.platform(),
+            quirks: cpu.quirks,
+            load_address: cpu.load_address(),
+            seed,
+            cycles_per_frame,
+            frames: 0,
+            events: Vec::new(),
+            checks: Vec::new(),
+        }
+    }
+
+    /// Puts a CPU in the state the movie starts from: powered on as the recorded platform, with
+    /// the recorded quirks, the ROM loaded at the recorded address and the random generator seeded.
+    /// The RAM outside the fonts, the stack, the RPL flags and the keypad are cleared, so that
+    /// nothing left by an earlier session changes the playback.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::RomMismatch` if the ROM is not the one the movie was recorded with,
+    /// or `MovieError::Fault` if it does not fit in memory.
+    pub fn boot(&self, cpu: &mut CPU, rom: &[u8]) -> Result<(), MovieError> {
+        let found = sha1(rom);
+        if found != self.rom_sha1 {
+            return Err(MovieError::RomMismatch {
+                expected: self.rom_sha1,
+                found,
+            });
+        }
+        cpu.memory = Memory::with_size(self.platform.map_or(MEM_SIZE, |platform| platform.memory_size()));
+        cpu.platform = self.platform;
+        cpu.quirks = self.quirks;
+        cpu.load_address = self.load_address;
+        cpu.reset();
+        cpu.load_program(rom).map_err(MovieError::Fault)?;
+        cpu.set_rng(Box::new(SeededRandom::new(self.seed)));
+        Ok(())
+    }
+
+    /// Parses a movie written by the `Display` implementation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::Parse` with the line of the first malformed record.
+    pub fn parse(text: &str) -> Result<Movie, MovieError> {
+        let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+        let error = |line: usize, message: &str| MovieError::Parse {
+            line,
+            message: message.to_string(),
+        };
+
+        match lines.next() {
+            Some((_, header)) if header == format!("{} {}", MOVIE_MAGIC, MOVIE_VERSION) => {}
+            Some((_, header)) if header.starts_with(MOVIE_MAGIC) => {
+                return Err(error(1, &format!("unsupported movie version, expected {}", MOVIE_VERSION)))
+            }
+            _ => return Err(error(1, "not a movie file")),
+        }
+
+        let mut rom_sha1 = None;
+        let mut platform = None;
+        let mut load_address = None;
+        let mut quirks = Vec::new();
+        let mut seed = None;
+        let mut cycles_per_frame = None;
+        let mut frames = None;
+        let mut events = Vec::new();
+        let mut checks = Vec::new();
+        for (number, line) in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let invalid = || error(number, &format!("invalid record '{}'", line));
+            match fields.as_slice() {
+                [] => {}
+                ["rom", hash] => {
+                    let bytes = from_hex(hash).ok_or_else(invalid)?;
+                    rom_sha1 = Some(bytes.try_into().map_err(|_| invalid())?);
+                }
+                ["platform", name] => platform = Some(Platform::from_name(name).ok_or_else(invalid)?),
+                ["load-address", value] => {
+                    let hex = value.strip_prefix("0x").ok_or_else(invalid)?;
+                    load_address = Some(u16::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                }
+                [name, value] if name.starts_with("quirk.") => quirks.push((number, &name["quirk.".len()..], *value)),
+                ["seed", value] => seed = Some(value.parse().map_err(|_| invalid())?),
+                ["cycles", value] => cycles_per_frame = Some(value.parse().map_err(|_| invalid())?),
+                ["frames", value] => frames = Some(value.parse().map_err(|_| invalid())?),
+                ["key", frame, direction, key] => {
+                    let key = u8::from_str_radix(key, 16).ok().filter(|&key| key < 16).ok_or_else(invalid)?;
+                    let pressed = match *direction {
+                        "down" => true,
+                        "up" => false,
+                        _ => return Err(invalid()),
+                    };
+                    events.push(KeyEvent {
+                        frame: frame.parse().map_err(|_| invalid())?,
+                        key,
+                        pressed,
+                    });
+                }
+                ["check", frame, hash] => checks.push((
+                    frame.parse().map_err(|_| invalid())?,
+                    u32::from_str_radix(hash, 16).map_err(|_| invalid())?,
+                )),
+                _ => return Err(invalid()),
+            }
+        }
+
+        /* Movies without these records start from the preset, or the defaults, at 0x200 */
+        let mut preset = platform.map_or_else(Quirks::default, Quirks::for_platform);
+        for (number, name, value) in quirks {
+            set_quirk(&mut preset, name, value).ok_or_else(|| error(number, &format!("invalid quirk '{}'", name)))?;
+        }
+
+        let last = text.lines().count();
+        Ok(Movie {
+            rom_sha1: rom_sha1.ok_or_else(|| error(last, "missing rom record"))?,
+            platform,
+            quirks: preset,
+            load_address: load_address.unwrap_or(0x200),
+            seed: seed.ok_or_else(|| error(last, "missing seed record"))?,
+            cycles_per_frame: cycles_per_frame.ok_or_else(|| error(last, "missing cycles record"))?,
+            frames: frames.ok_or_else(|| error(last, "missing frames record"))?,
+            events,
+            checks,
+        })
+    }
+}
+
+/// Sets a quirk from its field name in `Quirks`.
+fn set_quirk(quirks: &mut Quirks, name: &str, value: &str) -> Option<()> {
+    if name == "index_increment" {
+        quirks.index_increment = IndexIncrement::from_name(value)?;
+        return Some(());
+    }
+    let field = match name {
+        "shift_uses_vy" => &mut quirks.shift_uses_vy,
+        "jump_uses_vx" => &mut quirks.jump_uses_vx,
+        "clip_sprites" => &mut quirks.clip_sprites,
+        "logic_resets_vf" => &mut quirks.logic_resets_vf,
+        "display_wait" => &mut quirks.display_wait,
+        "lores_tall_sprites" => &mut quirks.lores_tall_sprites,
+        _ => return None,
+    };
+    *field = value.parse().ok()?;
+    Some(())
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", MOVIE_MAGIC, MOVIE_VERSION)?;
+        writeln!(f, "rom {}", to_hex(&self.rom_sha1))?;
+        if let Some(platform) = self.platform {
+            writeln!(f, "platform {}", platform.name())?;
+        }
+        writeln!(f, "load-address 0x{:03X}", self.load_address)?;
+        let quirks = &self.quirks;
+        let flags = [
+            ("shift_uses_vy", quirks.shift_uses_vy),
+            ("jump_uses_vx", quirks.jump_uses_vx),
+            ("clip_sprites", quirks.clip_sprites),
+            ("logic_resets_vf", quirks.logic_resets_vf),
+            ("display_wait", quirks.display_wait),
+            ("lores_tall_sprites", quirks.lores_tall_sprites),
+        ];
+        for (name, value) in flags {
+            writeln!(f, "quirk.{} {}", name, value)?;
+        }
+        writeln!(f, "quirk.index_increment {}", quirks.index_increment.name())?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "cycles {}", self.cycles_per_frame)?;
+        writeln!(f, "frames {}", self.frames)?;
+        for event in &self.events {
+            let direction = if event.pressed { "down" } else { "up" };
+            writeln!(f, "key {} {} {:X}", event.frame, direction, event.key)?;
+        }
+        for (frame, hash) in &self.checks {
+            writeln!(f, "check {} {:08x}", frame, hash)?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the keypad transitions of a session into a `Movie`.
+///
+/// The frontend forwards the keypad to the recorder instead of the CPU and runs the frames
+/// through it, so that every transition is stamped with the frame it happened in.
+pub struct MovieRecorder {
+    movie: Movie,
+}
+
+impl MovieRecorder {
+    /// Boots the CPU and starts recording.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The machine, which is reset. Its platform, quirks and load address are recorded.
+    /// * `rom` - The program to run.
+    /// * `seed` - The seed of the random generator.
+    /// * `cycles_per_frame` - The number of instructions run per frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::Fault` if the ROM does not fit in memory.
+    pub fn start(cpu: &mut CPU, rom: &[u8], seed: u64, cycles_per_frame: u32) -> Result<Self, MovieError> {
+        let movie = Movie::new(cpu, rom, seed, cycles_per_frame);
+        movie.boot(cpu, rom)?;
+        Ok(MovieRecorder { movie })
+    }
+
+    /// Returns the number of frames recorded.
+    pub fn frame(&self) -> u64 {
+        self.movie.frames
+    }
+
+    /// Presses a keypad key and records the transition.
+    pub fn key_pressed(&mut self, cpu: &mut CPU, index: usize) {
+        self.record_key(index, true);
+        cpu.key_pressed(index);
+    }
+
+    /// Releases a keypad key and records the transition.
+    pub fn key_released(&mut self, cpu: &mut CPU, index: usize) {
+        self.record_key(index, false);
+        cpu.key_released(index);
+    }
+
+    fn record_key(&mut self, index: usize, pressed: bool) {
+        self.movie.events.push(KeyEvent {
+            frame: self.movie.frames,
+            key: index as u8,
+            pressed,
+        });
+    }
+
+    /// Runs one frame, recording a state hash every `CHECK_INTERVAL` frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Chip8Error` if an instruction faults. The frame is still recorded.
+    pub fn run_frame(&mut self, cpu: &mut CPU) -> Result<(), Chip8Error> {
+        let result = cpu.run_frame(self.movie.cycles_per_frame);
+        self.movie.frames += 1;
+        if self.movie.frames.is_multiple_of(CHECK_INTERVAL) {
+            self.movie.checks.push((self.movie.frames, state_hash(cpu)));
+        }
+        result
+    }
+
+    /// Stops recording and returns the movie, ending with a check of the final state.
+    pub fn finish(mut self, cpu: &CPU) -> Movie {
+        if self.movie.checks.last().map(|&(frame, _)| frame) != Some(self.movie.frames) {
+            self.movie.checks.push((self.movie.frames, state_hash(cpu)));
+        }
+        self.movie
+    }
+}
+
+/// Plays a `Movie` back, feeding its keypad transitions to the CPU and checking the state hashes.
+pub struct MoviePlayer {
+    movie: Movie,
+    /// The number of frames played.
+    frame: u64,
+    /// The index of the next event to apply.
+    next_event: usize,
+    /// The index of the next check to verify.
+    next_check: usize,
+}
+
+impl MoviePlayer {
+    /// Boots the CPU and starts playing a movie.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::RomMismatch` if the ROM is not the one the movie was recorded with.
+    pub fn start(movie: Movie, cpu: &mut CPU, rom: &[u8]) -> Result<Self, MovieError> {
+        movie.boot(cpu, rom)?;
+        Ok(MoviePlayer {
+            movie,
+            frame: 0,
+            next_event: 0,
+            next_check: 0,
+        })
+    }
+
+    /// Returns the number of frames played.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns the movie being played.
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    /// Returns true once every frame of the movie has been played.
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.movie.frames
+    }
+
+    /// Plays the next frame of the movie.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, MovieError>` - False if the movie is finished and nothing was run.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::Desync` if the state hash differs from the recorded one,
+    /// or `MovieError::Fault` if the program faults.
+    pub fn run_frame(&mut self, cpu: &mut CPU) -> Result<bool, MovieError> {
+        if self.is_finished() {
+            return Ok(false);
+        }
+        while let Some(event) = self.movie.events.get(self.next_event).filter(|event| event.frame <= self.frame) {
+            if event.pressed {
+                cpu.key_pressed(event.key as usize);
+            } else {
+                cpu.key_released(event.key as usize);
+            }
+            self.next_event += 1;
+        }
+
+        let result = cpu.run_frame(self.movie.cycles_per_frame);
+        self.frame += 1;
+        while let Some(&(frame, expected)) = self.movie.checks.get(self.next_check).filter(|check| check.0 <= self.frame) {
+            self.next_check += 1;
+            let found = state_hash(cpu);
+            if frame == self.frame && found != expected {
+                return Err(MovieError::Desync { frame, expected, found });
+            }
+        }
+        result.map_err(MovieError::Fault)?;
+        Ok(true)
+    }
+}
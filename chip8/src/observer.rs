@@ -0,0 +1,47 @@
+/// Receives notifications of what happens inside the CPU, for profilers, cheat engines,
+/// achievements and other tools that need more than the registers between instructions.
+///
+/// Every method does nothing by default, so an observer only implements the events it needs.
+/// The CPU holds at most one observer, and without one each hook point costs a single check.
+pub trait Chip8Observer: Send {
+    /// Called before the instruction at `pc` is executed.
+    fn on_fetch(&mut self, _pc: u16, _opcode: u16) {}
+
+    /// Called after the instruction fetched at `pc` executed without a fault.
+    ///
+    /// Instructions that wait, such as `Fx0A` without a key, retire without moving the PC.
+    fn on_retire(&mut self, _pc: u16, _opcode: u16) {}
+
+    /// Called when an instruction reads a byte of memory, including sprite data.
+    ///
+    /// Opcode fetches are reported by `on_fetch` instead.
+    fn on_memory_read(&mut self, _addr: u16, _value: u8) {}
+
+    /// Called when an instruction writes a byte of memory.
+    fn on_memory_write(&mut self, _addr: u16, _value: u8) {}
+
+    /// Called after a sprite is drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column of the sprite, before wrapping.
+    /// * `y` - The row of the sprite, before wrapping.
+    /// * `height` - The number of rows, 16 for a SUPER-CHIP 16x16 sprite.
+    /// * `collision` - Whether a lit pixel was turned off.
+    fn on_draw(&mut self, _x: u8, _y: u8, _height: u8, _collision: bool) {}
+
+    /// Called when `CALL` pushes a return address, `depth` being the new stack pointer.
+    fn on_stack_push(&mut self, _addr: u16, _depth: u8) {}
+
+    /// Called when `RET` pops a return address, `depth` being the new stack pointer.
+    fn on_stack_pop(&mut self, _addr: u16, _depth: u8) {}
+
+    /// Called when `Fx0A` starts waiting for a key press into `Vx`.
+    fn on_key_wait(&mut self, _reg: u8) {}
+
+    /// Called when the sound timer becomes non-zero and the buzzer starts.
+    fn on_sound_start(&mut self) {}
+
+    /// Called when the sound timer reaches zero and the buzzer stops.
+    fn on_sound_stop(&mut self) {}
+}
@@ -0,0 +1,1063 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+
+use crate::instructions::{encode, Instruction};
+
+/// The address `compile` compiles programs for.
+const ORIGIN: u16 = 0x200;
+
+/// The maximum number of macro expansions, which also stops recursive macros.
+const MAX_EXPANSIONS: usize = 10_000;
+
+/// An error found while compiling, with the location it was found at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OctoError {
+    /// The 1-based line of the error, or 0 if it is not tied to a location.
+    pub line: usize,
+    /// The 1-based column of the error, or 0 if it is not tied to a location.
+    pub column: usize,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for OctoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for OctoError {}
+
+/// A compiled Octo program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OctoProgram {
+    /// The ROM, to be loaded at the address it was compiled for, 0x200 unless `compile_at` was used.
+    pub rom: Vec<u8>,
+    /// The address of every label.
+    pub symbols: BTreeMap<String, u16>,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: impl Into<String>) -> OctoError {
+        OctoError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+/// How a label is written into the ROM once it is defined.
+#[derive(Clone, Copy, Debug)]
+enum Fixup {
+    /// The low 12 bits of the opcode at the address.
+    Address,
+    /// The 16-bit word at the address.
+    Long,
+    /// The byte at the address, with the high nibble given and the low nibble taken from bits 8-11.
+    UnpackHigh(u8),
+    /// The byte at the address, taken from bits 8-15.
+    HighByte,
+    /// The byte at the address, taken from bits 0-7.
+    LowByte,
+}
+
+#[derive(Clone, Debug)]
+struct PendingReference {
+    addr: u16,
+    fixup: Fixup,
+    token: Token,
+}
+
+/// The structured control flow blocks that are still open.
+#[derive(Clone, Debug)]
+enum Block {
+    If { jump: u16, token: Token },
+    Else { jump: u16, token: Token },
+    Loop { start: u16, breaks: Vec<u16>, token: Token },
+}
+
+#[derive(Clone, Debug)]
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Key,
+    NotKey,
+    Less,
+    Greater,
+    LessOrEqual,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn negate(self) -> Self {
+        match self {
+            Comparison::Equal => Comparison::NotEqual,
+            Comparison::NotEqual => Comparison::Equal,
+            Comparison::Key => Comparison::NotKey,
+            Comparison::NotKey => Comparison::Key,
+            Comparison::Less => Comparison::GreaterOrEqual,
+            Comparison::GreaterOrEqual => Comparison::Less,
+            Comparison::Greater => Comparison::LessOrEqual,
+            Comparison::LessOrEqual => Comparison::Greater,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    Reg(u8),
+    Byte(u8),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Condition {
+    x: u8,
+    comparison: Comparison,
+    rhs: Operand,
+}
+
+/// Splits the source into whitespace separated tokens. Comments start with `#`.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut pos = 0;
+        while pos < chars.len() {
+            if chars[pos] == '#' {
+                break;
+            }
+            if chars[pos].is_whitespace() {
+                pos += 1;
+                continue;
+            }
+            let start = pos;
+            if chars[pos] == '{' || chars[pos] == '}' {
+                pos += 1;
+            } else {
+                while pos < chars.len() && !chars[pos].is_whitespace() && chars[pos] != '{' && chars[pos] != '}' {
+                    pos += 1;
+                }
+            }
+            tokens.push(Token {
+                text: chars[start..pos].iter().collect(),
+                line: index + 1,
+                column: start + 1,
+            });
+        }
+    }
+    tokens
+}
+
+/// Parses a decimal, `0x` hexadecimal or `0b` binary number, optionally negative.
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Returns the register number for a `v0` - `vf` name.
+fn register(name: &str) -> Option<u8> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(digit), None) => digit.to_digit(16).map(|reg| reg as u8),
+        _ => None,
+    }
+}
+
+/// Words that can never be label or constant names.
+fn is_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        ":=" | "+=" | "-=" | "=-" | "|=" | "&=" | "^=" | ">>=" | "<<=" | "==" | "!=" | "<" | ">" | "<=" | ">="
+            | "{" | "}" | ";" | "i" | "key" | "-key" | "random" | "delay" | "buzzer" | "pitch" | "hex" | "bighex"
+            | "long" | "clear" | "return" | "exit" | "lores" | "hires" | "scroll-left" | "scroll-right"
+            | "scroll-up" | "scroll-down" | "audio" | "plane" | "bcd" | "save" | "load" | "saveflags"
+            | "loadflags" | "sprite" | "jump" | "jump0" | "native" | "if" | "then" | "begin" | "else" | "end"
+            | "loop" | "again" | "while"
+    ) || name.starts_with(':')
+        || register(name).is_some()
+}
+
+struct Compiler {
+    /// The address the program is loaded at.
+    origin: u16,
+    tokens: Vec<Token>,
+    pos: usize,
+    rom: Vec<u8>,
+    here: u32,
+    labels: BTreeMap<String, u16>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    references: Vec<PendingReference>,
+    blocks: Vec<Block>,
+    expansions: usize,
+}
+
+impl Compiler {
+    fn new(source: &str, origin: u16) -> Self {
+        Compiler {
+            origin,
+            tokens: tokenize(source),
+            pos: 0,
+            rom: Vec::new(),
+            here: origin as u32,
+            labels: BTreeMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            references: Vec::new(),
+            blocks: Vec::new(),
+            expansions: 0,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, OctoError> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => Err(match self.tokens.last() {
+                Some(last) => last.error("unexpected end of file"),
+                None => OctoError {
+                    line: 0,
+                    column: 0,
+                    message: "unexpected end of file".to_string(),
+                },
+            }),
+        }
+    }
+
+    fn peek_text(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, OctoError> {
+        let token = self.next_token()?;
+        if token.text != text {
+            return Err(token.error(format!("expected '{}', found '{}'", text, token.text)));
+        }
+        Ok(token)
+    }
+
+    fn here(&self) -> u16 {
+        self.here as u16
+    }
+
+    fn emit_byte(&mut self, byte: u8, token: &Token) -> Result<(), OctoError> {
+        if self.here > 0xFFFF {
+            return Err(token.error("program exceeds the 64KB address space"));
+        }
+        let offset = (self.here - self.origin as u32) as usize;
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit_word(&mut self, word: u16, token: &Token) -> Result<(), OctoError> {
+        self.emit_byte((word >> 8) as u8, token)?;
+        self.emit_byte(word as u8, token)
+    }
+
+    fn emit(&mut self, instruction: Instruction, token: &Token) -> Result<(), OctoError> {
+        self.emit_word(encode(&instruction), token)
+    }
+
+    /// Emits a jump whose target is patched in later, returning its address.
+    fn emit_placeholder_jump(&mut self, token: &Token) -> Result<u16, OctoError> {
+        let addr = self.here();
+        self.emit(Instruction::JumpToAddress { nnn: 0 }, token)?;
+        Ok(addr)
+    }
+
+    /// Writes the target into the jump at `addr`.
+    fn patch_jump(&mut self, addr: u16, target: u16, token: &Token) -> Result<(), OctoError> {
+        if target > 0xFFF {
+            return Err(token.error(format!("jump target 0x{:04X} is out of range", target)));
+        }
+        let offset = (addr - self.origin) as usize;
+        self.rom[offset] = (self.rom[offset] & 0xF0) | (target >> 8) as u8;
+        self.rom[offset + 1] = target as u8;
+        Ok(())
+    }
+
+    fn define_label(&mut self, token: &Token, addr: u16) -> Result<(), OctoError> {
+        self.check_name(token)?;
+        self.labels.insert(token.text.clone(), addr);
+        Ok(())
+    }
+
+    fn check_name(&self, token: &Token) -> Result<(), OctoError> {
+        let name = token.text.as_str();
+        if is_keyword(name) || parse_number(name).is_some() {
+            return Err(token.error(format!("'{}' is a reserved word", name)));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) || self.macros.contains_key(name) {
+            return Err(token.error(format!("'{}' is already defined", name)));
+        }
+        Ok(())
+    }
+
+    fn register(&mut self) -> Result<u8, OctoError> {
+        let token = self.next_token()?;
+        register(&token.text)
+            .or_else(|| self.aliases.get(&token.text).copied())
+            .ok_or_else(|| token.error(format!("expected a register, found '{}'", token.text)))
+    }
+
+    fn is_register(&self, text: &str) -> bool {
+        register(text).is_some() || self.aliases.contains_key(text)
+    }
+
+    /// Reads a value that must already be known: a number, constant, defined label or `{ calc }`.
+    fn value(&mut self) -> Result<(i64, Token), OctoError> {
+        let token = self.next_token()?;
+        if token.text == "{" {
+            let value = self.calc_until_close()?;
+            return Ok((value.floor() as i64, token));
+        }
+        match self.known_value(&token.text) {
+            Some(value) => Ok((value, token)),
+            None => Err(token.error(format!("undefined name '{}'", token.text))),
+        }
+    }
+
+    fn known_value(&self, text: &str) -> Option<i64> {
+        parse_number(text)
+            .or_else(|| self.constants.get(text).map(|value| value.floor() as i64))
+            .or_else(|| self.labels.get(text).map(|addr| *addr as i64))
+    }
+
+    fn ranged_value(&mut self, min: i64, max: i64, what: &str) -> Result<i64, OctoError> {
+        let (value, token) = self.value()?;
+        if value < min || value > max {
+            return Err(token.error(format!("value {} does not fit in {}", value, what)));
+        }
+        Ok(value)
+    }
+
+    fn byte(&mut self) -> Result<u8, OctoError> {
+        Ok(self.ranged_value(-0x80, 0xFF, "a byte")? as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u8, OctoError> {
+        Ok(self.ranged_value(0, 0xF, "a nibble")? as u8)
+    }
+
+    /// Reads an address, which may be a label defined later on.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Where the value is written, used to patch forward references.
+    /// * `fixup` - How the value is written.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u16, OctoError>` - The address, or 0 if it is patched in later.
+    fn address(&mut self, addr: u16, fixup: Fixup) -> Result<u16, OctoError> {
+        let token = self.next_token()?;
+        let value = if token.text == "{" {
+            self.calc_until_close()?.floor() as i64
+        } else {
+            match self.known_value(&token.text) {
+                Some(value) => value,
+                None if !is_keyword(&token.text) => {
+                    self.references.push(PendingReference { addr, fixup, token });
+                    return Ok(0);
+                }
+                None => return Err(token.error(format!("expected an address, found '{}'", token.text))),
+            }
+        };
+        let max = match fixup {
+            Fixup::Address | Fixup::UnpackHigh(_) => 0xFFF,
+            _ => 0xFFFF,
+        };
+        if !(0..=max).contains(&value) {
+            return Err(token.error(format!("address {} is out of range", value)));
+        }
+        Ok(value as u16)
+    }
+
+    /// Writes a resolved reference into the ROM.
+    fn apply_fixup(&mut self, reference: &PendingReference, value: u16) -> Result<(), OctoError> {
+        let offset = (reference.addr - self.origin) as usize;
+        match reference.fixup {
+            Fixup::Address => {
+                if value > 0xFFF {
+                    return Err(reference.token.error(format!("address 0x{:04X} is out of range", value)));
+                }
+                self.rom[offset] = (self.rom[offset] & 0xF0) | (value >> 8) as u8;
+                self.rom[offset + 1] = value as u8;
+            }
+            Fixup::Long => self.rom[offset..offset + 2].copy_from_slice(&value.to_be_bytes()),
+            Fixup::UnpackHigh(nibble) => self.rom[offset] = (nibble << 4) | ((value >> 8) & 0xF) as u8,
+            Fixup::HighByte => self.rom[offset] = (value >> 8) as u8,
+            Fixup::LowByte => self.rom[offset] = value as u8,
+        }
+        Ok(())
+    }
+
+    /// Evaluates a `:calc` expression up to the closing brace.
+    fn calc_until_close(&mut self) -> Result<f64, OctoError> {
+        let value = self.calc_expression()?;
+        self.expect("}")?;
+        Ok(value)
+    }
+
+    /// Evaluates a `:calc` expression. Like Octo, binary operators have no precedence and
+    /// are evaluated right to left, so `2 * 3 + 1` is `2 * (3 + 1)`.
+    fn calc_expression(&mut self) -> Result<f64, OctoError> {
+        let lhs = self.calc_term()?;
+        let op = match self.peek_text() {
+            Some(op) if is_calc_binary(op) => op.to_string(),
+            _ => return Ok(lhs),
+        };
+        let token = self.next_token()?;
+        let rhs = self.calc_expression()?;
+        let (a, b) = (lhs as i64, rhs as i64);
+        let value = match op.as_str() {
+            "+" => lhs + rhs,
+            "-" => lhs - rhs,
+            "*" => lhs * rhs,
+            "/" | "%" if rhs == 0.0 => return Err(token.error("division by zero")),
+            "/" => lhs / rhs,
+            "%" => lhs % rhs,
+            "&" => (a & b) as f64,
+            "|" => (a | b) as f64,
+            "^" => (a ^ b) as f64,
+            "<<" => a.wrapping_shl(b as u32) as f64,
+            ">>" => a.wrapping_shr(b as u32) as f64,
+            "pow" => lhs.powf(rhs),
+            "min" => lhs.min(rhs),
+            "max" => lhs.max(rhs),
+            "<" => (lhs < rhs) as i64 as f64,
+            ">" => (lhs > rhs) as i64 as f64,
+            "<=" => (lhs <= rhs) as i64 as f64,
+            ">=" => (lhs >= rhs) as i64 as f64,
+            "==" => (lhs == rhs) as i64 as f64,
+            _ => (lhs != rhs) as i64 as f64,
+        };
+        Ok(value)
+    }
+
+    fn calc_term(&mut self) -> Result<f64, OctoError> {
+        let token = self.next_token()?;
+        let value = match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expression()?;
+                self.expect(")")?;
+                value
+            }
+            "-" => -self.calc_term()?,
+            "~" => !(self.calc_term()? as i64) as f64,
+            "!" => (self.calc_term()? == 0.0) as i64 as f64,
+            "abs" => self.calc_term()?.abs(),
+            "sqrt" => self.calc_term()?.sqrt(),
+            "sin" => self.calc_term()?.sin(),
+            "cos" => self.calc_term()?.cos(),
+            "tan" => self.calc_term()?.tan(),
+            "exp" => self.calc_term()?.exp(),
+            "log" => self.calc_term()?.ln(),
+            "sign" => self.calc_term()?.signum(),
+            "ceil" => self.calc_term()?.ceil(),
+            "floor" => self.calc_term()?.floor(),
+            "@" => {
+                let addr = self.calc_term()? as i64;
+                let offset = addr - self.origin as i64;
+                if offset < 0 || offset as usize >= self.rom.len() {
+                    return Err(token.error(format!("address {} has not been compiled", addr)));
+                }
+                self.rom[offset as usize] as f64
+            }
+            "HERE" => self.here as f64,
+            "PI" => std::f64::consts::PI,
+            "E" => std::f64::consts::E,
+            text => match parse_number(text) {
+                Some(value) => value as f64,
+                None => match (self.constants.get(text), self.labels.get(text)) {
+                    (Some(value), _) => *value,
+                    (None, Some(addr)) => *addr as f64,
+                    _ => return Err(token.error(format!("undefined name '{}'", text))),
+                },
+            },
+        };
+        Ok(value)
+    }
+
+    fn condition(&mut self) -> Result<Condition, OctoError> {
+        let x = self.register()?;
+        let token = self.next_token()?;
+        let comparison = match token.text.as_str() {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "key" => Comparison::Key,
+            "-key" => Comparison::NotKey,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            "<=" => Comparison::LessOrEqual,
+            ">=" => Comparison::GreaterOrEqual,
+            _ => return Err(token.error(format!("expected a comparison, found '{}'", token.text))),
+        };
+        let rhs = match comparison {
+            Comparison::Key | Comparison::NotKey => Operand::Byte(0),
+            _ => match self.peek_text() {
+                Some(text) if self.is_register(text) => Operand::Reg(self.register()?),
+                _ => Operand::Byte(self.byte()?),
+            },
+        };
+        Ok(Condition { x, comparison, rhs })
+    }
+
+    /// Emits the instructions that skip the next one if the condition holds.
+    ///
+    /// The ordered comparisons are computed in VF with a subtraction, as Octo does.
+    fn emit_skip_if(&mut self, condition: Condition, token: &Token) -> Result<(), OctoError> {
+        let x = condition.x;
+        let instruction = match (condition.comparison, condition.rhs) {
+            (Comparison::Equal, Operand::Reg(y)) => Instruction::SkipIfRegEqualsReg { x, y },
+            (Comparison::Equal, Operand::Byte(kk)) => Instruction::SkipIfRegEqualsByte { x, kk },
+            (Comparison::NotEqual, Operand::Reg(y)) => Instruction::SkipIfRegNotEqualsReg { x, y },
+            (Comparison::NotEqual, Operand::Byte(kk)) => Instruction::SkipIfRegNotEqualsByte { x, kk },
+            (Comparison::Key, _) => Instruction::SkipIfKeyPressed { x },
+            (Comparison::NotKey, _) => Instruction::SkipIfKeyNotPressed { x },
+            (comparison, rhs) => {
+                // VF becomes 1 if vx >= rhs for < and >=, or if rhs >= vx for > and <=
+                let vx_first = matches!(comparison, Comparison::Less | Comparison::GreaterOrEqual);
+                match (rhs, vx_first) {
+                    (Operand::Reg(y), true) => {
+                        self.emit(Instruction::LoadRegIntoReg { x: 0xF, y: x }, token)?;
+                        self.emit(Instruction::SubtractRegFromReg { x: 0xF, y }, token)?;
+                    }
+                    (Operand::Reg(y), false) => {
+                        self.emit(Instruction::LoadRegIntoReg { x: 0xF, y }, token)?;
+                        self.emit(Instruction::SubtractRegFromReg { x: 0xF, y: x }, token)?;
+                    }
+                    (Operand::Byte(kk), true) => {
+                        self.emit(Instruction::LoadByteIntoReg { x: 0xF, kk }, token)?;
+                        self.emit(Instruction::SubtractRegFromOtherReg { x: 0xF, y: x }, token)?;
+                    }
+                    (Operand::Byte(kk), false) => {
+                        self.emit(Instruction::LoadByteIntoReg { x: 0xF, kk }, token)?;
+                        self.emit(Instruction::SubtractRegFromReg { x: 0xF, y: x }, token)?;
+                    }
+                }
+                let kk = matches!(comparison, Comparison::GreaterOrEqual | Comparison::LessOrEqual) as u8;
+                Instruction::SkipIfRegEqualsByte { x: 0xF, kk }
+            }
+        };
+        self.emit(instruction, token)
+    }
+
+    /// Compiles every token.
+    fn compile(mut self) -> Result<OctoProgram, OctoError> {
+        // Execution starts with a jump to main, dropped below if main comes first
+        let entry = Token {
+            text: "main".to_string(),
+            line: 1,
+            column: 1,
+        };
+        self.emit(Instruction::JumpToAddress { nnn: 0 }, &entry)?;
+        self.references.push(PendingReference {
+            addr: self.origin,
+            fixup: Fixup::Address,
+            token: entry,
+        });
+
+        while self.pos < self.tokens.len() {
+            self.statement()?;
+        }
+
+        if let Some(block) = self.blocks.last() {
+            let (Block::If { token, .. } | Block::Else { token, .. } | Block::Loop { token, .. }) = block;
+            return Err(token.error(format!("'{}' is never closed", token.text)));
+        }
+        if !self.labels.contains_key("main") {
+            return Err(OctoError {
+                line: 0,
+                column: 0,
+                message: "the program has no ': main' label".to_string(),
+            });
+        }
+
+        for reference in std::mem::take(&mut self.references) {
+            let value = match self.labels.get(&reference.token.text) {
+                Some(addr) => *addr,
+                None => {
+                    return Err(reference
+                        .token
+                        .error(format!("undefined name '{}'", reference.token.text)))
+                }
+            };
+            self.apply_fixup(&reference, value)?;
+        }
+
+        Ok(OctoProgram {
+            rom: self.rom,
+            symbols: self.labels,
+        })
+    }
+
+    fn statement(&mut self) -> Result<(), OctoError> {
+        let token = self.next_token()?;
+        match token.text.as_str() {
+            ":" => {
+                let name = self.next_token()?;
+                if name.text == "main" && self.here == self.origin as u32 + 2 && self.rom.len() == 2 && self.labels.is_empty() {
+                    // main is the first thing in the program, so the entry jump is not needed
+                    self.rom.clear();
+                    self.here = self.origin as u32;
+                    self.references.clear();
+                }
+                self.define_label(&name, self.here())?;
+            }
+            ":alias" => {
+                let name = self.next_token()?;
+                let reg = self.register()?;
+                if is_keyword(&name.text) {
+                    return Err(name.error(format!("'{}' is a reserved word", name.text)));
+                }
+                self.aliases.insert(name.text, reg);
+            }
+            ":const" => {
+                let name = self.next_token()?;
+                let (value, _) = self.value()?;
+                self.check_name(&name)?;
+                self.constants.insert(name.text, value as f64);
+            }
+            ":calc" => {
+                let name = self.next_token()?;
+                self.expect("{")?;
+                let value = self.calc_until_close()?;
+                self.check_name(&name)?;
+                self.constants.insert(name.text, value);
+            }
+            ":next" => {
+                // Labels the operand byte of the next instruction, for self-modifying code
+                let name = self.next_token()?;
+                self.define_label(&name, self.here() + 1)?;
+            }
+            ":unpack" => {
+                // Loads an address into va and vb, optionally with a nibble in the top of va
+                let nibble = if self.peek_text() == Some("long") {
+                    self.next_token()?;
+                    None
+                } else {
+                    Some(self.nibble()?)
+                };
+                let high_addr = self.here() + 1;
+                let pending = self.references.len();
+                let fixup = match nibble {
+                    Some(nibble) => Fixup::UnpackHigh(nibble),
+                    None => Fixup::HighByte,
+                };
+                let addr = self.address(high_addr, fixup)?;
+                if self.references.len() > pending {
+                    let token = self.references[pending].token.clone();
+                    self.references.push(PendingReference {
+                        addr: high_addr + 2,
+                        fixup: Fixup::LowByte,
+                        token,
+                    });
+                }
+                let high = match nibble {
+                    Some(nibble) => (nibble << 4) | (addr >> 8) as u8,
+                    None => (addr >> 8) as u8,
+                };
+                self.emit(Instruction::LoadByteIntoReg { x: 0xA, kk: high }, &token)?;
+                self.emit(Instruction::LoadByteIntoReg { x: 0xB, kk: addr as u8 }, &token)?;
+            }
+            ":macro" => {
+                let name = self.next_token()?;
+                self.check_name(&name)?;
+                let mut params = Vec::new();
+                loop {
+                    let param = self.next_token()?;
+                    if param.text == "{" {
+                        break;
+                    }
+                    params.push(param.text);
+                }
+                let mut body = Vec::new();
+                let mut depth = 1;
+                loop {
+                    let body_token = self.next_token()?;
+                    match body_token.text.as_str() {
+                        "{" => depth += 1,
+                        "}" => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    body.push(body_token);
+                }
+                self.macros.insert(name.text, Macro { params, body });
+            }
+            ":org" => {
+                let (addr, addr_token) = self.value()?;
+                if !(self.origin as i64..=0xFFFF).contains(&addr) {
+                    return Err(addr_token.error(format!("address {} is out of range", addr)));
+                }
+                self.here = addr as u32;
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte, &token)?;
+            }
+            ":call" => {
+                let addr = self.here();
+                let nnn = self.address(addr, Fixup::Address)?;
+                self.emit(Instruction::CallAddress { nnn }, &token)?;
+            }
+            ":breakpoint" => {
+                self.next_token()?;
+            }
+            ":monitor" => {
+                self.next_token()?;
+                self.next_token()?;
+            }
+            ";" | "return" => self.emit(Instruction::Return, &token)?,
+            "clear" => self.emit(Instruction::ClearScreen, &token)?,
+            "exit" => self.emit(Instruction::Exit, &token)?,
+            "lores" => self.emit(Instruction::LowRes, &token)?,
+            "hires" => self.emit(Instruction::HighRes, &token)?,
+            "scroll-left" => self.emit(Instruction::ScrollLeft, &token)?,
+            "scroll-right" => self.emit(Instruction::ScrollRight, &token)?,
+            "audio" => self.emit(Instruction::LoadAudioPattern, &token)?,
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(Instruction::ScrollDown { n }, &token)?;
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(Instruction::ScrollUp { n }, &token)?;
+            }
+            "plane" => {
+                let n = self.ranged_value(0, 3, "a plane mask")? as u8;
+                self.emit(Instruction::SelectPlanes { n }, &token)?;
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(Instruction::LoadBCDIntoMem { x }, &token)?;
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::StoreRplFlags { x }, &token)?;
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::LoadRplFlags { x }, &token)?;
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let range_end = if self.peek_text() == Some("-") {
+                    self.next_token()?;
+                    Some(self.register()?)
+                } else {
+                    None
+                };
+                let instruction = match (token.text == "save", range_end) {
+                    (true, None) => Instruction::StoreRegsIntoMem { x },
+                    (false, None) => Instruction::LoadRegsFromMem { x },
+                    (true, Some(y)) => Instruction::StoreRegRangeIntoMem { x, y },
+                    (false, Some(y)) => Instruction::LoadRegRangeFromMem { x, y },
+                };
+                self.emit(instruction, &token)?;
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble()?;
+                self.emit(Instruction::DrawSprite { x, y, n }, &token)?;
+            }
+            "jump" | "jump0" | "native" => {
+                let addr = self.here();
+                let nnn = self.address(addr, Fixup::Address)?;
+                let instruction = match token.text.as_str() {
+                    "jump" => Instruction::JumpToAddress { nnn },
+                    "jump0" => Instruction::JumpToAddressPlusV0 { nnn },
+                    _ => Instruction::Unknown(nnn),
+                };
+                self.emit(instruction, &token)?;
+            }
+            "if" => {
+                let condition = self.condition()?;
+                let keyword = self.next_token()?;
+                match keyword.text.as_str() {
+                    "then" => {
+                        let negated = Condition {
+                            comparison: condition.comparison.negate(),
+                            ..condition
+                        };
+                        self.emit_skip_if(negated, &token)?;
+                    }
+                    "begin" => {
+                        self.emit_skip_if(condition, &token)?;
+                        let jump = self.emit_placeholder_jump(&token)?;
+                        self.blocks.push(Block::If { jump, token });
+                    }
+                    _ => return Err(keyword.error(format!("expected 'then' or 'begin', found '{}'", keyword.text))),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, token: if_token }) => {
+                    let else_jump = self.emit_placeholder_jump(&token)?;
+                    self.patch_jump(jump, self.here(), &if_token)?;
+                    self.blocks.push(Block::Else { jump: else_jump, token });
+                }
+                _ => return Err(token.error("'else' without a matching 'if ... begin'")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. } | Block::Else { jump, .. }) => self.patch_jump(jump, self.here(), &token)?,
+                _ => return Err(token.error("'end' without a matching 'begin'")),
+            },
+            "loop" => {
+                let start = self.here();
+                self.blocks.push(Block::Loop {
+                    start,
+                    breaks: Vec::new(),
+                    token,
+                });
+            }
+            "while" => {
+                let condition = self.condition()?;
+                self.emit_skip_if(condition, &token)?;
+                let jump = self.emit_placeholder_jump(&token)?;
+                match self.blocks.iter_mut().rev().find(|block| matches!(block, Block::Loop { .. })) {
+                    Some(Block::Loop { breaks, .. }) => breaks.push(jump),
+                    _ => return Err(token.error("'while' outside of a loop")),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, breaks, .. }) => {
+                    if start > 0xFFF {
+                        return Err(token.error(format!("jump target 0x{:04X} is out of range", start)));
+                    }
+                    self.emit(Instruction::JumpToAddress { nnn: start }, &token)?;
+                    for jump in breaks {
+                        self.patch_jump(jump, self.here(), &token)?;
+                    }
+                }
+                _ => return Err(token.error("'again' without a matching 'loop'")),
+            },
+            "i" => {
+                let op = self.next_token()?;
+                match op.text.as_str() {
+                    ":=" => self.assign_index(&token)?,
+                    "+=" => {
+                        let x = self.register()?;
+                        self.emit(Instruction::AddRegToIndex { x }, &token)?;
+                    }
+                    _ => return Err(op.error(format!("expected ':=' or '+=', found '{}'", op.text))),
+                }
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let instruction = match token.text.as_str() {
+                    "delay" => Instruction::LoadRegIntoDelayTimer { x },
+                    "buzzer" => Instruction::LoadRegIntoSoundTimer { x },
+                    _ => Instruction::LoadRegIntoPitch { x },
+                };
+                self.emit(instruction, &token)?;
+            }
+            text if self.is_register(text) => {
+                self.pos -= 1;
+                self.register_statement(&token)?;
+            }
+            text if self.macros.contains_key(text) => self.expand_macro(&token)?,
+            text if parse_number(text).is_some() || self.constants.contains_key(text) || text == "{" => {
+                self.pos -= 1;
+                let byte = self.byte()?;
+                self.emit_byte(byte, &token)?;
+            }
+            text if !is_keyword(text) => {
+                // Any other name is a call to a subroutine, possibly defined later
+                self.pos -= 1;
+                let addr = self.here();
+                let nnn = self.address(addr, Fixup::Address)?;
+                self.emit(Instruction::CallAddress { nnn }, &token)?;
+            }
+            _ => return Err(token.error(format!("unexpected '{}'", token.text))),
+        }
+        Ok(())
+    }
+
+    fn assign_index(&mut self, token: &Token) -> Result<(), OctoError> {
+        match self.peek_text() {
+            Some("hex") => {
+                self.next_token()?;
+                let x = self.register()?;
+                self.emit(Instruction::LoadFontIntoReg { x }, token)
+            }
+            Some("bighex") => {
+                self.next_token()?;
+                let x = self.register()?;
+                self.emit(Instruction::LoadBigFontIntoReg { x }, token)
+            }
+            Some("long") => {
+                self.next_token()?;
+                let addr = self.here() + 2;
+                let value = self.address(addr, Fixup::Long)?;
+                self.emit(Instruction::LoadLongIndex, token)?;
+                self.emit_word(value, token)
+            }
+            _ => {
+                let addr = self.here();
+                let nnn = self.address(addr, Fixup::Address)?;
+                self.emit(Instruction::LoadIndex { nnn }, token)
+            }
+        }
+    }
+
+    fn register_statement(&mut self, token: &Token) -> Result<(), OctoError> {
+        let x = self.register()?;
+        let op = self.next_token()?;
+        let rhs_is_register = matches!(self.peek_text(), Some(text) if self.is_register(text));
+        let instruction = match (op.text.as_str(), rhs_is_register) {
+            (":=", true) => Instruction::LoadRegIntoReg { x, y: self.register()? },
+            (":=", false) => match self.peek_text() {
+                Some("random") => {
+                    self.next_token()?;
+                    Instruction::RandomByte { x, kk: self.byte()? }
+                }
+                Some("key") => {
+                    self.next_token()?;
+                    Instruction::LoadKeyIntoReg { x }
+                }
+                Some("delay") => {
+                    self.next_token()?;
+                    Instruction::LoadDelayTimerIntoReg { x }
+                }
+                _ => Instruction::LoadByteIntoReg { x, kk: self.byte()? },
+            },
+            ("+=", true) => Instruction::AddRegToReg { x, y: self.register()? },
+            ("+=", false) => Instruction::AddByteToReg { x, kk: self.byte()? },
+            ("-=", true) => Instruction::SubtractRegFromReg { x, y: self.register()? },
+            ("-=", false) => Instruction::AddByteToReg {
+                x,
+                kk: self.byte()?.wrapping_neg(),
+            },
+            ("=-", true) => Instruction::SubtractRegFromOtherReg { x, y: self.register()? },
+            ("|=", true) => Instruction::OrRegWithReg { x, y: self.register()? },
+            ("&=", true) => Instruction::AndRegWithReg { x, y: self.register()? },
+            ("^=", true) => Instruction::XorRegWithReg { x, y: self.register()? },
+            (">>=", true) => Instruction::ShiftRight { x, y: self.register()? },
+            ("<<=", true) => Instruction::ShiftLeft { x, y: self.register()? },
+            _ => return Err(op.error(format!("invalid operation '{}'", op.text))),
+        };
+        self.emit(instruction, token)
+    }
+
+    /// Replaces a macro invocation with the macro body, its parameters substituted.
+    fn expand_macro(&mut self, token: &Token) -> Result<(), OctoError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(token.error("too many macro expansions, is a macro recursive?"));
+        }
+        let definition = self.macros[&token.text].clone();
+        let mut args = HashMap::new();
+        for param in &definition.params {
+            let arg = self.next_token()?;
+            args.insert(param.clone(), arg.text);
+        }
+        let body = definition.body.into_iter().map(|mut body_token| {
+            if let Some(arg) = args.get(&body_token.text) {
+                body_token.text = arg.clone();
+            }
+            body_token
+        });
+        self.tokens.splice(self.pos..self.pos, body);
+        Ok(())
+    }
+}
+
+fn is_calc_binary(op: &str) -> bool {
+    matches!(
+        op,
+        "+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "<<" | ">>" | "pow" | "min" | "max" | "<" | ">" | "<=" | ">="
+            | "==" | "!="
+    )
+}
+
+/// Compiles Octo source into a ROM and its symbol table.
+///
+/// Supports the Octo statements for every CHIP-8, SUPER-CHIP and XO-CHIP instruction,
+/// `if ... then`, `if ... begin ... else ... end`, `loop ... while ... again`, and the
+/// `:alias`, `:const`, `:calc`, `:macro`, `:next`, `:unpack`, `:org`, `:byte` and `:call`
+/// directives. Execution starts at the `main` label.
+///
+/// # Arguments
+///
+/// * `source` - The Octo source.
+///
+/// # Returns
+///
+/// * `Result<OctoProgram, OctoError>` - The program, or the first error found.
+///
+/// # Examples
+///
+/// ```rust
+/// use chip8::octo::compile;
+///
+/// let program = compile(": main\n  v0 := 5\n  loop again").unwrap();
+/// assert_eq!(program.rom, [0x60, 0x05, 0x12, 0x02]);
+/// assert_eq!(program.symbols["main"], 0x200);
+/// ```
+pub fn compile(source: &str) -> Result<OctoProgram, OctoError> {
+    compile_at(source, ORIGIN)
+}
+
+/// Compiles Octo source into a ROM to be loaded at another address than 0x200, such as
+/// 0x600 for ETI 660 programs. Every label, jump and `:org` is relative to that address.
+///
+/// # Arguments
+///
+/// * `source` - The Octo source.
+/// * `origin` - The address the ROM is loaded and started at.
+///
+/// # Returns
+///
+/// * `Result<OctoProgram, OctoError>` - The program, or the first error found.
+///
+/// # Examples
+///
+/// ```rust
+/// use chip8::octo::compile_at;
+///
+/// let program = compile_at(": main\n  loop again", 0x600).unwrap();
+/// assert_eq!(program.rom, [0x16, 0x00]);
+/// ```
+pub fn compile_at(source: &str, origin: u16) -> Result<OctoProgram, OctoError> {
+    Compiler::new(source, origin).compile()
+}
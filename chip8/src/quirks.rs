@@ -0,0 +1,188 @@
+use crate::memory::{MEM_SIZE, XO_MEM_SIZE};
+
+/// The platforms whose interpreter behaviour can be emulated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    /// The original COSMAC VIP interpreter (1977).
+    CosmacVip,
+    /// The CHIP-48 interpreter for the HP-48 calculators.
+    Chip48,
+    /// SUPER-CHIP 1.1 as found on the HP-48S/SX.
+    SuperChip,
+    /// John Earnest's XO-CHIP extension, as implemented by Octo.
+    XoChip,
+}
+
+impl Platform {
+    /// Looks up a platform by name: `vip`, `chip48`, `schip` or `xochip`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::quirks::Platform;
+    ///
+    /// assert_eq!(Platform::from_name("schip"), Some(Platform::SuperChip));
+    /// ```
+    pub fn from_name(name: &str) -> Option<Platform> {
+        match name.to_ascii_lowercase().as_str() {
+            "vip" | "chip8" | "chip-8" => Some(Platform::CosmacVip),
+            "chip48" | "chip-48" => Some(Platform::Chip48),
+            "schip" | "superchip" | "super-chip" => Some(Platform::SuperChip),
+            "xochip" | "xo-chip" => Some(Platform::XoChip),
+            _ => None,
+        }
+    }
+
+    /// Returns the short name of the platform, as accepted by `from_name`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::CosmacVip => "vip",
+            Platform::Chip48 => "chip48",
+            Platform::SuperChip => "schip",
+            Platform::XoChip => "xochip",
+        }
+    }
+
+    /// Returns the size in bytes of the memory available on the platform.
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Platform::XoChip => XO_MEM_SIZE,
+            _ => MEM_SIZE,
+        }
+    }
+}
+
+/// How `Fx55` and `Fx65` modify the index register after copying registers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexIncrement {
+    /// I is left untouched.
+    None,
+    /// I is incremented by x (CHIP-48 behaviour).
+    ByX,
+    /// I is incremented by x + 1 (COSMAC VIP behaviour).
+    ByXPlusOne,
+}
+
+impl IndexIncrement {
+    /// Looks up an increment by name: `none`, `x` or `x+1`.
+    pub fn from_name(name: &str) -> Option<IndexIncrement> {
+        match name {
+            "none" => Some(IndexIncrement::None),
+            "x" => Some(IndexIncrement::ByX),
+            "x+1" => Some(IndexIncrement::ByXPlusOne),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the increment, as accepted by `from_name`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndexIncrement::None => "none",
+            IndexIncrement::ByX => "x",
+            IndexIncrement::ByXPlusOne => "x+1",
+        }
+    }
+}
+
+/// The set of ambiguous behaviours that differ between CHIP-8 interpreters.
+///
+/// `Quirks::default()` keeps the quirks this crate always had, while the platform presets
+/// match the interpreters ROMs were originally written for.
+///
+/// One behaviour changed for every profile, including the default: `Fx55`/`Fx65` copy
+/// V0 to Vx, as all interpreters do, where this crate used to copy all 16 registers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8xy6`/`8xyE` shift Vy into Vx instead of shifting Vx in place.
+    pub shift_uses_vy: bool,
+    /// How `Fx55`/`Fx65` leave the index register.
+    pub index_increment: IndexIncrement,
+    /// `Bnnn` is decoded as `Bxnn` and jumps to `xnn + Vx` instead of `nnn + V0`.
+    pub jump_uses_vx: bool,
+    /// Sprites are clipped at the screen edges instead of wrapping around.
+    pub clip_sprites: bool,
+    /// `8xy1`/`8xy2`/`8xy3` reset VF to 0.
+    pub logic_resets_vf: bool,
+    /// `Dxyn` waits for the next vertical blank before drawing.
+    pub display_wait: bool,
+    /// `Dxy0` draws an 8x16 sprite in low resolution instead of a 16x16 one.
+    pub lores_tall_sprites: bool,
+}
+
+impl Quirks {
+    /// Returns the quirks of the given platform.
+    pub fn for_platform(platform: Platform) -> Quirks {
+        match platform {
+            Platform::CosmacVip => Quirks::cosmac_vip(),
+            Platform::Chip48 => Quirks::chip48(),
+            Platform::SuperChip => Quirks::superchip(),
+            Platform::XoChip => Quirks::xochip(),
+        }
+    }
+
+    /// The behaviour of the original COSMAC VIP interpreter.
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_uses_vx: false,
+            clip_sprites: true,
+            logic_resets_vf: true,
+            display_wait: true,
+            lores_tall_sprites: false,
+        }
+    }
+
+    /// The behaviour of CHIP-48 on the HP-48 calculators.
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::ByX,
+            jump_uses_vx: true,
+            clip_sprites: true,
+            logic_resets_vf: false,
+            display_wait: false,
+            lores_tall_sprites: true,
+        }
+    }
+
+    /// The behaviour of SUPER-CHIP 1.1.
+    pub fn superchip() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::None,
+            jump_uses_vx: true,
+            clip_sprites: true,
+            logic_resets_vf: false,
+            display_wait: false,
+            lores_tall_sprites: true,
+        }
+    }
+
+    /// The behaviour of XO-CHIP as implemented by Octo.
+    pub fn xochip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_uses_vx: false,
+            clip_sprites: false,
+            logic_resets_vf: false,
+            display_wait: false,
+            lores_tall_sprites: false,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::None,
+            jump_uses_vx: false,
+            clip_sprites: false,
+            logic_resets_vf: false,
+            display_wait: false,
+            lores_tall_sprites: false,
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use crate::cpu::CPU;
+use crate::savestate::SaveStateError;
+
+/// The limits of the rewind history.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RewindConfig {
+    /// The maximum number of frames that can be rewound.
+    pub max_frames: usize,
+    /// The maximum number of bytes used by the snapshots, the oldest ones being dropped first.
+    pub max_bytes: usize,
+}
+
+impl Default for RewindConfig {
+    /// Keeps up to 30 seconds of history at 60 frames per second, in at most 16 MiB.
+    fn default() -> Self {
+        RewindConfig {
+            max_frames: 30 * 60,
+            max_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// A bounded history of machine snapshots, recorded every frame and played back in reverse.
+///
+/// Only the newest snapshot is kept in full. Every older one is stored as the XOR of itself and
+/// its successor, with the runs of zero bytes compressed, so a frame in which little happened
+/// costs a few bytes.
+#[derive(Clone, Debug)]
+pub struct RewindBuffer {
+    /// The limits of the history.
+    config: RewindConfig,
+    /// The newest snapshot, as written by `CPU::save_state`.
+    latest: Option<Vec<u8>>,
+    /// The compressed deltas between consecutive snapshots, the newest at the back.
+    deltas: VecDeque<Vec<u8>>,
+    /// The total size of the deltas in bytes.
+    delta_bytes: usize,
+}
+
+impl RewindBuffer {
+    /// Creates an empty `RewindBuffer`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The limits of the history.
+    pub fn new(config: RewindConfig) -> Self {
+        RewindBuffer {
+            config,
+            latest: None,
+            deltas: VecDeque::new(),
+            delta_bytes: 0,
+        }
+    }
+
+    /// Returns the limits of the history.
+    pub fn config(&self) -> RewindConfig {
+        self.config
+    }
+
+    /// Returns the number of frames that can be rewound.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Returns true if no frame can be rewound.
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Returns the number of bytes used by the snapshots.
+    pub fn memory_usage(&self) -> usize {
+        self.latest.as_ref().map_or(0, Vec::len) + self.delta_bytes
+    }
+
+    /// Drops the whole history.
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+        self.delta_bytes = 0;
+    }
+
+    /// Records a snapshot of the machine, dropping the oldest ones if over the limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The machine, usually at the end of a frame.
+    pub fn record(&mut self, cpu: &CPU) {
+        let snapshot = cpu.save_state();
+        if let Some(latest) = self.latest.take() {
+            if latest.len() == snapshot.len() {
+                let delta = compress(&xor(&latest, &snapshot));
+                self.delta_bytes += delta.len();
+                self.deltas.push_back(delta);
+            } else {
+                // The machine changed shape, the old snapshots cannot be diffed against it
+                self.deltas.clear();
+                self.delta_bytes = 0;
+            }
+        }
+        self.latest = Some(snapshot);
+
+        while self.deltas.len() > self.config.max_frames || self.memory_usage() > self.config.max_bytes {
+            match self.deltas.pop_front() {
+                Some(delta) => self.delta_bytes -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Restores the machine to the frame before the newest snapshot and drops that snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpu` - The machine to restore.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, SaveStateError>` - False if the history is exhausted and nothing was restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `SaveStateError` if the machine no longer matches the recorded snapshots,
+    /// for example after switching platforms.
+    pub fn rewind(&mut self, cpu: &mut CPU) -> Result<bool, SaveStateError> {
+        let (Some(latest), Some(delta)) = (self.latest.as_mut(), self.deltas.pop_back()) else {
+            return Ok(false);
+        };
+        self.delta_bytes -= delta.len();
+        decompress_xor(&delta, latest);
+        cpu.load_state(latest)?;
+        Ok(true)
+    }
+}
+
+/// Returns the bytewise XOR of two buffers of the same length.
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+}
+
+/// Compresses a delta into pairs of a run of zero bytes and a run of literal bytes.
+///
+/// Each pair is written as a `u16` zero count, a `u16` literal count and the literals.
+fn compress(delta: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < delta.len() {
+        let zeros = delta[pos..].iter().take(u16::MAX as usize).take_while(|&&b| b == 0).count();
+        pos += zeros;
+        let literals = delta[pos..].iter().take(u16::MAX as usize).take_while(|&&b| b != 0).count();
+        out.extend_from_slice(&(zeros as u16).to_le_bytes());
+        out.extend_from_slice(&(literals as u16).to_le_bytes());
+        out.extend_from_slice(&delta[pos..pos + literals]);
+        pos += literals;
+    }
+    out
+}
+
+/// Applies a delta written by `compress` to a snapshot in place.
+fn decompress_xor(compressed: &[u8], snapshot: &mut [u8]) {
+    let mut pos = 0;
+    let mut input = compressed;
+    while input.len() >= 4 {
+        let zeros = u16::from_le_bytes([input[0], input[1]]) as usize;
+        let literals = u16::from_le_bytes([input[2], input[3]]) as usize;
+        pos += zeros;
+        for (byte, delta) in snapshot[pos..pos + literals].iter_mut().zip(&input[4..4 + literals]) {
+            *byte ^= delta;
+        }
+        pos += literals;
+        input = &input[4 + literals..];
+    }
+}
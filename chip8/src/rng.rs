@@ -0,0 +1,125 @@
+use rand::Rng;
+
+/// A source of random bytes for the `Cxkk` instruction.
+///
+/// The state is exposed as a `u64` so that save states and movies can capture it.
+pub trait RandomSource: Send {
+    /// Returns the next random byte.
+    fn next_byte(&mut self) -> u8;
+
+    /// Called on every 60 Hz timer tick, for generators that depend on the frame timing.
+    fn interrupt(&mut self) {}
+
+    /// Returns the internal state of the generator, 0 if it has none that can be restored.
+    fn state(&self) -> u64 {
+        0
+    }
+
+    /// Restores a state returned by `state`.
+    fn set_state(&mut self, _state: u64) {}
+}
+
+/// Random bytes from the operating system seeded thread RNG. Not reproducible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadRandom;
+
+impl RandomSource for ThreadRandom {
+    fn next_byte(&mut self) -> u8 {
+        rand::thread_rng().gen::<u8>()
+    }
+}
+
+/// A deterministic xorshift64* generator, producing the same bytes for the same seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    /// Creates a new generator from a seed.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Any value. Xorshift cannot start from 0, so 0 is replaced by a fixed constant.
+    pub fn new(seed: u64) -> Self {
+        SeededRandom {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_byte(&mut self) -> u8 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> u64 {
+        self.state
+    }
+
+    fn set_state(&mut self, state: u64) {
+        *self = SeededRandom::new(state);
+    }
+}
+
+/// Builds the table standing in for the interpreter bytes read by `VipRandom`.
+const fn vip_table() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut value: u32 = 0xC3;
+    let mut i = 0;
+    while i < 256 {
+        value = (value * 109 + 89) % 251;
+        table[i] = value as u8;
+        i += 1;
+    }
+    table
+}
+
+/// The bytes read by `VipRandom`, indexed by its counter.
+const VIP_TABLE: [u8; 256] = vip_table();
+
+/// An approximation of the COSMAC VIP interpreter's random routine.
+///
+/// The VIP adds a byte of its own interpreter code, indexed by a counter that the display
+/// interrupt increments, to the previous random value. As the interpreter ROM is not
+/// distributed with this crate, a fixed generated table stands in for its bytes. The
+/// sequence is fully deterministic, but depends on how many frames pass between calls,
+/// as on the real machine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VipRandom {
+    counter: u8,
+    last: u8,
+}
+
+impl VipRandom {
+    /// Creates a new generator in the state of a freshly booted VIP.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RandomSource for VipRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.last = VIP_TABLE[self.counter as usize].wrapping_add(self.last);
+        self.counter = self.counter.wrapping_add(1);
+        self.last
+    }
+
+    fn interrupt(&mut self) {
+        self.counter = self.counter.wrapping_add(1);
+    }
+
+    fn state(&self) -> u64 {
+        (self.last as u64) << 8 | self.counter as u64
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.counter = state as u8;
+        self.last = (state >> 8) as u8;
+    }
+}
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::frontend::Rotation;
+use crate::hash::{from_hex, sha1, to_hex};
+use crate::json::{self, JsonError, JsonValue};
+use crate::quirks::{IndexIncrement, Platform, Quirks};
+
+/// The name of the imported copy of the community database in the config directory.
+pub const DATABASE_FILE: &str = "chip-8-database.json";
+/// The directory of the local per-ROM settings in the config directory, one `<sha1>.cfg` per ROM.
+pub const ROMS_DIR: &str = "roms";
+
+/// Overrides of some of the quirks of a platform.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuirkOverrides {
+    pub shift_uses_vy: Option<bool>,
+    pub index_increment: Option<IndexIncrement>,
+    pub jump_uses_vx: Option<bool>,
+    pub clip_sprites: Option<bool>,
+    pub logic_resets_vf: Option<bool>,
+    pub display_wait: Option<bool>,
+    pub lores_tall_sprites: Option<bool>,
+}
+
+impl QuirkOverrides {
+    /// Replaces the quirks that are overridden.
+    pub fn apply(&self, quirks: &mut Quirks) {
+        quirks.shift_uses_vy = self.shift_uses_vy.unwrap_or(quirks.shift_uses_vy);
+        quirks.index_increment = self.index_increment.unwrap_or(quirks.index_increment);
+        quirks.jump_uses_vx = self.jump_uses_vx.unwrap_or(quirks.jump_uses_vx);
+        quirks.clip_sprites = self.clip_sprites.unwrap_or(quirks.clip_sprites);
+        quirks.logic_resets_vf = self.logic_resets_vf.unwrap_or(quirks.logic_resets_vf);
+        quirks.display_wait = self.display_wait.unwrap_or(quirks.display_wait);
+        quirks.lores_tall_sprites = self.lores_tall_sprites.unwrap_or(quirks.lores_tall_sprites);
+    }
+
+    /// Takes the overrides that `other` sets.
+    fn merge(&mut self, other: &QuirkOverrides) {
+        self.shift_uses_vy = other.shift_uses_vy.or(self.shift_uses_vy);
+        self.index_increment = other.index_increment.or(self.index_increment);
+        self.jump_uses_vx = other.jump_uses_vx.or(self.jump_uses_vx);
+        self.clip_sprites = other.clip_sprites.or(self.clip_sprites);
+        self.logic_resets_vf = other.logic_resets_vf.or(self.logic_resets_vf);
+        self.display_wait = other.display_wait.or(self.display_wait);
+        self.lores_tall_sprites = other.lores_tall_sprites.or(self.lores_tall_sprites);
+    }
+}
+
+/// The settings a ROM should run with. Unset fields keep the frontend's defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RomSettings {
+    pub title: Option<String>,
+    /// The platform, giving the memory size and the quirk preset.
+    pub platform: Option<Platform>,
+    /// The quirks that differ from the platform preset.
+    pub quirks: QuirkOverrides,
+    /// The number of instructions run per 60 Hz frame.
+    pub cycles_per_frame: Option<u32>,
+    /// The colours of the pixels as RGB, indexed by their XO-CHIP bitplanes.
+    pub colors: Vec<(u8, u8, u8)>,
+    /// The keypad keys of the game's actions, such as `up` or `a`.
+    pub keys: Vec<(String, u8)>,
+    /// Where the program is loaded and started.
+    pub load_address: Option<u16>,
+    /// How the screen is turned, for games written for rotated displays.
+    pub rotation: Option<Rotation>,
+}
+
+impl RomSettings {
+    /// Returns the quirks to run with: the platform preset, or the defaults, with the overrides applied.
+    pub fn quirks(&self) -> Quirks {
+        let mut quirks = self.platform.map_or_else(Quirks::default, Quirks::for_platform);
+        self.quirks.apply(&mut quirks);
+        quirks
+    }
+
+    /// Returns the keypad key of an action, such as `up` or `a`.
+    pub fn key(&self, action: &str) -> Option<u8> {
+        self.keys.iter().find(|(name, _)| name == action).map(|&(_, key)| key)
+    }
+
+    /// Takes the settings that `other` sets, keeping the others.
+    pub fn merge(&mut self, other: &RomSettings) {
+        if other.title.is_some() {
+            self.title = other.title.clone();
+        }
+        self.platform = other.platform.or(self.platform);
+        self.quirks.merge(&other.quirks);
+        self.cycles_per_frame = other.cycles_per_frame.or(self.cycles_per_frame);
+        if !other.colors.is_empty() {
+            self.colors = other.colors.clone();
+        }
+        for (name, key) in &other.keys {
+            self.keys.retain(|(existing, _)| existing != name);
+            self.keys.push((name.clone(), *key));
+        }
+        self.load_address = other.load_address.or(self.load_address);
+        self.rotation = other.rotation.or(self.rotation);
+    }
+
+    /// Parses the local settings format written by the `Display` implementation.
+    ///
+    /// Each line is `<setting> = <value>`, and lines starting with `#` are comments:
+    ///
+    /// ```text
+    /// title = Space Invaders
+    /// platform = schip
+    /// cycles-per-frame = 30
+    /// load-address = 0x200
+    /// colors = 000000 ffffff aaaaaa 555555
+    /// key.up = 5
+    /// rotation = 90
+    /// quirk.clip_sprites = false
+    /// quirk.index_increment = x+1
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RomDbError::Parse` with the line of the first malformed setting.
+    pub fn parse(text: &str) -> Result<RomSettings, RomDbError> {
+        let mut settings = RomSettings::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| RomDbError::Parse {
+                line: index + 1,
+                message: message.to_string(),
+            };
+            let (name, value) = line.split_once('=').ok_or_else(|| error("expected <setting> = <value>"))?;
+            let (name, value) = (name.trim(), value.trim());
+            match name {
+                "title" => settings.title = Some(value.to_string()),
+                "platform" => settings.platform = Some(Platform::from_name(value).ok_or_else(|| error("unknown platform"))?),
+                "cycles-per-frame" => {
+                    settings.cycles_per_frame = Some(value.parse().map_err(|_| error("expected a number"))?)
+                }
+                "load-address" => settings.load_address = Some(parse_addr(value).ok_or_else(|| error("expected an address"))?),
+                "rotation" => {
+                    settings.rotation = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .and_then(Rotation::from_degrees)
+                            .ok_or_else(|| error("expected a rotation of 0, 90, 180 or 270"))?,
+                    )
+                }
+                "colors" => {
+                    settings.colors = value
+                        .split_whitespace()
+                        .map(parse_color)
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| error("expected colours as rrggbb"))?
+                }
+                _ => {
+                    if let Some(action) = name.strip_prefix("key.") {
+                        let key = u8::from_str_radix(value, 16)
+                            .ok()
+                            .filter(|&key| key < 16)
+                            .ok_or_else(|| error("expected a keypad key from 0 to f"))?;
+                        settings.keys.push((action.to_string(), key));
+                    } else if let Some(quirk) = name.strip_prefix("quirk.") {
+                        parse_quirk(&mut settings.quirks, quirk, value).ok_or_else(|| error("invalid quirk"))?;
+                    } else {
+                        return Err(error(&format!("unknown setting '{}'", name)));
+                    }
+                }
+            }
+        }
+        Ok(settings)
+    }
+}
+
+impl fmt::Display for RomSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(title) = &self.title {
+            writeln!(f, "title = {}", title)?;
+        }
+        if let Some(platform) = self.platform {
+            writeln!(f, "platform = {}", platform.name())?;
+        }
+        if let Some(cycles) = self.cycles_per_frame {
+            writeln!(f, "cycles-per-frame = {}", cycles)?;
+        }
+        if let Some(addr) = self.load_address {
+            writeln!(f, "load-address = 0x{:03X}", addr)?;
+        }
+        if !self.colors.is_empty() {
+            let colors: Vec<String> = self
+                .colors
+                .iter()
+                .map(|(r, g, b)| format!("{:02x}{:02x}{:02x}", r, g, b))
+                .collect();
+            writeln!(f, "colors = {}", colors.join(" "))?;
+        }
+        for (action, key) in &self.keys {
+            writeln!(f, "key.{} = {:x}", action, key)?;
+        }
+        if let Some(rotation) = self.rotation {
+            writeln!(f, "rotation = {}", rotation.degrees())?;
+        }
+        let quirks = &self.quirks;
+        let flags = [
+            ("shift_uses_vy", quirks.shift_uses_vy),
+            ("jump_uses_vx", quirks.jump_uses_vx),
+            ("clip_sprites", quirks.clip_sprites),
+            ("logic_resets_vf", quirks.logic_resets_vf),
+            ("display_wait", quirks.display_wait),
+            ("lores_tall_sprites", quirks.lores_tall_sprites),
+        ];
+        for (name, value) in flags {
+            if let Some(value) = value {
+                writeln!(f, "quirk.{} = {}", name, value)?;
+            }
+        }
+        if let Some(increment) = quirks.index_increment {
+            writeln!(f, "quirk.index_increment = {}", increment.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an address, decimal or hexadecimal with a `0x` prefix.
+fn parse_addr(value: &str) -> Option<u16> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Parses a colour written as `rrggbb`, with an optional leading `#`.
+fn parse_color(value: &str) -> Option<(u8, u8, u8)> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    match from_hex(hex)?.as_slice() {
+        &[r, g, b] => Some((r, g, b)),
+        _ => None,
+    }
+}
+
+/// Sets a quirk override from its field name in `Quirks`.
+fn parse_quirk(quirks: &mut QuirkOverrides, name: &str, value: &str) -> Option<()> {
+    if name == "index_increment" {
+        quirks.index_increment = Some(IndexIncrement::from_name(value)?);
+        return Some(());
+    }
+    let value = value.parse().ok()?;
+    let field = match name {
+        "shift_uses_vy" => &mut quirks.shift_uses_vy,
+        "jump_uses_vx" => &mut quirks.jump_uses_vx,
+        "clip_sprites" => &mut quirks.clip_sprites,
+        "logic_resets_vf" => &mut quirks.logic_resets_vf,
+        "display_wait" => &mut quirks.display_wait,
+        "lores_tall_sprites" => &mut quirks.lores_tall_sprites,
+        _ => return None,
+    };
+    *field = Some(value);
+    Some(())
+}
+
+/// An error reading the ROM settings.
+#[derive(Debug)]
+pub enum RomDbError {
+    /// A file could not be read.
+    Io { path: PathBuf, error: io::Error },
+    /// The database is not valid JSON.
+    Json(JsonError),
+    /// The database is valid JSON but not laid out as the community database.
+    Format(String),
+    /// A local settings file is malformed.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for RomDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomDbError::Io { path, error } => write!(f, "cannot read {}: {}", path.display(), error),
+            RomDbError::Json(error) => write!(f, "{}", error),
+            RomDbError::Format(message) => write!(f, "not a CHIP-8 database: {}", message),
+            RomDbError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for RomDbError {}
+
+/// The quirk flags of the community database, all of which are off for `modernChip8`.
+const MODERN_CHIP8_FLAGS: [&str; 7] = [
+    "shift",
+    "memoryLeaveIUnchanged",
+    "memoryIncrementByX",
+    "vblank",
+    "wrap",
+    "jump",
+    "logic",
+];
+
+/// Maps a platform of the community database to the closest platform and quirks of this crate.
+///
+/// Platforms that cannot be emulated, such as MegaChip, give None.
+fn database_platform(id: &str) -> Option<(Platform, QuirkOverrides)> {
+    match id {
+        "originalChip8" | "hybridVIP" => Some((Platform::CosmacVip, QuirkOverrides::default())),
+        /* Modern interpreters keep the VIP memory layout but none of the quirks the database lists */
+        "modernChip8" => {
+            let flags = MODERN_CHIP8_FLAGS.iter().map(|flag| (flag.to_string(), JsonValue::Bool(false)));
+            Some((Platform::CosmacVip, database_quirks(&JsonValue::Object(flags.collect()))))
+        }
+        "chip48" => Some((Platform::Chip48, QuirkOverrides::default())),
+        "superchip1" | "superchip" => Some((Platform::SuperChip, QuirkOverrides::default())),
+        "xochip" => Some((Platform::XoChip, QuirkOverrides::default())),
+        _ => None,
+    }
+}
+
+/// Reads the quirks of a `quirkyPlatforms` entry of the community database.
+fn database_quirks(quirks: &JsonValue) -> QuirkOverrides {
+    let flag = |name: &str| quirks.get(name).and_then(JsonValue::as_bool);
+    let index_increment = match (flag("memoryLeaveIUnchanged"), flag("memoryIncrementByX")) {
+        (Some(true), _) => Some(IndexIncrement::None),
+        (_, Some(true)) => Some(IndexIncrement::ByX),
+        (Some(false), _) | (_, Some(false)) => Some(IndexIncrement::ByXPlusOne),
+        (None, None) => None,
+    };
+    QuirkOverrides {
+        /* The database flags describe the modern behaviour: shifting Vx in place and wrapping */
+        shift_uses_vy: flag("shift").map(|shift| !shift),
+        index_increment,
+        jump_uses_vx: flag("jump"),
+        clip_sprites: flag("wrap").map(|wrap| !wrap),
+        logic_resets_vf: flag("logic"),
+        display_wait: flag("vblank"),
+        /* The database has no flag for it, the platform decides */
+        lores_tall_sprites: None,
+    }
+}
+
+/// Reads the settings of one ROM entry of the community database.
+fn database_settings(title: Option<&str>, rom: &JsonValue) -> RomSettings {
+    let mut settings = RomSettings {
+        title: title.map(str::to_string),
+        ..RomSettings::default()
+    };
+    let platforms = rom.get("platforms").and_then(JsonValue::as_array).unwrap_or_default();
+    if let Some((id, (platform, quirks))) = platforms
+        .iter()
+        .filter_map(JsonValue::as_str)
+        .find_map(|id| Some((id, database_platform(id)?)))
+    {
+        settings.platform = Some(platform);
+        settings.quirks = quirks;
+        if let Some(overrides) = rom.get("quirkyPlatforms").and_then(|quirky| quirky.get(id)) {
+            settings.quirks.merge(&database_quirks(overrides));
+        }
+    }
+    settings.cycles_per_frame = rom
+        .get("tickrate")
+        .and_then(JsonValue::as_f64)
+        .filter(|&rate| rate >= 1.0)
+        .map(|rate| rate as u32);
+    settings.load_address = rom
+        .get("startAddress")
+        .and_then(JsonValue::as_f64)
+        .map(|addr| addr as u16);
+    settings.rotation = rom
+        .get("screenRotation")
+        .and_then(JsonValue::as_f64)
+        .and_then(|degrees| Rotation::from_degrees(degrees as u32));
+    if let Some(pixels) = rom
+        .get("colors")
+        .and_then(|colors| colors.get("pixels"))
+        .and_then(JsonValue::as_array)
+    {
+        settings.colors = pixels.iter().filter_map(|color| parse_color(color.as_str()?)).collect();
+    }
+    if let Some(keys) = rom.get("keys").and_then(JsonValue::as_object) {
+        settings.keys = keys
+            .iter()
+            .filter_map(|(action, key)| Some((action.clone(), key.as_f64().filter(|&key| key < 16.0)? as u8)))
+            .collect();
+    }
+    settings
+}
+
+/// The settings of the known ROMs, keyed by the SHA-1 of their contents.
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+    entries: HashMap<[u8; 20], RomSettings>,
+}
+
+impl RomDatabase {
+    /// Creates an empty database.
+    pub fn new() -> Self {
+        RomDatabase::default()
+    }
+
+    /// Returns the number of ROMs with settings.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no ROM has settings.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds settings for a ROM, merged over those it already has.
+    pub fn insert(&mut self, hash: [u8; 20], settings: &RomSettings) {
+        self.entries.entry(hash).or_default().merge(settings);
+    }
+
+    /// Returns the settings of the ROM with the given SHA-1.
+    pub fn lookup(&self, hash: &[u8; 20]) -> Option<&RomSettings> {
+        self.entries.get(hash)
+    }
+
+    /// Returns the settings of a ROM, hashing it first.
+    pub fn lookup_rom(&self, rom: &[u8]) -> Option<&RomSettings> {
+        self.lookup(&sha1(rom))
+    }
+
+    /// Imports the `programs.json` file of the community chip-8-database.
+    ///
+    /// Each program lists its ROMs by SHA-1 with their platforms, quirks, tick rate, colours,
+    /// keys, screen rotation and start address. The first platform this crate can emulate is used.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, RomDbError>` - The number of ROMs imported.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RomDbError::Json` if the file is not valid JSON and `RomDbError::Format` if it
+    /// is not an array of programs.
+    pub fn import_chip8_database(&mut self, text: &str) -> Result<usize, RomDbError> {
+        let document = json::parse(text).map_err(RomDbError::Json)?;
+        let programs = document
+            .as_array()
+            .ok_or_else(|| RomDbError::Format("expected an array of programs".to_string()))?;
+        let mut count = 0;
+        for program in programs {
+            let title = program.get("title").and_then(JsonValue::as_str);
+            let Some(roms) = program.get("roms").and_then(JsonValue::as_object) else {
+                continue;
+            };
+            for (hash, rom) in roms {
+                let Some(hash) = from_hex(hash).and_then(|bytes| <[u8; 20]>::try_from(bytes).ok()) else {
+                    continue;
+                };
+                self.insert(hash, &database_settings(title, rom));
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Loads the settings of a config directory: the imported database, then the local
+    /// `roms/<sha1>.cfg` files, which take precedence. Missing files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first file that cannot be read or parsed.
+    pub fn load_config_dir(&mut self, dir: &Path) -> Result<(), RomDbError> {
+        let io_error = |path: &Path, error| RomDbError::Io {
+            path: path.to_path_buf(),
+            error,
+        };
+        let database = dir.join(DATABASE_FILE);
+        match fs::read_to_string(&database) {
+            Ok(text) => {
+                self.import_chip8_database(&text)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(io_error(&database, e)),
+        }
+
+        let roms = dir.join(ROMS_DIR);
+        let entries = match fs::read_dir(&roms) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(io_error(&roms, e)),
+        };
+        let mut paths: Vec<PathBuf> = entries.filter_map(|entry| Some(entry.ok()?.path())).collect();
+        paths.sort();
+        for path in paths {
+            let hash = path
+                .file_stem()
+                .and_then(|stem| from_hex(stem.to_str()?))
+                .and_then(|bytes| <[u8; 20]>::try_from(bytes).ok());
+            let (Some(hash), Some("cfg")) = (hash, path.extension().and_then(|ext| ext.to_str())) else {
+                continue;
+            };
+            let text = fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+            let settings = RomSettings::parse(&text).map_err(|e| match e {
+                RomDbError::Parse { line, message } => RomDbError::Parse {
+                    line,
+                    message: format!("{}: {}", path.display(), message),
+                },
+                other => other,
+            })?;
+            self.insert(hash, &settings);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path of the local settings file of a ROM in a config directory.
+pub fn local_settings_path(dir: &Path, hash: &[u8; 20]) -> PathBuf {
+    dir.join(ROMS_DIR).join(format!("{}.cfg", to_hex(hash)))
+}
+
+/// Returns the config directory: `$CHIP8_CONFIG_DIR`, else `$XDG_CONFIG_HOME/chip8`,
+/// else `$HOME/.config/chip8`.
+pub fn default_config_dir() -> Option<PathBuf> {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    var("CHIP8_CONFIG_DIR")
+        .or_else(|| var("XDG_CONFIG_HOME").map(|dir| dir.join("chip8")))
+        .or_else(|| var("HOME").map(|dir| dir.join(".config").join("chip8")))
+}
@@ -0,0 +1,307 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::cpu::CPU;
+use crate::gpu::{HIRES_HEIGHT, HIRES_WIDTH};
+use crate::hash::crc32;
+use crate::memory::{AUDIO_PATTERN_SIZE, RPL_FLAGS};
+
+/// The bytes every save state starts with.
+pub const SAVE_STATE_MAGIC: [u8; 4] = *b"C8SS";
+
+/// The version of the save state format written by this crate.
+pub const SAVE_STATE_VERSION: u16 = 1;
+
+/// The size of the header: the magic, the version and the payload length.
+const HEADER_SIZE: usize = 4 + 2 + 4;
+
+/// The value stored for `waiting_for_key` when no `Fx0A` is waiting.
+const NOT_WAITING: u8 = 0xFF;
+
+/// Represents the reasons a save state cannot be loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveStateError {
+    /// The data does not start with `SAVE_STATE_MAGIC`.
+    BadMagic,
+    /// The state was written by an incompatible version of the format.
+    UnsupportedVersion { found: u16, supported: u16 },
+    /// The data ends before the state is complete.
+    Truncated,
+    /// The checksum does not match, the data is corrupted.
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The state was saved from a machine with a different amount of RAM.
+    MemorySizeMismatch { expected: usize, found: usize },
+    /// A field holds a value that cannot be restored.
+    InvalidValue { field: &'static str },
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveStateError::BadMagic => write!(f, "not a save state"),
+            SaveStateError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "save state version {} is not supported, this build reads version {}",
+                found, supported
+            ),
+            SaveStateError::Truncated => write!(f, "save state is truncated"),
+            SaveStateError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "save state is corrupted: checksum 0x{:08X} does not match 0x{:08X}",
+                found, expected
+            ),
+            SaveStateError::MemorySizeMismatch { expected, found } => write!(
+                f,
+                "save state has {} bytes of RAM but the machine has {}",
+                found, expected
+            ),
+            SaveStateError::InvalidValue { field } => write!(f, "save state has an invalid {}", field),
+        }
+    }
+}
+
+impl Error for SaveStateError {}
+
+/// Reads little-endian values from a save state.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SaveStateError> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or(SaveStateError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SaveStateError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SaveStateError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SaveStateError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn bool(&mut self, field: &'static str) -> Result<bool, SaveStateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SaveStateError::InvalidValue { field }),
+        }
+    }
+}
+
+/// The machine state decoded from a save state, before it is applied to the CPU.
+struct State<'a> {
+    pc: u16,
+    sp: u8,
+    i: u16,
+    dt: u8,
+    st: u8,
+    v: [u8; 16],
+    stack: [u16; 0x100],
+    ram: &'a [u8],
+    rpl: [u8; RPL_FLAGS],
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    audio_pattern_loaded: bool,
+    pitch: u8,
+    hires: bool,
+    plane_mask: u8,
+    video_buffer: &'a [u8],
+    key_state: u16,
+    waiting_for_key: Option<u8>,
+    halt: bool,
+    vblank_ready: bool,
+    rng_state: u64,
+}
+
+impl CPU {
+    /// Serializes the complete machine state.
+    ///
+    /// The format is the `C8SS` magic, a `u16` version and a `u32` payload length,
+    /// followed by the payload and its CRC-32. All values are little-endian.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The save state, to be restored with `load_state`.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(self.memory.memory.len() + HIRES_WIDTH * HIRES_HEIGHT + 1024);
+        let memory = &self.memory;
+        payload.extend_from_slice(&memory.pc.to_le_bytes());
+        payload.push(memory.sp);
+        payload.extend_from_slice(&memory.i.to_le_bytes());
+        payload.push(memory.dt);
+        payload.push(memory.st);
+        payload.extend_from_slice(&memory.v);
+        for entry in memory.stack {
+            payload.extend_from_slice(&entry.to_le_bytes());
+        }
+        payload.extend_from_slice(&(memory.memory.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&memory.memory);
+        payload.extend_from_slice(&memory.rpl);
+        payload.extend_from_slice(&memory.audio_pattern);
+        payload.push(memory.audio_pattern_loaded as u8);
+        payload.push(memory.pitch);
+
+        payload.push(self.gpu.is_hires() as u8);
+        payload.push(self.gpu.plane_mask());
+        for row in &self.gpu.video_buffer {
+            payload.extend_from_slice(row);
+        }
+
+        payload.extend_from_slice(&self.key_state.to_le_bytes());
+        payload.push(self.waiting_for_key.unwrap_or(NOT_WAITING));
+        payload.push(self.halt as u8);
+        payload.push(self.vblank_ready as u8);
+        payload.extend_from_slice(&self.rng.state().to_le_bytes());
+
+        let mut data = Vec::with_capacity(HEADER_SIZE + payload.len() + 4);
+        data.extend_from_slice(&SAVE_STATE_MAGIC);
+        data.extend_from_slice(&SAVE_STATE_VERSION.to_le_bytes());
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(&crc32(&payload).to_le_bytes());
+        data
+    }
+
+    /// Restores a state written by `save_state`.
+    ///
+    /// The state is fully validated first, so the CPU is left untouched on error.
+    /// The quirks are not part of the state and keep their current values.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `SaveStateError` if the data is not a save state, is corrupted, was written
+    /// by an unsupported version or does not match the memory size of the machine.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), SaveStateError> {
+        let mut header = Reader { data, pos: 0 };
+        if header.bytes(4).map_err(|_| SaveStateError::BadMagic)? != SAVE_STATE_MAGIC {
+            return Err(SaveStateError::BadMagic);
+        }
+        let version = header.u16()?;
+        if version != SAVE_STATE_VERSION {
+            return Err(SaveStateError::UnsupportedVersion {
+                found: version,
+                supported: SAVE_STATE_VERSION,
+            });
+        }
+        let length = header.u32()? as usize;
+        let payload = header.bytes(length)?;
+        let checksum = header.u32()?;
+        let expected = crc32(payload);
+        if checksum != expected {
+            return Err(SaveStateError::ChecksumMismatch {
+                expected,
+                found: checksum,
+            });
+        }
+
+        let state = self.decode_state(payload)?;
+        self.apply_state(state);
+        Ok(())
+    }
+
+    fn decode_state<'a>(&self, payload: &'a [u8]) -> Result<State<'a>, SaveStateError> {
+        let mut reader = Reader { data: payload, pos: 0 };
+        let pc = reader.u16()?;
+        let sp = reader.u8()?;
+        let i = reader.u16()?;
+        let dt = reader.u8()?;
+        let st = reader.u8()?;
+        let v = reader.bytes(16)?.try_into().unwrap();
+        let mut stack = [0; 0x100];
+        for entry in stack.iter_mut() {
+            *entry = reader.u16()?;
+        }
+        let ram_size = reader.u32()? as usize;
+        if ram_size != self.memory.memory.len() {
+            return Err(SaveStateError::MemorySizeMismatch {
+                expected: self.memory.memory.len(),
+                found: ram_size,
+            });
+        }
+        let ram = reader.bytes(ram_size)?;
+        let rpl = reader.bytes(RPL_FLAGS)?.try_into().unwrap();
+        let audio_pattern = reader.bytes(AUDIO_PATTERN_SIZE)?.try_into().unwrap();
+        let audio_pattern_loaded = reader.bool("audio pattern flag")?;
+        let pitch = reader.u8()?;
+        let hires = reader.bool("resolution")?;
+        let plane_mask = reader.u8()?;
+        if plane_mask > 3 {
+            return Err(SaveStateError::InvalidValue { field: "plane mask" });
+        }
+        let video_buffer = reader.bytes(HIRES_WIDTH * HIRES_HEIGHT)?;
+        let key_state = reader.u16()?;
+        let waiting_for_key = match reader.u8()? {
+            NOT_WAITING => None,
+            reg if reg < 16 => Some(reg),
+            _ => return Err(SaveStateError::InvalidValue { field: "key wait register" }),
+        };
+        let halt = reader.bool("halt flag")?;
+        let vblank_ready = reader.bool("vertical blank flag")?;
+        let rng_state = reader.u64()?;
+        if reader.pos != payload.len() {
+            return Err(SaveStateError::InvalidValue { field: "payload length" });
+        }
+
+        Ok(State {
+            pc,
+            sp,
+            i,
+            dt,
+            st,
+            v,
+            stack,
+            ram,
+            rpl,
+            audio_pattern,
+            audio_pattern_loaded,
+            pitch,
+            hires,
+            plane_mask,
+            video_buffer,
+            key_state,
+            waiting_for_key,
+            halt,
+            vblank_ready,
+            rng_state,
+        })
+    }
+
+    fn apply_state(&mut self, state: State) {
+        let memory = &mut self.memory;
+        memory.pc = state.pc;
+        memory.sp = state.sp;
+        memory.i = state.i;
+        memory.dt = state.dt;
+        memory.st = state.st;
+        memory.v = state.v;
+        memory.stack = state.stack;
+        memory.memory.copy_from_slice(state.ram);
+        memory.rpl = state.rpl;
+        memory.audio_pattern = state.audio_pattern;
+        memory.audio_pattern_loaded = state.audio_pattern_loaded;
+        memory.pitch = state.pitch;
+
+        self.gpu.set_hires(state.hires);
+        self.gpu.select_planes(state.plane_mask);
+        for (row, data) in self.gpu.video_buffer.iter_mut().zip(state.video_buffer.chunks(HIRES_WIDTH)) {
+            row.copy_from_slice(data);
+        }
+
+        self.key_state = state.key_state;
+        self.waiting_for_key = state.waiting_for_key;
+        self.halt = state.halt;
+        self.vblank_ready = state.vblank_ready;
+        self.rng.set_state(state.rng_state);
+    }
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use crate::gpu::GPU;
+use crate::hash::{adler32, crc32};
+
+/// The grey level of the pixels in PNG screenshots, indexed by their XO-CHIP bitplanes.
+const GREYS: [u8; 4] = [0, 255, 170, 85];
+
+/// The characters of the pixels in ASCII screenshots, indexed by their XO-CHIP bitplanes.
+const ASCII_PIXELS: [char; 4] = ['.', '#', 'o', '+'];
+
+/// The largest block of a stored deflate stream.
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// The file formats a screenshot can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// One character per pixel, one line per row.
+    Ascii,
+    /// A plain 1-bit portable bitmap, any lit plane being black.
+    Pbm,
+    /// An 8-bit greyscale PNG.
+    Png,
+}
+
+impl ImageFormat {
+    /// Picks the format from the extension of a path: `.txt`, `.pbm` or `.png`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        match path.as_ref().extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "txt" => Some(ImageFormat::Ascii),
+            "pbm" => Some(ImageFormat::Pbm),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+}
+
+/// A copy of the visible part of the display.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screenshot {
+    /// The width in pixels, 64 or 128.
+    pub width: usize,
+    /// The height in pixels, 32 or 64.
+    pub height: usize,
+    /// The bitplanes of each pixel, row by row.
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Copies the display of the current resolution.
+    pub fn capture(gpu: &GPU) -> Self {
+        let (width, height) = (gpu.width(), gpu.height());
+        let pixels = gpu.video_buffer[..height]
+            .iter()
+            .flat_map(|row| row[..width].iter().map(|pixel| pixel & 0x3))
+            .collect();
+        Screenshot { width, height, pixels }
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks(self.width)
+    }
+
+    /// Encodes the screenshot in a format.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The file format.
+    /// * `scale` - The size of each pixel in the image, only used by PNG.
+    pub fn encode(&self, format: ImageFormat, scale: usize) -> Vec<u8> {
+        match format {
+            ImageFormat::Ascii => self.to_ascii().into_bytes(),
+            ImageFormat::Pbm => self.to_pbm().into_bytes(),
+            ImageFormat::Png => self.to_png(scale),
+        }
+    }
+
+    /// Renders the screenshot as text, one character per pixel.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for row in self.rows() {
+            text.extend(row.iter().map(|&pixel| ASCII_PIXELS[pixel as usize]));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Encodes the screenshot as a plain (P1) portable bitmap.
+    pub fn to_pbm(&self) -> String {
+        let mut text = format!("P1\n{} {}\n", self.width, self.height);
+        for row in self.rows() {
+            let bits: Vec<&str> = row.iter().map(|&pixel| if pixel != 0 { "1" } else { "0" }).collect();
+            text.push_str(&bits.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Encodes the screenshot as an 8-bit greyscale PNG.
+    ///
+    /// The image data is stored uncompressed, which keeps the encoder small.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - The size of each pixel in the image, at least 1.
+    pub fn to_png(&self, scale: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+        let (width, height) = (self.width * scale, self.height * scale);
+
+        // Every scanline starts with its filter type, 0 for none
+        let mut raw = Vec::with_capacity((width + 1) * height);
+        for row in self.rows() {
+            let mut scanline = vec![0];
+            for &pixel in row {
+                scanline.extend(std::iter::repeat_n(GREYS[pixel as usize], scale));
+            }
+            for _ in 0..scale {
+                raw.extend_from_slice(&scanline);
+            }
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+/// Appends a PNG chunk: its length, type, data and the CRC-32 of the type and data.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps data in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
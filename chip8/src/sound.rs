@@ -0,0 +1,146 @@
+use std::f32::consts::PI;
+
+use crate::memory::{AUDIO_PATTERN_SIZE, DEFAULT_PITCH};
+
+/// The number of 1-bit samples in the XO-CHIP audio pattern buffer.
+const PATTERN_BITS: f32 = (AUDIO_PATTERN_SIZE * 8) as f32;
+
+/// The shape of the tone played by the buzzer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+/// The settings of the tone generator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoundConfig {
+    /// The frequency of the buzzer tone in Hz.
+    pub frequency: f32,
+    /// The shape of the buzzer tone.
+    pub waveform: Waveform,
+    /// The amplitude of the samples, from 0.0 to 1.0.
+    pub volume: f32,
+    /// The number of samples per second of the output.
+    pub sample_rate: u32,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        SoundConfig {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+            sample_rate: 44_100,
+        }
+    }
+}
+
+/// What the buzzer should be playing, taken from the CPU once per frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SoundState {
+    /// Whether the buzzer is on, which is while the sound timer is non-zero.
+    pub active: bool,
+    /// The XO-CHIP audio pattern, if the program loaded one with `F002`.
+    pub pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    /// The XO-CHIP pitch register, setting the playback rate of the pattern.
+    pub pitch: u8,
+}
+
+impl Default for SoundState {
+    fn default() -> Self {
+        SoundState {
+            active: false,
+            pattern: None,
+            pitch: DEFAULT_PITCH,
+        }
+    }
+}
+
+/// Returns the playback rate of the XO-CHIP audio pattern in bits per second.
+///
+/// # Arguments
+///
+/// * `pitch` - The pitch register, 64 being 4000 bits per second.
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+/// Generates PCM samples for the buzzer.
+///
+/// Frontends call `fill` from their audio callback with the latest `SoundState`.
+#[derive(Clone, Debug)]
+pub struct ToneGenerator {
+    /// The settings of the generator.
+    pub config: SoundConfig,
+    /// The position in the current period of the tone, or in the audio pattern, from 0.0 to 1.0.
+    phase: f32,
+}
+
+impl ToneGenerator {
+    /// Creates a new `ToneGenerator`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The settings of the generator.
+    pub fn new(config: SoundConfig) -> Self {
+        ToneGenerator { config, phase: 0.0 }
+    }
+
+    /// Fills a buffer with mono samples between -1.0 and 1.0.
+    ///
+    /// The buffer is filled with silence while the buzzer is inactive. Otherwise the audio
+    /// pattern is played if there is one, and the configured tone if not.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer to fill.
+    /// * `state` - What the buzzer should be playing.
+    pub fn fill(&mut self, buffer: &mut [f32], state: &SoundState) {
+        if !state.active {
+            self.phase = 0.0;
+            buffer.fill(0.0);
+            return;
+        }
+
+        let sample_rate = self.config.sample_rate as f32;
+        let step = match state.pattern {
+            Some(_) => pattern_rate(state.pitch) / PATTERN_BITS / sample_rate,
+            None => self.config.frequency / sample_rate,
+        };
+        for sample in buffer.iter_mut() {
+            let value = match &state.pattern {
+                Some(pattern) => {
+                    let bit = (self.phase * PATTERN_BITS) as usize % (AUDIO_PATTERN_SIZE * 8);
+                    if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                None => self.waveform_sample(),
+            };
+            *sample = value * self.config.volume;
+            self.phase = (self.phase + step).fract();
+        }
+    }
+
+    /// Returns the value of the configured waveform at the current phase.
+    fn waveform_sample(&self) -> f32 {
+        match self.config.waveform {
+            Waveform::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * self.phase - 1.0,
+            Waveform::Sine => (2.0 * PI * self.phase).sin(),
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::BitOr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::instructions::decode;
+use crate::memory::Memory;
+
+/// The severity of a trace record, from the most to the least important.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Faults and other failures.
+    Error,
+    /// Suspicious but recoverable situations.
+    Warn,
+    /// Rare events such as loading a program, resetting or halting.
+    Info,
+    /// Frequent events such as key presses.
+    Debug,
+    /// One or more records per instruction.
+    Trace,
+}
+
+impl Level {
+    /// Looks up a level by its lowercase name.
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The part of the emulator a trace record comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// Fetching and decoding opcodes.
+    Decode,
+    /// Executing instructions, including the per-instruction trace line.
+    Execute,
+    /// Loading programs and writes to memory.
+    Memory,
+    /// The keypad.
+    Input,
+}
+
+impl Category {
+    /// Looks up a category by its lowercase name.
+    pub fn from_name(name: &str) -> Option<Category> {
+        match name.to_ascii_lowercase().as_str() {
+            "decode" => Some(Category::Decode),
+            "execute" => Some(Category::Execute),
+            "memory" => Some(Category::Memory),
+            "input" => Some(Category::Input),
+            _ => None,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Category::Decode => "decode",
+            Category::Execute => "execute",
+            Category::Memory => "memory",
+            Category::Input => "input",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A set of categories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Categories(u8);
+
+impl Categories {
+    /// No category.
+    pub const NONE: Categories = Categories(0);
+    /// Every category.
+    pub const ALL: Categories = Categories(0xF);
+
+    /// Returns true if the set contains a category.
+    pub fn contains(self, category: Category) -> bool {
+        self.0 & category.bit() != 0
+    }
+
+    /// Parses a comma-separated list of category names, such as `execute,input`.
+    pub fn from_names(names: &str) -> Option<Categories> {
+        names
+            .split(',')
+            .try_fold(Categories::NONE, |set, name| Some(set | Category::from_name(name.trim())?))
+    }
+}
+
+impl From<Category> for Categories {
+    fn from(category: Category) -> Self {
+        Categories(category.bit())
+    }
+}
+
+impl<T: Into<Categories>> BitOr<T> for Categories {
+    type Output = Categories;
+
+    fn bitor(self, other: T) -> Categories {
+        Categories(self.0 | other.into().0)
+    }
+}
+
+impl BitOr for Category {
+    type Output = Categories;
+
+    fn bitor(self, other: Category) -> Categories {
+        Categories(self.bit() | other.bit())
+    }
+}
+
+/// A single trace message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub level: Level,
+    pub category: Category,
+    pub message: String,
+}
+
+impl fmt::Display for Record {
+    /// Formats the record as `[category] message`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.category, self.message)
+    }
+}
+
+/// A destination for trace records.
+pub trait TraceSink: Send {
+    /// Writes a record.
+    fn write(&mut self, record: &Record);
+
+    /// Flushes buffered records, if the sink buffers them.
+    fn flush(&mut self) {}
+}
+
+/// Discards every record.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullSink;
+
+impl TraceSink for NullSink {
+    fn write(&mut self, _record: &Record) {}
+}
+
+/// Writes the records to stderr, one per line.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrSink;
+
+impl TraceSink for StderrSink {
+    fn write(&mut self, record: &Record) {
+        eprintln!("{}", record);
+    }
+}
+
+/// Keeps the most recent records in memory.
+///
+/// The sink is a shared handle: keep a clone to read the records while the CPU owns the other.
+#[derive(Clone, Debug)]
+pub struct RingBufferSink {
+    capacity: usize,
+    records: Arc<Mutex<VecDeque<Record>>>,
+}
+
+impl RingBufferSink {
+    /// Creates a sink keeping at most `capacity` records, the oldest being dropped first.
+    pub fn new(capacity: usize) -> Self {
+        RingBufferSink {
+            capacity,
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Returns a copy of the records, the oldest first.
+    pub fn records(&self) -> Vec<Record> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Returns the records formatted as lines, the oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        self.records.lock().unwrap().iter().map(Record::to_string).collect()
+    }
+
+    /// Drops every record.
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+}
+
+impl TraceSink for RingBufferSink {
+    fn write(&mut self, record: &Record) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+    }
+}
+
+/// Writes the records to a file, one per line.
+pub struct FileSink {
+    writer: BufWriter<File>,
+}
+
+impl FileSink {
+    /// Creates or truncates the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the file cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(FileSink {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+}
+
+impl TraceSink for FileSink {
+    fn write(&mut self, record: &Record) {
+        // Tracing must not disturb the emulation, so write errors are ignored
+        let _ = writeln!(self.writer, "{}", record);
+    }
+
+    fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Filters trace records by level and category and passes them to a sink.
+///
+/// The default tracer is disabled, so that tracing costs a single check per call site.
+pub struct Tracer {
+    sink: Box<dyn TraceSink>,
+    /// The least important level written.
+    level: Level,
+    /// The categories written.
+    categories: Categories,
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Tracer::disabled()
+    }
+}
+
+impl Tracer {
+    /// Creates a tracer writing to a sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Where the records are written.
+    /// * `level` - The least important level written.
+    /// * `categories` - The categories written.
+    pub fn new(sink: Box<dyn TraceSink>, level: Level, categories: Categories) -> Self {
+        Tracer { sink, level, categories }
+    }
+
+    /// Creates a tracer that writes nothing.
+    pub fn disabled() -> Self {
+        Tracer::new(Box::new(NullSink), Level::Error, Categories::NONE)
+    }
+
+    /// Returns true if records of a level and category are written.
+    #[inline]
+    pub fn enabled(&self, level: Level, category: Category) -> bool {
+        level <= self.level && self.categories.contains(category)
+    }
+
+    /// Writes a record if its level and category are enabled.
+    ///
+    /// Use `enabled` first when building the message is expensive.
+    pub fn log(&mut self, level: Level, category: Category, message: fmt::Arguments) {
+        if self.enabled(level, category) {
+            self.sink.write(&Record {
+                level,
+                category,
+                message: message.to_string(),
+            });
+        }
+    }
+
+    /// Flushes the sink.
+    pub fn flush(&mut self) {
+        self.sink.flush();
+    }
+}
+
+/// The registers compared before and after an instruction for the trace line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RegisterSnapshot {
+    v: [u8; 16],
+    i: u16,
+    sp: u8,
+    dt: u8,
+    st: u8,
+}
+
+impl RegisterSnapshot {
+    pub(crate) fn capture(memory: &Memory) -> Self {
+        RegisterSnapshot {
+            v: memory.v,
+            i: memory.i,
+            sp: memory.sp,
+            dt: memory.dt,
+            st: memory.st,
+        }
+    }
+}
+
+/// Formats the trace line of an instruction: its address, opcode, mnemonic and the registers it
+/// changed, such as `0200 602A LD V0, 0x2A       V0=2A`.
+///
+/// The line has no timestamp or other varying field, so traces of two runs can be diffed.
+pub(crate) fn instruction_line(pc: u16, opcode: u16, before: &RegisterSnapshot, after: &Memory) -> String {
+    let mut line = format!("{:04X} {:04X} {:<18}", pc, opcode, decode(opcode).to_string());
+    for (reg, (old, new)) in before.v.iter().zip(after.v.iter()).enumerate() {
+        if old != new {
+            line.push_str(&format!(" V{:X}={:02X}", reg, new));
+        }
+    }
+    if before.i != after.i {
+        line.push_str(&format!(" I={:04X}", after.i));
+    }
+    if before.sp != after.sp {
+        line.push_str(&format!(" SP={:02X}", after.sp));
+    }
+    if before.dt != after.dt {
+        line.push_str(&format!(" DT={:02X}", after.dt));
+    }
+    if before.st != after.st {
+        line.push_str(&format!(" ST={:02X}", after.st));
+    }
+    line.truncate(line.trim_end().len());
+    line
+}
@@ -0,0 +1,118 @@
+#[cfg(test)]
+pub mod tests {
+    use std::fs;
+
+    use chip8::asm::{assemble, assemble_file};
+    use chip8::cpu::CPU;
+    use chip8::disasm::disassemble;
+
+    #[test]
+    fn test_instructions() {
+        let rom = assemble(
+            "
+            CLS
+            LD V0, 0x02
+            LD I, 0xFF0
+            DRW V0, V1, 5
+            LD [I], V3
+            LD V1, [I]
+            SAVE V2 - V4
+            LD I, LONG 0xABCD
+            SHR V5
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rom,
+            [
+                0x00, 0xE0, 0x60, 0x02, 0xAF, 0xF0, 0xD0, 0x15, 0xF3, 0x55, 0xF1, 0x65, 0x52, 0x42, 0xF0, 0x00, 0xAB,
+                0xCD, 0x85, 0x56
+            ]
+        );
+    }
+
+    #[test]
+    fn test_labels_constants_and_expressions() {
+        let rom = assemble(
+            "
+            SPRITE_HEIGHT = 5
+            start:
+                LD I, sprite
+                DRW V0, V0, SPRITE_HEIGHT
+                JP start
+            sprite:
+                DB 0xF0, 0x90, 1 << 4 | 0x8, -1
+                DW sprite + 2 * (SPRITE_HEIGHT - 4), $
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rom,
+            [0xA2, 0x06, 0xD0, 0x05, 0x12, 0x00, 0xF0, 0x90, 0x18, 0xFF, 0x02, 0x08, 0x02, 0x0A]
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(assemble("DB \"Hi\\n\", 0").unwrap(), [b'H', b'i', b'\n', 0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = assemble("CLS\n  LD V0, missing").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 10));
+        assert_eq!(error.message, "undefined symbol 'missing'");
+        assert_eq!(error.to_string(), "<source>:2:10: undefined symbol 'missing'");
+
+        let error = assemble("LD V0, 0x100").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 8));
+
+        let error = assemble("FOO V0").unwrap_err();
+        assert_eq!(error.message, "unknown instruction 'FOO'");
+
+        let error = assemble("ADD I, 5").unwrap_err();
+        assert_eq!(error.message, "invalid operands for ADD");
+
+        let error = assemble("first = second\nsecond = first\nLD V0, first").unwrap_err();
+        assert_eq!(error.message, "circular definition of 'first'");
+
+        let error = assemble("x: CLS\nx: CLS").unwrap_err();
+        assert_eq!(error.message, "duplicate symbol 'x'");
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("chip8_asm_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("sprites.asm"), "sprite: DB 0xFF\n").unwrap();
+        fs::write(dir.join("main.asm"), "LD I, sprite\nEXIT\nINCLUDE \"sprites.asm\"\n").unwrap();
+
+        let rom = assemble_file(dir.join("main.asm")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(rom, [0xA2, 0x04, 0x00, 0xFD, 0xFF]);
+    }
+
+    #[test]
+    fn test_disassembly_round_trip() {
+        let rom = [
+            0x22, 0x08, 0x30, 0x00, 0xF0, 0x00, 0x02, 0x0E, 0x6A, 0x05, 0xF3, 0x01, 0x00, 0xEE, 0xAA, 0x55,
+        ];
+        let source = disassemble(&rom, 0x200).to_string();
+
+        assert_eq!(assemble(&source).unwrap(), rom);
+    }
+
+    #[test]
+    fn test_runs_on_cpu() {
+        let rom = assemble("LD V0, 3\nloop: ADD V1, 2\nADD V0, -1\nSE V0, 0\nJP loop\nEXIT").unwrap();
+        let mut cpu = CPU::new();
+        cpu.load_program(&rom).unwrap();
+
+        while !cpu.halt {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.memory.read_reg(1), 6);
+    }
+}
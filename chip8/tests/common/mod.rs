@@ -0,0 +1,33 @@
+// Each test crate uses only some of the helpers
+#![allow(dead_code)]
+
+use chip8::cpu::CPU;
+use chip8::rng::SeededRandom;
+
+/// Returns a CPU with the program loaded at 0x200.
+pub fn cpu_with(program: &[u8]) -> CPU {
+    let mut cpu = CPU::new();
+    cpu.load_program(program).unwrap();
+    cpu
+}
+
+/// Returns a CPU running a program that draws random sprites and calls a subroutine forever.
+///
+/// # Arguments
+///
+/// * `seed` - The seed of the random generator.
+pub fn running_cpu(seed: u64) -> CPU {
+    let mut cpu = cpu_with(&[
+        0xC0, 0x3F, /* RND V0, 0x3F */
+        0xC1, 0x1F, /* RND V1, 0x1F */
+        0xF0, 0x29, /* LD F, V0 */
+        0xD0, 0x15, /* DRW V0, V1, 5 */
+        0x22, 0x0C, /* CALL 0x20C */
+        0x12, 0x00, /* JP 0x200 */
+        0xF2, 0x15, /* LD DT, V2 */
+        0x72, 0x01, /* ADD V2, 1 */
+        0x00, 0xEE, /* RET */
+    ]);
+    cpu.set_rng(Box::new(SeededRandom::new(seed)));
+    cpu
+}
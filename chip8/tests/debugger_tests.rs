@@ -0,0 +1,121 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::asm::assemble;
+    use chip8::cpu::CPU;
+    use chip8::debugger::{Debugger, Reply, StopReason};
+
+    /// A program with nested subroutine calls.
+    const PROGRAM: &str = "
+        LD V0, 1
+        CALL outer
+        ADD V0, 1
+        EXIT
+    outer:
+        CALL inner
+        RET
+    inner:
+        LD V1, 7
+        RET
+    ";
+
+    fn setup() -> (Debugger, CPU) {
+        let mut cpu = CPU::new();
+        cpu.load_program(&assemble(PROGRAM).unwrap()).unwrap();
+        (Debugger::new(20), cpu)
+    }
+
+    fn output(debugger: &mut Debugger, cpu: &mut CPU, line: &str) -> String {
+        match debugger.execute(cpu, line).unwrap() {
+            Reply::Output(text) => text,
+            Reply::Quit => panic!("unexpected quit"),
+        }
+    }
+
+    #[test]
+    fn test_step_and_breakpoints() {
+        let (mut debugger, mut cpu) = setup();
+        assert_eq!(debugger.step(&mut cpu, 2), StopReason::Step);
+        assert_eq!(cpu.memory.pc, 0x208);
+
+        debugger.add_breakpoint(0x20C);
+        assert_eq!(debugger.resume(&mut cpu), StopReason::Breakpoint(0x20C));
+        // Resuming from a breakpoint executes it instead of stopping again
+        assert_eq!(debugger.resume(&mut cpu), StopReason::Halted);
+        assert_eq!(cpu.memory.v[0], 2);
+        assert_eq!(cpu.memory.v[1], 7);
+    }
+
+    #[test]
+    fn test_step_over_and_out() {
+        let (mut debugger, mut cpu) = setup();
+        debugger.step(&mut cpu, 1);
+        assert_eq!(debugger.step_over(&mut cpu), StopReason::Reached(0x204));
+        assert_eq!(cpu.memory.v[1], 7);
+        assert_eq!(cpu.memory.sp, 0);
+
+        let (mut debugger, mut cpu) = setup();
+        debugger.step(&mut cpu, 3);
+        assert_eq!(cpu.memory.pc, 0x20C);
+        assert_eq!(debugger.step_out(&mut cpu), Ok(StopReason::Reached(0x20A)));
+        assert_eq!(debugger.step_out(&mut cpu), Ok(StopReason::Reached(0x204)));
+        assert!(debugger.step_out(&mut cpu).is_err());
+    }
+
+    #[test]
+    fn test_run_to() {
+        let (mut debugger, mut cpu) = setup();
+        assert_eq!(debugger.run_to(&mut cpu, 0x206), StopReason::Reached(0x206));
+        assert_eq!(cpu.memory.v[0], 2);
+    }
+
+    #[test]
+    fn test_commands() {
+        let (mut debugger, mut cpu) = setup();
+        assert_eq!(output(&mut debugger, &mut cpu, "b 0x20C"), "Breakpoint set at 0x020C\n");
+        assert_eq!(
+            output(&mut debugger, &mut cpu, "c"),
+            "Breakpoint at 0x020C\n=>* 0x020C: 6107  LD V1, 0x07\n"
+        );
+        assert_eq!(
+            output(&mut debugger, &mut cpu, "bt"),
+            "#0  0x020C\n#1  0x0208 (returns to 0x020A)\n#2  0x0202 (returns to 0x0204)\n"
+        );
+
+        output(&mut debugger, &mut cpu, "set V1 0x42");
+        output(&mut debugger, &mut cpu, "set pc 0x206");
+        assert_eq!(cpu.memory.v[1], 0x42);
+        assert_eq!(cpu.memory.pc, 0x206);
+
+        output(&mut debugger, &mut cpu, "w 0x300 0xAB 12");
+        assert_eq!(output(&mut debugger, &mut cpu, "x 0x300 2"), "0x0300: AB 0C\n");
+        assert_eq!(
+            output(&mut debugger, &mut cpu, "l 0x200 2"),
+            "    0x0200: 6001  LD V0, 0x01\n    0x0202: 2208  CALL 0x208\n"
+        );
+
+        let error = debugger.execute(&mut cpu, "set V1 300").unwrap_err();
+        assert_eq!(error.to_string(), "V1 must fit in a byte");
+        assert!(debugger.execute(&mut cpu, "frobnicate").is_err());
+        assert_eq!(debugger.execute(&mut cpu, "quit"), Ok(Reply::Quit));
+    }
+
+    #[test]
+    fn test_empty_line_repeats() {
+        let (mut debugger, mut cpu) = setup();
+        output(&mut debugger, &mut cpu, "step");
+        output(&mut debugger, &mut cpu, "");
+        output(&mut debugger, &mut cpu, "");
+        assert_eq!(cpu.memory.pc, 0x20C);
+    }
+
+    #[test]
+    fn test_waiting_for_key() {
+        let mut cpu = CPU::new();
+        cpu.load_program(&assemble("LD V2, K\nEXIT").unwrap()).unwrap();
+        let mut debugger = Debugger::new(20);
+        assert_eq!(debugger.resume(&mut cpu), StopReason::WaitingForKey);
+        output(&mut debugger, &mut cpu, "key 9");
+        assert_eq!(debugger.resume(&mut cpu), StopReason::Halted);
+        assert_eq!(cpu.memory.v[2], 9);
+    }
+}
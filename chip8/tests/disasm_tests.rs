@@ -0,0 +1,104 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::asm::assemble;
+    use chip8::disasm::{disassemble, Line};
+
+    #[test]
+    fn test_sprite_data_is_not_decoded() {
+        let rom = [
+            0xA2, 0x06, /* LD I, 0x206 */
+            0xD0, 0x15, /* DRW V0, V1, 5 */
+            0x12, 0x04, /* JP 0x204 */
+            0xF0, 0x90, 0x90, 0x90, 0xF0, /* sprite */
+        ];
+        let disassembly = disassemble(&rom, 0x200);
+
+        assert_eq!(disassembly.lines.len(), 4);
+        assert_eq!(
+            disassembly.lines[2],
+            Line::Code {
+                addr: 0x204,
+                bytes: vec![0x12, 0x04],
+                text: "JP label_204".to_string()
+            }
+        );
+        assert_eq!(
+            disassembly.lines[3],
+            Line::Data {
+                addr: 0x206,
+                bytes: vec![0xF0, 0x90, 0x90, 0x90, 0xF0]
+            }
+        );
+        assert_eq!(disassembly.labels[&0x206], "data_206");
+    }
+
+    #[test]
+    fn test_calls_and_skips_are_followed() {
+        let rom = [
+            0x22, 0x08, /* CALL 0x208 */
+            0x30, 0x00, /* SE V0, 0x00 */
+            0x00, 0xFD, /* EXIT */
+            0x00, 0xFD, /* EXIT */
+            0x70, 0x01, /* ADD V0, 0x01 */
+            0x00, 0xEE, /* RET */
+            0xFF, 0xFF, /* unreachable */
+        ];
+        let disassembly = disassemble(&rom, 0x200);
+
+        let code: Vec<u16> = disassembly
+            .lines
+            .iter()
+            .filter(|line| matches!(line, Line::Code { .. }))
+            .map(Line::addr)
+            .collect();
+        assert_eq!(code, [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A]);
+        assert_eq!(disassembly.labels[&0x200], "start");
+        assert_eq!(disassembly.labels[&0x208], "sub_208");
+    }
+
+    #[test]
+    fn test_skip_over_long_load() {
+        let rom = [
+            0x30, 0x00, /* SE V0, 0x00 */
+            0xF0, 0x00, 0x02, 0x08, /* LD I, LONG 0x0208 */
+            0x00, 0xFD, /* EXIT */
+            0xAA, /* data */
+        ];
+        let listing = disassemble(&rom, 0x200).to_string();
+
+        assert!(listing.contains("LD I, LONG data_208"));
+        assert!(listing.contains("EXIT"));
+        assert!(listing.contains("DB 0xAA"));
+    }
+
+    #[test]
+    fn test_target_inside_instruction() {
+        let rom = [
+            0xF0, 0x00, 0x02, 0x0A, /* LD I, LONG 0x020A */
+            0xA2, 0x02, /* LD I, 0x202, the operand of the LD I, LONG */
+            0x22, 0x0A, /* CALL 0x20A */
+            0x12, 0x00, /* JP 0x200 */
+            0xF0, 0x00, 0x00, 0x00, /* LD I, LONG 0x0000 */
+            0x00, 0xEE, /* RET */
+        ];
+        let disassembly = disassemble(&rom, 0x200);
+        let listing = disassembly.to_string();
+
+        assert!(listing.contains("LD I, start+2"));
+        assert!(!disassembly.labels.contains_key(&0x202));
+        for label in disassembly.labels.values() {
+            assert!(listing.contains(&format!("{}:", label)));
+        }
+        assert_eq!(assemble(&listing).unwrap(), rom);
+    }
+
+    #[test]
+    fn test_listing_format() {
+        let rom = [0x12, 0x00];
+        let listing = disassemble(&rom, 0x200).to_string();
+
+        assert_eq!(listing.lines().next(), Some("start:"));
+        assert!(listing.contains("JP start"));
+        assert!(listing.contains("; 0x200: 1200"));
+    }
+}
@@ -0,0 +1,136 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::error::Chip8Error;
+
+    #[test]
+    fn test_unknown_opcode() {
+        let mut cpu = CPU::new();
+
+        let error = cpu.execute(0x8008).unwrap_err();
+        assert_eq!(
+            error,
+            Chip8Error::UnknownOpcode {
+                pc: 0x200,
+                opcode: 0x8008
+            }
+        );
+        assert_eq!(cpu.memory.pc, 0x200);
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let mut cpu = CPU::new();
+
+        // RET with an empty stack
+        let error = cpu.execute(0x00EE).unwrap_err();
+        assert_eq!(
+            error,
+            Chip8Error::StackUnderflow {
+                pc: 0x200,
+                opcode: 0x00EE
+            }
+        );
+        assert_eq!(cpu.memory.sp, 0);
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let mut cpu = CPU::new();
+        cpu.load_program(&[0x22, 0x00]).unwrap(); // CALL 0x200
+
+        let error = loop {
+            if let Err(error) = cpu.cycle() {
+                break error;
+            }
+        };
+        assert!(matches!(error, Chip8Error::StackOverflow { pc: 0x200, opcode: 0x2200 }));
+    }
+
+    #[test]
+    fn test_bcd_out_of_bounds() {
+        let mut cpu = CPU::new();
+        cpu.memory.write_reg(0, 123);
+        cpu.memory.i = 0xFFE;
+
+        // LD B, V0
+        let error = cpu.execute(0xF033).unwrap_err();
+        assert_eq!(
+            error,
+            Chip8Error::MemoryOutOfBounds {
+                pc: 0x200,
+                opcode: 0xF033,
+                addr: 0x1000
+            }
+        );
+        assert_eq!(error.pc(), Some(0x200));
+        assert_eq!(error.opcode(), Some(0xF033));
+    }
+
+    #[test]
+    fn test_sprite_out_of_bounds() {
+        let mut cpu = CPU::new();
+        cpu.memory.i = 0xFFC;
+
+        // DRW V0, V0, 5
+        let error = cpu.execute(0xD005).unwrap_err();
+        assert!(matches!(error, Chip8Error::MemoryOutOfBounds { addr: 0xFFC, .. }));
+    }
+
+    #[test]
+    fn test_long_load_out_of_bounds() {
+        let mut cpu = CPU::new();
+        cpu.memory.pc = 0xFFE;
+
+        // LD I, LONG with its operand past the end of memory
+        let error = cpu.execute(0xF000).unwrap_err();
+        assert_eq!(
+            error,
+            Chip8Error::MemoryOutOfBounds {
+                pc: 0xFFE,
+                opcode: 0xF000,
+                addr: 0x1000
+            }
+        );
+        assert_eq!(cpu.memory.pc, 0xFFE);
+    }
+
+    #[test]
+    fn test_pc_out_of_bounds() {
+        let mut cpu = CPU::new();
+        cpu.memory.pc = 0xFFF;
+
+        assert_eq!(cpu.cycle(), Err(Chip8Error::PcOutOfBounds { pc: 0xFFF }));
+    }
+
+    #[test]
+    fn test_program_too_large() {
+        let mut cpu = CPU::new();
+
+        let error = cpu.load_program(&[0; 0xE01]).unwrap_err();
+        assert_eq!(
+            error,
+            Chip8Error::ProgramTooLarge {
+                size: 0xE01,
+                max: 0xE00
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_address() {
+        let mut cpu = CPU::new();
+
+        let error = cpu.load_program_at(&[0; 0x201], 0xE00).unwrap_err();
+        assert_eq!(error, Chip8Error::ProgramTooLarge { size: 0x201, max: 0x200 });
+        assert_eq!(cpu.load_address(), 0x200);
+        assert_eq!(cpu.memory.pc, 0x200);
+
+        cpu.load_program_at(&[0x12, 0x00], 0x600).unwrap(); /* JP 0x200 */
+        assert_eq!(cpu.memory.pc, 0x600);
+        cpu.cycle().unwrap();
+        cpu.reset();
+        assert_eq!(cpu.memory.pc, 0x600);
+        assert_eq!(cpu.load_address(), 0x600);
+    }
+}
@@ -0,0 +1,202 @@
+mod common;
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::VecDeque;
+    use std::fs;
+    use std::time::Duration;
+
+    use chip8::cpu::CPU;
+    use chip8::frontend::{Audio, Command, Display, Input, Rotation, ScaleMode, Session, SessionConfig, Viewport};
+    use chip8::gpu::GPU;
+    use chip8::rewind::RewindConfig;
+    use chip8::sound::SoundState;
+
+    use crate::common::cpu_with;
+
+    /// Returns one batch of commands per frame, then quits.
+    struct ScriptedInput(VecDeque<Vec<Command>>);
+
+    impl Input for ScriptedInput {
+        fn poll(&mut self) -> Vec<Command> {
+            self.0.pop_front().unwrap_or_else(|| vec![Command::Quit])
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingDisplay {
+        frames: usize,
+        lit: usize,
+        messages: Vec<String>,
+        fullscreen: bool,
+    }
+
+    impl Display for RecordingDisplay {
+        fn present(&mut self, gpu: &GPU) {
+            self.frames += 1;
+            self.lit = gpu.video_buffer.iter().flatten().filter(|&&pixel| pixel != 0).count();
+        }
+
+        fn message(&mut self, text: &str) {
+            self.messages.push(text.to_string());
+        }
+
+        fn toggle_fullscreen(&mut self) {
+            self.fullscreen = !self.fullscreen;
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingAudio(Vec<bool>);
+
+    impl Audio for RecordingAudio {
+        fn update(&mut self, state: SoundState) {
+            self.0.push(state.active);
+        }
+    }
+
+    fn fast_session() -> Session {
+        Session::new(SessionConfig {
+            cycles_per_frame: 10,
+            frame_duration: Duration::ZERO,
+            rewind: RewindConfig::default(),
+        })
+    }
+
+    fn run(session: &mut Session, cpu: &mut CPU, script: Vec<Vec<Command>>) -> (RecordingDisplay, RecordingAudio) {
+        let mut display = RecordingDisplay::default();
+        let mut audio = RecordingAudio::default();
+        let mut input = ScriptedInput(script.into());
+        session.run(cpu, &mut display, &mut input, &mut audio);
+        (display, audio)
+    }
+
+    #[test]
+    fn test_frames_and_pause() {
+        let mut cpu = cpu_with(&[0x70, 0x01, 0x12, 0x00]); /* ADD V0, 1; JP 0x200 */
+        let script = vec![vec![], vec![Command::ToggleFullscreen], vec![Command::TogglePause], vec![Command::Step]];
+        let (display, _) = run(&mut fast_session(), &mut cpu, script);
+        assert_eq!(display.frames, 4);
+        assert!(display.fullscreen);
+        /* Two frames of 5 additions, then a single step while paused */
+        assert_eq!(cpu.memory.v[0], 11);
+        assert!(!cpu.halt);
+    }
+
+    #[test]
+    fn test_pause_does_not_resume_a_fault() {
+        let mut cpu = cpu_with(&[0x00, 0xEE]); /* RET */
+        let mut session = fast_session();
+        let script = vec![vec![], vec![Command::TogglePause], vec![Command::TogglePause], vec![Command::Step]];
+        let (display, _) = run(&mut session, &mut cpu, script);
+        assert!(cpu.halt);
+        assert!(!session.is_paused());
+        assert_eq!(display.messages.len(), 2);
+        assert_eq!(display.messages[1], "The program has halted, reset to run it again");
+    }
+
+    #[test]
+    fn test_keys_draw_and_sound() {
+        /* LD V0, K; LD F, V0; DRW V1, V1, 5; LD ST, V0; EXIT */
+        let mut cpu = cpu_with(&[0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0xF0, 0x18, 0x00, 0xFD]);
+        let mut session = fast_session();
+        let script = vec![
+            vec![],
+            vec![Command::Key { key: 8, pressed: true }],
+            vec![Command::ToggleMute],
+        ];
+        let (display, audio) = run(&mut session, &mut cpu, script);
+        assert_eq!(cpu.memory.v[0], 8);
+        assert_eq!(display.lit, 16); /* The 8 glyph */
+        /* The buzzer sounds once ST is set, but the CPU halted on 00FD in the same frame */
+        assert_eq!(audio.0, vec![false, false, false]);
+        assert!(session.is_muted());
+    }
+
+    #[test]
+    fn test_faults_are_shown() {
+        let mut cpu = cpu_with(&[0x00, 0xEE]); /* RET */
+        let (display, _) = run(&mut fast_session(), &mut cpu, vec![vec![]]);
+        assert!(cpu.halt);
+        assert_eq!(display.messages.len(), 1);
+        assert!(display.messages[0].contains("0x0200"));
+    }
+
+    #[test]
+    fn test_save_states_and_rewind() {
+        let rom = [0x70, 0x01, 0x12, 0x00]; /* ADD V0, 1; JP 0x200 */
+        let path = std::env::temp_dir().join(format!("chip8-frontend-{}.ch8", std::process::id()));
+        let mut session = fast_session();
+        session.set_rom(&path, &rom);
+        let mut cpu = cpu_with(&rom);
+        let script = vec![
+            vec![Command::NextSlot, Command::SaveState],
+            vec![],
+            vec![Command::LoadState],
+            vec![Command::Rewind(true)],
+        ];
+        let (display, _) = run(&mut session, &mut cpu, script);
+        let state_path = format!("{}.state1", path.display());
+        fs::remove_file(&state_path).unwrap();
+
+        assert_eq!(session.slot(), 1);
+        assert_eq!(
+            display.messages,
+            vec!["Save slot 1", "Saved state to slot 1", "Loaded state from slot 1"]
+        );
+        /* Each frame adds 5. Saved at 0, loaded at 10, run once more to 5, then rewound to the
+        frame before, which is the second one */
+        assert_eq!(cpu.memory.v[0], 10);
+    }
+
+    #[test]
+    fn test_movie_locks_reset_and_step() {
+        let rom = [0x70, 0x01, 0x12, 0x00];
+        let path = std::env::temp_dir().join(format!("chip8-frontend-movie-{}.ch8", std::process::id()));
+        let mut session = fast_session();
+        session.set_rom(&path, &rom);
+        let mut cpu = cpu_with(&rom);
+        let script = vec![
+            vec![Command::ToggleRecording],
+            vec![Command::Key { key: 1, pressed: true }],
+            vec![Command::Reset],
+            vec![Command::Step],
+            vec![Command::ToggleRecording],
+        ];
+        let (display, _) = run(&mut session, &mut cpu, script);
+        let movie_path = format!("{}.movie", path.display());
+        let movie = fs::read_to_string(&movie_path).unwrap();
+        fs::remove_file(&movie_path).unwrap();
+
+        assert_eq!(display.messages[0], "Recording movie");
+        assert_eq!(display.messages[1], "Not available while a movie is recording or playing");
+        assert_eq!(display.messages[2], "Not available while a movie is recording or playing");
+        assert!(display.messages[3].starts_with("Wrote 4 frames"));
+        assert!(movie.contains("key 1 down 1"));
+    }
+
+    #[test]
+    fn test_viewport() {
+        let viewport = |window, mode, rotation| {
+            let Viewport { x, y, width, height } = Viewport::new(window, mode, rotation);
+            (x, y, width, height)
+        };
+        /* Whole high-resolution pixels, centred */
+        assert_eq!(viewport((640, 320), ScaleMode::Integer, Rotation::None), (0, 0, 640, 320));
+        assert_eq!(viewport((700, 500), ScaleMode::Integer, Rotation::None), (30, 90, 640, 320));
+        assert_eq!(viewport((700, 500), ScaleMode::Fit, Rotation::None), (0, 75, 700, 350));
+        /* Pillarboxed when the window is too wide */
+        assert_eq!(viewport((1000, 300), ScaleMode::Fit, Rotation::None), (200, 0, 600, 300));
+        /* Sideways screens are 1:2 */
+        assert_eq!(viewport((640, 320), ScaleMode::Integer, Rotation::Quarter), (256, 32, 128, 256));
+        assert_eq!(viewport((640, 320), ScaleMode::Fit, Rotation::ThreeQuarters), (240, 0, 160, 320));
+        assert_eq!(viewport((640, 320), ScaleMode::Integer, Rotation::Half), (0, 0, 640, 320));
+        /* Windows smaller than one pixel per high-resolution pixel fall back to fitting */
+        assert_eq!(viewport((100, 100), ScaleMode::Integer, Rotation::None), (0, 25, 100, 50));
+
+        assert_eq!(Rotation::from_degrees(270), Some(Rotation::ThreeQuarters));
+        assert_eq!(Rotation::from_degrees(45), None);
+        assert_eq!(Rotation::Quarter.degrees(), 90);
+        assert_eq!(ScaleMode::from_name("fit"), Some(ScaleMode::Fit));
+    }
+}
@@ -0,0 +1,135 @@
+#[cfg(test)]
+pub mod tests {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use chip8::asm::assemble;
+    use chip8::cpu::CPU;
+    use chip8::gdbstub::{GdbStub, TARGET_XML};
+
+    /// A minimal RSP client.
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn send(&mut self, data: &str) -> String {
+            let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+            write!(self.stream, "${}#{:02x}", data, checksum).unwrap();
+            self.receive()
+        }
+
+        fn receive(&mut self) -> String {
+            let mut byte = [0];
+            loop {
+                self.stream.read_exact(&mut byte).unwrap();
+                if byte[0] == b'$' {
+                    break;
+                }
+            }
+            let mut data = Vec::new();
+            loop {
+                self.stream.read_exact(&mut byte).unwrap();
+                if byte[0] == b'#' {
+                    break;
+                }
+                data.push(byte[0]);
+            }
+            let mut checksum = [0; 2];
+            self.stream.read_exact(&mut checksum).unwrap();
+            self.stream.write_all(b"+").unwrap();
+            String::from_utf8(data).unwrap()
+        }
+    }
+
+    /// Starts a stub serving a program and connects a client to it.
+    fn connect(source: &str) -> (Client, thread::JoinHandle<CPU>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let rom = assemble(source).unwrap();
+        let server = thread::spawn(move || {
+            let mut cpu = CPU::new();
+            cpu.load_program(&rom).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            GdbStub::new(20).serve(&mut cpu, stream).unwrap();
+            cpu
+        });
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_nodelay(true).unwrap();
+        (Client { stream }, server)
+    }
+
+    #[test]
+    fn test_registers_and_memory() {
+        let (mut client, server) = connect("LD V3, 0x42\nLD I, 0x321\nEXIT");
+        assert!(client.send("qSupported:multiprocess+").contains("qXfer:features:read+"));
+        assert_eq!(client.send("?"), "S05");
+        assert_eq!(client.send("s"), "S05");
+        assert_eq!(client.send("s"), "S05");
+
+        let registers = client.send("g");
+        assert_eq!(registers.len(), 16 * 2 + 4 + 4 + 2 + 2 + 2);
+        assert_eq!(&registers[6..8], "42");
+        assert_eq!(client.send("p10"), "2103");
+        assert_eq!(client.send("p11"), "0402");
+        assert_eq!(client.send("p15"), "E01");
+
+        assert_eq!(client.send("P0=99"), "OK");
+        assert_eq!(client.send("m200,4"), "6342a321");
+        assert_eq!(client.send("M300,3:010203"), "OK");
+        assert_eq!(client.send("m300,3"), "010203");
+        assert_eq!(client.send("mfff,2"), "E01");
+        assert_eq!(client.send("mffffffffffffffff,1"), "E01");
+
+        assert_eq!(client.send("c"), "W00");
+        assert_eq!(client.send("D"), "OK");
+        let cpu = server.join().unwrap();
+        assert_eq!(cpu.memory.v[0], 0x99);
+        assert_eq!(&cpu.memory.memory[0x300..0x303], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let (mut client, server) = connect("loop:\nADD V0, 1\nADD V1, 2\nJP loop");
+        assert_eq!(client.send("Z0,202,2"), "OK");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("p11"), "0202");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("p0"), "02");
+        assert_eq!(client.send("z0,202,2"), "OK");
+
+        client.send("Z1,204,2");
+        assert_eq!(client.send("c"), "S05");
+        assert_eq!(client.send("p11"), "0402");
+        client.stream.write_all(b"$k#6b").unwrap();
+        drop(client);
+        let cpu = server.join().unwrap();
+        assert_eq!(cpu.memory.v[1], 4);
+    }
+
+    #[test]
+    fn test_target_description_and_monitor() {
+        let (mut client, server) = connect("EXIT");
+        let mut xml = String::new();
+        loop {
+            let reply = client.send(&format!("qXfer:features:read:target.xml:{:x},80", xml.len()));
+            xml.push_str(&reply[1..]);
+            if reply.starts_with('l') {
+                break;
+            }
+        }
+        assert_eq!(xml, TARGET_XML);
+
+        // monitor regs
+        let reply = client.send("qRcmd,72656773");
+        let bytes: Vec<u8> = (0..reply.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&reply[i..i + 2], 16).unwrap())
+            .collect();
+        assert!(String::from_utf8(bytes).unwrap().starts_with("PC: 0x0200"));
+        assert_eq!(client.send("vMustReplyEmpty"), "");
+        client.send("D");
+        server.join().unwrap();
+    }
+}
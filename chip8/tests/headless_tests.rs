@@ -0,0 +1,95 @@
+mod common;
+
+#[cfg(test)]
+pub mod tests {
+    use chip8::error::Chip8Error;
+    use chip8::hash::crc32;
+    use chip8::headless::{register_dump, HeadlessRunner, RunLimit, RunOutcome, ScriptedKey};
+    use chip8::screenshot::{ImageFormat, Screenshot};
+
+    use crate::common::cpu_with;
+
+    #[test]
+    fn test_outcomes() {
+        let runner = HeadlessRunner::new(10, RunLimit::Frames(5));
+        let mut cpu = cpu_with(&[0x60, 0x01, 0x00, 0xFD]); /* LD V0, 1; EXIT */
+        assert_eq!(runner.run(&mut cpu), RunOutcome::Halted { frames: 1 });
+
+        let mut cpu = cpu_with(&[0x12, 0x00]); /* JP 0x200 */
+        assert_eq!(runner.run(&mut cpu), RunOutcome::Timeout { frames: 5 });
+
+        let mut cpu = cpu_with(&[0x00, 0xEE]); /* RET */
+        assert_eq!(
+            runner.run(&mut cpu),
+            RunOutcome::Fault {
+                frames: 1,
+                error: Chip8Error::StackUnderflow { pc: 0x200, opcode: 0x00EE }
+            }
+        );
+    }
+
+    #[test]
+    fn test_cycle_limit() {
+        let runner = HeadlessRunner::new(10, RunLimit::Cycles(25));
+        let mut cpu = cpu_with(&[0x70, 0x01, 0x12, 0x00]); /* ADD V0, 1; JP 0x200 */
+        assert_eq!(runner.run(&mut cpu), RunOutcome::Timeout { frames: 3 });
+        assert_eq!(cpu.memory.v[0], 13);
+    }
+
+    #[test]
+    fn test_scripted_keys() {
+        let mut runner = HeadlessRunner::new(10, RunLimit::Frames(10));
+        runner.keys.push(ScriptedKey {
+            frame: 4,
+            key: 0xA,
+            duration: 2,
+        });
+        /* LD V1, K; ADD V2, 1; SKP V1; EXIT; JP 0x202 */
+        let mut cpu = cpu_with(&[0xF1, 0x0A, 0x72, 0x01, 0xE1, 0x9E, 0x00, 0xFD, 0x12, 0x02]);
+        let outcome = runner.run(&mut cpu);
+        assert_eq!(outcome, RunOutcome::Halted { frames: 7 });
+        assert_eq!(cpu.memory.v[1], 0xA);
+    }
+
+    #[test]
+    fn test_screenshots() {
+        /* LD I, font 0; DRW V0, V0, 5 draws a 0 in the top left corner */
+        let mut cpu = cpu_with(&[0xF0, 0x29, 0xD0, 0x05, 0x00, 0xFD]);
+        HeadlessRunner::new(10, RunLimit::Frames(1)).run(&mut cpu);
+        let screenshot = Screenshot::capture(cpu.get_gpu());
+        assert_eq!((screenshot.width, screenshot.height), (64, 32));
+
+        let ascii = screenshot.to_ascii();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 32);
+        assert_eq!(&lines[0][..5], "####.");
+        assert_eq!(&lines[1][..5], "#..#.");
+
+        let pbm = screenshot.to_pbm();
+        assert!(pbm.starts_with("P1\n64 32\n1 1 1 1 0 0"));
+
+        let png = screenshot.to_png(2);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 128, 0, 0, 0, 64]);
+        let ihdr_crc = u32::from_be_bytes(png[29..33].try_into().unwrap());
+        assert_eq!(ihdr_crc, crc32(&png[12..29]));
+        assert!(png.ends_with(b"IEND\xAE\x42\x60\x82"));
+
+        assert_eq!(ImageFormat::from_path("out/screen.PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path("screen.bmp"), None);
+    }
+
+    #[test]
+    fn test_register_dump() {
+        /* LD V3, 0x42; CALL 0x206; EXIT; LD DT, V3 */
+        let mut cpu = cpu_with(&[0x63, 0x42, 0x22, 0x06, 0x00, 0xFD, 0xF3, 0x15]);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let dump = register_dump(&cpu);
+        assert!(dump.starts_with("PC: 0x0208  SP: 0x01  I: 0x0000  DT: 0x42  ST: 0x00\n"));
+        assert!(dump.contains("V0: 0x00  V1: 0x00  V2: 0x00  V3: 0x42\n"));
+        assert!(dump.ends_with("Stack: [0x0204]\n"));
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::instructions::{decode, encode, Instruction};
+
+    #[test]
+    fn test_round_trip_every_opcode() {
+        for opcode in 0..=u16::MAX {
+            assert_eq!(encode(&decode(opcode)), opcode, "opcode {:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_decode_operands() {
+        assert_eq!(decode(0x5120), Instruction::SkipIfRegEqualsReg { x: 1, y: 2 });
+        assert_eq!(decode(0x3A42), Instruction::SkipIfRegEqualsByte { x: 0xA, kk: 0x42 });
+        assert_eq!(decode(0xD125), Instruction::DrawSprite { x: 1, y: 2, n: 5 });
+        assert_eq!(decode(0xB345), Instruction::JumpToAddressPlusV0 { nnn: 0x345 });
+        assert_eq!(decode(0xF301), Instruction::SelectPlanes { n: 3 });
+    }
+
+    #[test]
+    fn test_decode_unknown() {
+        // Only the exact 00E0 clears the screen
+        assert_eq!(decode(0x01E0), Instruction::Unknown(0x01E0));
+        assert_eq!(decode(0x5121), Instruction::Unknown(0x5121));
+        assert_eq!(decode(0x9121), Instruction::Unknown(0x9121));
+        assert_eq!(decode(0xF100), Instruction::Unknown(0xF100));
+    }
+
+    #[test]
+    fn test_display() {
+        let cases = [
+            (0x00E0, "CLS"),
+            (0x1234, "JP 0x234"),
+            (0x6002, "LD V0, 0x02"),
+            (0x8014, "ADD V0, V1"),
+            (0x8AB6, "SHR VA, VB"),
+            (0xAFF0, "LD I, 0xFF0"),
+            (0xB200, "JP V0, 0x200"),
+            (0xD01F, "DRW V0, V1, 0xF"),
+            (0xF00A, "LD V0, K"),
+            (0xF055, "LD [I], V0"),
+            (0xF165, "LD V1, [I]"),
+            (0x5242, "SAVE V2 - V4"),
+            (0xF000, "LD I, LONG"),
+            (0x00C4, "SCD 0x4"),
+            (0x8008, "DW 0x8008"),
+        ];
+
+        for (opcode, mnemonic) in cases {
+            assert_eq!(decode(opcode).to_string(), mnemonic);
+        }
+    }
+}
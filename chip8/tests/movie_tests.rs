@@ -0,0 +1,154 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::hash::{sha1, to_hex};
+    use chip8::memory::XO_MEM_SIZE;
+    use chip8::movie::{Movie, MovieError, MoviePlayer, MovieRecorder, CHECK_INTERVAL};
+    use chip8::quirks::{Platform, Quirks};
+
+    /// A program that draws a random sprite at random coordinates whenever key 5 is held.
+    const ROM: [u8; 20] = [
+        0x65, 0x05, /* LD V5, 0x05 */
+        0xE5, 0xA1, /* SKNP V5 */
+        0x12, 0x08, /* JP 0x208 */
+        0x12, 0x02, /* JP 0x202 */
+        0xC0, 0x3F, /* RND V0, 0x3F */
+        0xC1, 0x1F, /* RND V1, 0x1F */
+        0xC2, 0x0F, /* RND V2, 0x0F */
+        0xF2, 0x29, /* LD F, V2 */
+        0xD0, 0x15, /* DRW V0, V1, 5 */
+        0x12, 0x02, /* JP 0x202 */
+    ];
+
+    /// Records a session pressing and releasing key 5 a few times.
+    fn record(cpu: &mut CPU) -> Movie {
+        let mut recorder = MovieRecorder::start(cpu, &ROM, 1234, 20).unwrap();
+        for frame in 0..150 {
+            match frame % 30 {
+                3 => recorder.key_pressed(cpu, 5),
+                9 => recorder.key_released(cpu, 5),
+                _ => {}
+            }
+            recorder.run_frame(cpu).unwrap();
+        }
+        recorder.finish(cpu)
+    }
+
+    #[test]
+    fn test_sha1() {
+        assert_eq!(to_hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            to_hex(&sha1(&[b'a'; 1000])),
+            "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+        );
+    }
+
+    #[test]
+    fn test_playback_reproduces_session() {
+        let mut cpu = CPU::new();
+        let movie = record(&mut cpu);
+        assert_eq!(movie.frames, 150);
+        assert_eq!(movie.events.len(), 10);
+        assert_eq!(movie.checks.len(), (150 / CHECK_INTERVAL + 1) as usize);
+
+        let mut replay = CPU::new();
+        let mut player = MoviePlayer::start(movie, &mut replay, &ROM).unwrap();
+        while player.run_frame(&mut replay).unwrap() {}
+        assert!(player.is_finished());
+        assert_eq!(replay.save_state(), cpu.save_state());
+    }
+
+    #[test]
+    fn test_boot_clears_earlier_sessions() {
+        let mut cpu = CPU::new();
+        cpu.memory.memory[0x900] = 0xAA;
+        cpu.memory.push_stack(0x300).unwrap();
+        cpu.memory.rpl[0] = 7;
+        cpu.key_pressed(5);
+        let movie = record(&mut cpu);
+
+        let mut replay = CPU::new();
+        let mut player = MoviePlayer::start(movie, &mut replay, &ROM).unwrap();
+        while player.run_frame(&mut replay).unwrap() {}
+        assert_eq!(replay.save_state(), cpu.save_state());
+    }
+
+    #[test]
+    fn test_machine_is_recorded() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        cpu.quirks.display_wait = true;
+        let movie = record(&mut cpu);
+        let text = movie.to_string();
+        assert!(text.contains("platform xochip\nload-address 0x200\n"));
+        assert!(text.contains("quirk.display_wait true\n"));
+        assert!(text.contains("quirk.index_increment x+1\n"));
+
+        let mut replay = CPU::new();
+        let mut player = MoviePlayer::start(Movie::parse(&text).unwrap(), &mut replay, &ROM).unwrap();
+        while player.run_frame(&mut replay).unwrap() {}
+        assert_eq!(replay.platform(), Some(Platform::XoChip));
+        assert_eq!(replay.quirks, cpu.quirks);
+        assert_eq!(replay.memory.memory.len(), XO_MEM_SIZE);
+        assert_eq!(replay.save_state(), cpu.save_state());
+
+        /* Movies written before these records boot the default machine */
+        let old: Vec<&str> = text
+            .lines()
+            .filter(|line| !line.starts_with("platform") && !line.starts_with("load-address") && !line.starts_with("quirk."))
+            .collect();
+        let old = Movie::parse(&old.join("\n")).unwrap();
+        assert_eq!((old.platform, old.quirks, old.load_address), (None, Quirks::default(), 0x200));
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let movie = record(&mut CPU::new());
+        let text = movie.to_string();
+        assert!(text.starts_with("chip8-movie 1\n"));
+        assert!(text.contains("key 3 down 5\n"));
+        assert_eq!(Movie::parse(&text).unwrap(), movie);
+    }
+
+    #[test]
+    fn test_desync_is_detected() {
+        let mut movie = record(&mut CPU::new());
+        movie.events.retain(|event| event.frame > 100);
+
+        let mut cpu = CPU::new();
+        let mut player = MoviePlayer::start(movie, &mut cpu, &ROM).unwrap();
+        let error = loop {
+            match player.run_frame(&mut cpu) {
+                Ok(true) => {}
+                Ok(false) => panic!("movie finished without a desync"),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(error, MovieError::Desync { frame, .. } if frame == CHECK_INTERVAL));
+    }
+
+    #[test]
+    fn test_rom_mismatch() {
+        let movie = record(&mut CPU::new());
+        let error = MoviePlayer::start(movie, &mut CPU::new(), &ROM[..18]).err().unwrap();
+        assert!(matches!(error, MovieError::RomMismatch { .. }));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Movie::parse("hello").unwrap_err().to_string(),
+            "line 1: not a movie file"
+        );
+        assert_eq!(
+            Movie::parse("chip8-movie 1\nseed 1\nkey 0 sideways 5\n").unwrap_err(),
+            MovieError::Parse {
+                line: 3,
+                message: "invalid record 'key 0 sideways 5'".to_string()
+            }
+        );
+        assert_eq!(
+            Movie::parse("chip8-movie 1\nquirk.warp true\n").unwrap_err().to_string(),
+            "line 2: invalid quirk 'warp'"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+#[cfg(test)]
+pub mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chip8::cpu::CPU;
+    use chip8::observer::Chip8Observer;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum Event {
+        Fetch(u16, u16),
+        Retire(u16, u16),
+        Read(u16, u8),
+        Write(u16, u8),
+        Draw(u8, u8, u8, bool),
+        Push(u16, u8),
+        Pop(u16, u8),
+        KeyWait(u8),
+        SoundStart,
+        SoundStop,
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<Event>>>);
+
+    impl Recorder {
+        fn take(&self) -> Vec<Event> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+
+        fn push(&mut self, event: Event) {
+            self.0.lock().unwrap().push(event);
+        }
+    }
+
+    impl Chip8Observer for Recorder {
+        fn on_fetch(&mut self, pc: u16, opcode: u16) {
+            self.push(Event::Fetch(pc, opcode));
+        }
+
+        fn on_retire(&mut self, pc: u16, opcode: u16) {
+            self.push(Event::Retire(pc, opcode));
+        }
+
+        fn on_memory_read(&mut self, addr: u16, value: u8) {
+            self.push(Event::Read(addr, value));
+        }
+
+        fn on_memory_write(&mut self, addr: u16, value: u8) {
+            self.push(Event::Write(addr, value));
+        }
+
+        fn on_draw(&mut self, x: u8, y: u8, height: u8, collision: bool) {
+            self.push(Event::Draw(x, y, height, collision));
+        }
+
+        fn on_stack_push(&mut self, addr: u16, depth: u8) {
+            self.push(Event::Push(addr, depth));
+        }
+
+        fn on_stack_pop(&mut self, addr: u16, depth: u8) {
+            self.push(Event::Pop(addr, depth));
+        }
+
+        fn on_key_wait(&mut self, reg: u8) {
+            self.push(Event::KeyWait(reg));
+        }
+
+        fn on_sound_start(&mut self) {
+            self.push(Event::SoundStart);
+        }
+
+        fn on_sound_stop(&mut self) {
+            self.push(Event::SoundStop);
+        }
+    }
+
+    fn observed_cpu(program: &[u8]) -> (CPU, Recorder) {
+        let recorder = Recorder::default();
+        let mut cpu = CPU::new();
+        cpu.load_program(program).unwrap();
+        cpu.set_observer(Box::new(recorder.clone()));
+        (cpu, recorder)
+    }
+
+    #[test]
+    fn test_fetch_retire_and_memory() {
+        /* LD I, 0x300; LD V1, 7; LD [I], V1; LD V1, [I] */
+        let (mut cpu, recorder) = observed_cpu(&[0xA3, 0x00, 0x61, 0x07, 0xF1, 0x55, 0xF1, 0x65]);
+        cpu.quirks.index_increment = chip8::quirks::IndexIncrement::None;
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(
+            recorder.take(),
+            vec![
+                Event::Fetch(0x200, 0xA300),
+                Event::Retire(0x200, 0xA300),
+                Event::Fetch(0x202, 0x6107),
+                Event::Retire(0x202, 0x6107),
+                Event::Fetch(0x204, 0xF155),
+                Event::Write(0x300, 0),
+                Event::Write(0x301, 7),
+                Event::Retire(0x204, 0xF155),
+                Event::Fetch(0x206, 0xF165),
+                Event::Read(0x300, 0),
+                Event::Read(0x301, 7),
+                Event::Retire(0x206, 0xF165),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_faults_do_not_retire() {
+        let (mut cpu, recorder) = observed_cpu(&[0x00, 0xEE]); /* RET */
+        assert!(cpu.cycle().is_err());
+        assert_eq!(recorder.take(), vec![Event::Fetch(0x200, 0x00EE)]);
+    }
+
+    #[test]
+    fn test_draw_and_stack() {
+        /* CALL 0x204; ...; LD V0, 0xA; LD F, V0; DRW V0, V0, 5; DRW V0, V0, 5; RET */
+        let (mut cpu, recorder) = observed_cpu(&[
+            0x22, 0x04, 0x00, 0x00, 0x60, 0x0A, 0xF0, 0x29, 0xD0, 0x05, 0xD0, 0x05, 0x00, 0xEE,
+        ]);
+        cpu.quirks.display_wait = false;
+        for _ in 0..6 {
+            cpu.cycle().unwrap();
+        }
+        let events: Vec<Event> = recorder
+            .take()
+            .into_iter()
+            .filter(|event| !matches!(event, Event::Fetch(..) | Event::Retire(..)))
+            .collect();
+        let font = cpu.memory.i;
+        let mut expected = vec![Event::Push(0x202, 1)];
+        for collision in [false, true] {
+            expected.extend((0..5).map(|row| Event::Read(font + row, cpu.memory.memory[(font + row) as usize])));
+            expected.push(Event::Draw(0x0A, 0x0A, 5, collision));
+        }
+        expected.push(Event::Pop(0x202, 0));
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn test_key_wait_and_sound() {
+        /* LD V3, 2; LD ST, V3; LD V0, K */
+        let (mut cpu, recorder) = observed_cpu(&[0x63, 0x02, 0xF3, 0x18, 0xF0, 0x0A]);
+        cpu.run_frame(10).unwrap();
+        cpu.run_frame(10).unwrap();
+        let events: Vec<Event> = recorder
+            .take()
+            .into_iter()
+            .filter(|event| !matches!(event, Event::Fetch(..) | Event::Retire(..)))
+            .collect();
+        assert_eq!(events, vec![Event::SoundStart, Event::KeyWait(0), Event::SoundStop]);
+    }
+
+    #[test]
+    fn test_take_observer() {
+        let (mut cpu, recorder) = observed_cpu(&[0x60, 0x01, 0x60, 0x02]);
+        cpu.cycle().unwrap();
+        assert!(cpu.take_observer().is_some());
+        assert!(cpu.take_observer().is_none());
+        cpu.cycle().unwrap();
+        assert_eq!(recorder.take().len(), 2);
+    }
+}
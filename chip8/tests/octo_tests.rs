@@ -0,0 +1,165 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::octo::{compile, compile_at};
+
+    /// Compiles and runs a program until it exits, returning the CPU.
+    fn run(source: &str) -> CPU {
+        let program = compile(source).unwrap();
+        let mut cpu = CPU::new();
+        cpu.load_program(&program.rom).unwrap();
+        for _ in 0..10_000 {
+            if cpu.halt {
+                break;
+            }
+            cpu.cycle().unwrap();
+        }
+        assert!(cpu.halt, "program did not exit");
+        cpu
+    }
+
+    #[test]
+    fn test_entry_jump() {
+        // main first needs no jump
+        let program = compile(": main clear").unwrap();
+        assert_eq!(program.rom, [0x00, 0xE0]);
+
+        let program = compile(": helper return\n: main helper exit").unwrap();
+        assert_eq!(program.rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02, 0x00, 0xFD]);
+        assert_eq!(program.symbols["helper"], 0x202);
+        assert_eq!(program.symbols["main"], 0x204);
+    }
+
+    #[test]
+    fn test_statements() {
+        let program = compile(
+            ": main
+                v0 := 5  v1 += v0  v2 -= 1  v3 =- v4  v5 >>= v5
+                i := shape  i += v1  delay := v0  v6 := random 0xFF
+                sprite v0 v1 5  save v3  load v2 - v4  bcd v7
+                i := long shape
+                jump shape
+            : shape 0xF0 0x90",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program.rom,
+            [
+                0x60, 0x05, 0x81, 0x04, 0x72, 0xFF, 0x83, 0x47, 0x85, 0x56, 0xA2, 0x20, 0xF1, 0x1E, 0xF0, 0x15, 0xC6,
+                0xFF, 0xD0, 0x15, 0xF3, 0x55, 0x52, 0x43, 0xF7, 0x33, 0xF0, 0x00, 0x02, 0x20, 0x12, 0x20, 0xF0, 0x90
+            ]
+        );
+    }
+
+    #[test]
+    fn test_if_else() {
+        let cpu = run(
+            ": main
+                v0 := 3
+                if v0 == 3 then v1 := 1
+                if v0 != 3 then v1 := 2
+                if v0 == 4 begin
+                    v2 := 1
+                else
+                    v2 := 2
+                end
+                exit",
+        );
+        assert_eq!(cpu.memory.read_reg(1), 1);
+        assert_eq!(cpu.memory.read_reg(2), 2);
+    }
+
+    #[test]
+    fn test_loop_while() {
+        let cpu = run(
+            ": main
+                loop
+                    v0 += 1
+                    while v0 < 10
+                    v1 += 2
+                again
+                exit",
+        );
+        assert_eq!(cpu.memory.read_reg(0), 10);
+        assert_eq!(cpu.memory.read_reg(1), 18);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let cpu = run(
+            ": main
+                v0 := 5  v1 := 7
+                if v0 < v1 then va += 1
+                if v1 > v0 then va += 1
+                if v0 <= 5 then va += 1
+                if v0 >= 6 then va += 1
+                if v1 >= v1 then va += 1
+                if v1 > 200 then va += 1
+                exit",
+        );
+        assert_eq!(cpu.memory.read_reg(0xA), 4);
+    }
+
+    #[test]
+    fn test_directives() {
+        let program = compile(
+            ":alias counter v3
+            :const START 2
+            :calc DOUBLE { START * 3 + 1 }
+            :macro add-twice reg amount { reg += amount reg += amount }
+            : main
+                counter := DOUBLE
+                add-twice counter START
+                :next operand
+                v4 := 0
+                :unpack 0xA data
+                :unpack long data
+            : data",
+        )
+        .unwrap();
+
+        // :calc is evaluated right to left, so DOUBLE is 2 * (3 + 1)
+        assert_eq!(&program.rom[0..2], [0x63, 0x08]);
+        assert_eq!(&program.rom[2..6], [0x73, 0x02, 0x73, 0x02]);
+        assert_eq!(program.symbols["operand"], 0x207);
+        assert_eq!(&program.rom[8..12], [0x6A, 0xA2, 0x6B, 0x10]);
+        assert_eq!(&program.rom[12..16], [0x6A, 0x02, 0x6B, 0x10]);
+        assert_eq!(program.symbols["data"], 0x210);
+    }
+
+    #[test]
+    fn test_origin() {
+        let source = ": data 0x2A\n: main\n  i := data\n  load v0\n  exit";
+        let program = compile_at(source, 0x600).unwrap();
+        assert_eq!(program.symbols["main"], 0x603);
+        assert_eq!(&program.rom[..2], [0x16, 0x03]);
+
+        let mut cpu = CPU::new();
+        cpu.load_program_at(&program.rom, 0x600).unwrap();
+        while !cpu.halt {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.memory.v[0], 0x2A);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = compile(": main\n  v0 := missing").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 9));
+        assert_eq!(error.to_string(), "2:9: undefined name 'missing'");
+
+        let error = compile(": main\n  loop v0 += 1").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(error.message, "'loop' is never closed");
+
+        let error = compile(": main undefined-sub").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 8));
+
+        let error = compile(": main v0 := 256").unwrap_err();
+        assert_eq!(error.message, "value 256 does not fit in a byte");
+
+        let error = compile(": start exit").unwrap_err();
+        assert_eq!(error.message, "the program has no ': main' label");
+    }
+}
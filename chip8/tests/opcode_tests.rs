@@ -1,5 +1,4 @@
 #[cfg(test)]
-
 pub mod tests {
 
     use chip8::{cpu::CPU, gpu};
@@ -7,14 +6,14 @@ pub mod tests {
     fn test_jump_n() {
         let mut cpu = CPU::new();
         cpu.memory.pc = 0x200;
-        cpu.execute(0x1234); // Jump to address 0x234
+        cpu.execute(0x1234).unwrap(); // Jump to address 0x234
         assert_eq!(cpu.memory.pc, 0x234);
     }
     #[test]
     fn test_call_n() {
         let mut cpu = CPU::new();
         cpu.memory.pc = 0x200;
-        cpu.execute(0x2234); // Call address 0x234
+        cpu.execute(0x2234).unwrap(); // Call address 0x234
 
         // Check if the return address (next instruction) is pushed onto the stack
         assert_eq!(cpu.memory.stack[cpu.memory.sp as usize], 0x202);
@@ -31,7 +30,7 @@ pub mod tests {
         cpu.memory.write_reg(0, 0x02);
 
         // ADD V0, 0x02
-        cpu.execute(0x7002);
+        cpu.execute(0x7002).unwrap();
 
         // Verify that V0 is now 0x04
         assert_eq!(cpu.memory.v[0], 0x04);
@@ -45,11 +44,11 @@ pub mod tests {
         cpu.memory.write_reg(0, 0x02);
 
         // SE V0, 0x02 (Should Skip)
-        cpu.execute(0x3002);
+        cpu.execute(0x3002).unwrap();
         assert_eq!(cpu.memory.pc, 0x204);
 
         // SE V0, 0x03 (Should Not Skip)
-        cpu.execute(0x3003);
+        cpu.execute(0x3003).unwrap();
         assert_eq!(cpu.memory.pc, 0x206);
     }
 
@@ -61,11 +60,11 @@ pub mod tests {
         cpu.memory.write_reg(0, 0x02);
 
         // SNE V0, 0x02 (Should Not Skip)
-        cpu.execute(0x4002);
+        cpu.execute(0x4002).unwrap();
         assert_eq!(cpu.memory.pc, 0x202);
 
         // SNE V0, 0x03 (Should Skip)
-        cpu.execute(0x4003);
+        cpu.execute(0x4003).unwrap();
         assert_eq!(cpu.memory.pc, 0x206);
     }
 
@@ -76,7 +75,7 @@ pub mod tests {
         // For each register, set its value and then check its value
         for reg_i in 0..16 {
             // Execute the LD Vx, NN opcode
-            cpu.execute(0x6000 + (reg_i << 8) + 0x02);
+            cpu.execute(0x6000 + (reg_i << 8) + 0x02).unwrap();
 
             // Verify the value in the register
             assert_eq!(cpu.memory.v[reg_i as usize], 0x02);
@@ -92,7 +91,7 @@ pub mod tests {
         cpu.memory.write_reg(1, 0x02); // V1 = 0x02
 
         // Execute OR V0, V1
-        cpu.execute(0x8011);
+        cpu.execute(0x8011).unwrap();
 
         // Verify that V0 now holds the result of the OR operation
         assert_eq!(cpu.memory.v[0], 0x3);
@@ -107,7 +106,7 @@ pub mod tests {
         cpu.memory.write_reg(1, 0x01); // V1 = 0x01
 
         // Execute SE V0, V1
-        cpu.execute(0x5010);
+        cpu.execute(0x5010).unwrap();
 
         // Verify the program counter was incremented by 2 (skipped)
         assert_eq!(cpu.memory.pc, 0x204);
@@ -122,7 +121,7 @@ pub mod tests {
         cpu.memory.write_reg(1, 0x02); // V1 = 0x02
 
         // Execute LD V2, V0
-        cpu.execute(0x8200);
+        cpu.execute(0x8200).unwrap();
 
         // Validate register values
         assert_eq!(cpu.memory.read_reg(0), 0x1);
@@ -140,11 +139,11 @@ pub mod tests {
         cpu.memory.write_reg(2, 0x02); // V2 = 0x02
 
         // Execute AND V0, V1 and validate the result
-        cpu.execute(0x8012);
+        cpu.execute(0x8012).unwrap();
         assert_eq!(cpu.memory.read_reg(0), 0x0);
 
         // Execute AND V2, V1 and validate the result
-        cpu.execute(0x8212);
+        cpu.execute(0x8212).unwrap();
         assert_eq!(cpu.memory.read_reg(2), 0x2);
     }
 
@@ -157,7 +156,7 @@ pub mod tests {
         cpu.memory.write_reg(1, 0b11001100); // V2 = 0b11001100
 
         // Execute XOR Vx, Vy opcode (assuming 0x8013 represents XOR V1, V2)
-        cpu.execute(0x8013);
+        cpu.execute(0x8013).unwrap();
 
         // The result should be the bitwise XOR of the initial values
         assert_eq!(cpu.memory.read_reg(0), 0b01100110); // The result should be 0b01100110
@@ -170,24 +169,23 @@ pub mod tests {
         cpu.memory.write_reg(0, 6); // V0 = 6
         cpu.memory.write_reg(1, 4); // V1 = 4
 
-        cpu.execute(0x8015);
+        cpu.execute(0x8015).unwrap();
 
         assert_eq!(cpu.memory.read_reg(0), 0x2);
         assert_eq!(cpu.memory.read_reg(0xF), 0x1);
 
-        cpu.execute(0x8015);
+        cpu.execute(0x8015).unwrap();
 
         assert_eq!(cpu.memory.read_reg(0), 0xFE); //
         assert_eq!(cpu.memory.read_reg(0xF), 0x0);
     }
 
     #[test]
-
     fn test_return() {
         let mut cpu = CPU::new();
 
-        cpu.memory.push_stack(0x300);
-        cpu.execute(0x00EE);
+        cpu.memory.push_stack(0x300).unwrap();
+        cpu.execute(0x00EE).unwrap();
 
         assert_eq!(cpu.memory.pc, 0x300);
         assert_eq!(cpu.memory.sp, 0x0);
@@ -199,7 +197,7 @@ pub mod tests {
 
         cpu.get_gpu().video_buffer[5][5] = 1;
 
-        cpu.execute(0x00E0);
+        cpu.execute(0x00E0).unwrap();
 
         for x in 0..gpu::VRAM_WIDTH {
             for y in 0..gpu::VRAM_HEIGHT {
@@ -215,7 +213,7 @@ pub mod tests {
         cpu.memory.write_reg(0, 0x1); // V0 = 1
         cpu.memory.write_reg(1, 0x2); // V1 = 2
 
-        cpu.execute(0x8014);
+        cpu.execute(0x8014).unwrap();
 
         assert_eq!(cpu.memory.read_reg(0), 0x3);
     }
@@ -226,7 +224,7 @@ pub mod tests {
 
         cpu.memory.write_reg(0, 0b00001100); // V0 = 0b10101010
 
-        cpu.execute(0x8006);
+        cpu.execute(0x8006).unwrap();
 
         assert_eq!(cpu.memory.read_reg(0), 0b00000110);
         assert_eq!(cpu.memory.read_reg(0xF), 0b0);
@@ -238,50 +236,79 @@ pub mod tests {
 
         cpu.memory.write_reg(0, 0b00001100); // V0 = 0b10101010
 
-        cpu.execute(0x800E);
+        cpu.execute(0x800E).unwrap();
 
         assert_eq!(cpu.memory.read_reg(0), 0b000011000);
         assert_eq!(cpu.memory.read_reg(0xF), 0b0);
     }
 
     #[test]
-
     fn test_subtract_vy_vx() {
         let mut cpu = CPU::new();
 
         // Normal Case: V1 - V0 = 2 - 1
         cpu.memory.write_reg(0, 1); // V0 = 1
         cpu.memory.write_reg(1, 2); // V1 = 2
-        cpu.execute(0x8017);
+        cpu.execute(0x8017).unwrap();
         assert_eq!(cpu.memory.read_reg(0), 1); // 2 - 1 = 1
         assert_eq!(cpu.memory.read_reg(0xF), 1); // No borrow
 
         // Normal Case: Underflow, V1 - V0 = 1 - 2
         cpu.memory.write_reg(0, 2); // V0 = 2
         cpu.memory.write_reg(1, 1); // V1 = 1
-        cpu.execute(0x8017);
+        cpu.execute(0x8017).unwrap();
         assert_eq!(cpu.memory.read_reg(0), 255); // 1 - 2 = -1, wraps to 255 in u8
         assert_eq!(cpu.memory.read_reg(0xF), 0); // Borrow occurred
 
         // Edge Case: Subtracting identical values, V1 - V0 = 1 - 1
         cpu.memory.write_reg(0, 1); // V0 = 1
         cpu.memory.write_reg(1, 1); // V1 = 1
-        cpu.execute(0x8017);
+        cpu.execute(0x8017).unwrap();
         assert_eq!(cpu.memory.read_reg(0), 0);
         assert_eq!(cpu.memory.read_reg(0xF), 1); // No borrow
 
         // Edge Case: Subtracting from 0, V1 - V0 = 0 - 1
         cpu.memory.write_reg(0, 1); // V0 = 1
         cpu.memory.write_reg(1, 0); // V1 = 0
-        cpu.execute(0x8017);
+        cpu.execute(0x8017).unwrap();
         assert_eq!(cpu.memory.read_reg(0), 255);
         assert_eq!(cpu.memory.read_reg(0xF), 0); // Borrow occurred
 
         // Edge Case: Subtracting max u8 value, V1 - V0 = 255 - 1
         cpu.memory.write_reg(0, 1); // V0 = 1
         cpu.memory.write_reg(1, 255); // V1 = 255
-        cpu.execute(0x8017);
+        cpu.execute(0x8017).unwrap();
         assert_eq!(cpu.memory.read_reg(0), 254);
         assert_eq!(cpu.memory.read_reg(0xF), 1); // No borrow
     }
+
+    #[test]
+    fn test_flag_written_after_result() {
+        let mut cpu = CPU::new();
+
+        // SUB VF, V0 keeps the borrow flag rather than the difference
+        cpu.memory.write_reg(0xF, 5);
+        cpu.memory.write_reg(0, 3);
+        cpu.execute(0x8F05).unwrap();
+        assert_eq!(cpu.memory.read_reg(0xF), 1);
+
+        // ADD VF, V0 keeps the carry flag rather than the sum
+        cpu.memory.write_reg(0xF, 0xFF);
+        cpu.execute(0x8F04).unwrap();
+        assert_eq!(cpu.memory.read_reg(0xF), 1);
+    }
+
+    #[test]
+    fn test_reset_clears_keypad() {
+        let mut cpu = CPU::new();
+        cpu.key_pressed(3);
+        cpu.reset();
+        assert!(!cpu.is_key_pressed(3));
+
+        // LD V0, K is waiting, a key pressed after the reset must not complete it
+        cpu.execute(0xF00A).unwrap();
+        cpu.reset();
+        cpu.key_pressed(5);
+        assert_eq!(cpu.memory.read_reg(0), 0);
+    }
 }
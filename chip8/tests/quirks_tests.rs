@@ -0,0 +1,129 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::quirks::{Platform, Quirks};
+
+    #[test]
+    fn test_shift_uses_vy() {
+        let mut cpu = CPU::with_quirks(Quirks::cosmac_vip());
+
+        cpu.memory.write_reg(0, 0xFF); // V0 = 0xFF
+        cpu.memory.write_reg(1, 0b00001101); // V1 = 0b00001101
+
+        // SHR V0, V1 shifts V1 into V0
+        cpu.execute(0x8016).unwrap();
+        assert_eq!(cpu.memory.read_reg(0), 0b00000110);
+        assert_eq!(cpu.memory.read_reg(0xF), 1);
+
+        // SHL V0, V1 shifts V1 into V0
+        cpu.execute(0x801E).unwrap();
+        assert_eq!(cpu.memory.read_reg(0), 0b00011010);
+        assert_eq!(cpu.memory.read_reg(0xF), 0);
+    }
+
+    #[test]
+    fn test_store_regs_index_increment() {
+        let cases = [
+            (Platform::CosmacVip, 0x304),
+            (Platform::Chip48, 0x303),
+            (Platform::SuperChip, 0x300),
+        ];
+
+        for (platform, expected_i) in cases {
+            let mut cpu = CPU::with_quirks(Quirks::for_platform(platform));
+            for reg in 0..4 {
+                cpu.memory.write_reg(reg, reg + 1);
+            }
+            cpu.memory.write_reg(4, 0xAA);
+            cpu.memory.i = 0x300;
+
+            // LD [I], V3
+            cpu.execute(0xF355).unwrap();
+
+            assert_eq!(cpu.memory.i, expected_i);
+            assert_eq!(cpu.memory.memory[0x300..0x305], [1, 2, 3, 4, 0]);
+        }
+    }
+
+    #[test]
+    fn test_load_regs_only_up_to_vx() {
+        let mut cpu = CPU::new();
+        cpu.memory.memory[0x300..0x304].copy_from_slice(&[9, 8, 7, 6]);
+        cpu.memory.i = 0x300;
+
+        // LD V2, [I]
+        cpu.execute(0xF265).unwrap();
+
+        assert_eq!(cpu.memory.v[0..4], [9, 8, 7, 0]);
+        assert_eq!(cpu.memory.i, 0x300);
+    }
+
+    #[test]
+    fn test_jump_uses_vx() {
+        let mut cpu = CPU::with_quirks(Quirks::superchip());
+        cpu.memory.write_reg(0, 0x10);
+        cpu.memory.write_reg(2, 0x04);
+
+        // JP V2, 0x230
+        cpu.execute(0xB230).unwrap();
+        assert_eq!(cpu.memory.pc, 0x234);
+
+        let mut cpu = CPU::new();
+        cpu.memory.write_reg(0, 0x10);
+
+        // JP V0, 0x230
+        cpu.execute(0xB230).unwrap();
+        assert_eq!(cpu.memory.pc, 0x240);
+    }
+
+    #[test]
+    fn test_logic_resets_vf() {
+        let mut cpu = CPU::with_quirks(Quirks::cosmac_vip());
+        cpu.memory.write_reg(0xF, 0x01);
+
+        // OR V0, V1
+        cpu.execute(0x8011).unwrap();
+        assert_eq!(cpu.memory.read_reg(0xF), 0);
+    }
+
+    #[test]
+    fn test_clip_sprites() {
+        let mut wrapping = CPU::new();
+        let mut clipping = CPU::with_quirks(Quirks::superchip());
+
+        for cpu in [&mut wrapping, &mut clipping] {
+            cpu.memory.write_reg(0, 62); // x = 62
+            cpu.memory.write_reg(1, 30); // y = 30
+            cpu.memory.i = 0x0; // Font sprite for 0
+
+            // DRW V0, V1, 5
+            cpu.execute(0xD015).unwrap();
+        }
+
+        // The top row of the 0 glyph wraps to the left edge only without clipping
+        assert_eq!(wrapping.get_gpu().video_buffer[30][0], 1);
+        assert_eq!(clipping.get_gpu().video_buffer[30][0], 0);
+        assert_eq!(clipping.get_gpu().video_buffer[30][63], 1);
+
+        // The bottom rows wrap to the top edge only without clipping
+        assert_eq!(wrapping.get_gpu().video_buffer[0][62], 1);
+        assert_eq!(clipping.get_gpu().video_buffer[0][62], 0);
+    }
+
+    #[test]
+    fn test_display_wait() {
+        let mut cpu = CPU::with_quirks(Quirks::cosmac_vip());
+
+        // DRW V0, V0, 1 draws immediately after a vertical blank
+        cpu.execute(0xD001).unwrap();
+        assert_eq!(cpu.memory.pc, 0x202);
+
+        // A second draw waits for the next vertical blank
+        cpu.execute(0xD001).unwrap();
+        assert_eq!(cpu.memory.pc, 0x202);
+
+        cpu.vblank();
+        cpu.execute(0xD001).unwrap();
+        assert_eq!(cpu.memory.pc, 0x204);
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::quirks::Platform;
+    use chip8::rewind::{RewindBuffer, RewindConfig};
+    use chip8::rng::SeededRandom;
+
+    use crate::common::running_cpu;
+
+    #[test]
+    fn test_rewind_restores_previous_frames() {
+        let mut cpu = running_cpu(9);
+        let mut rewind = RewindBuffer::new(RewindConfig::default());
+        let mut states = vec![];
+        for _ in 0..10 {
+            cpu.run_frame(25).unwrap();
+            rewind.record(&cpu);
+            states.push(cpu.save_state());
+        }
+        assert_eq!(rewind.len(), 9);
+
+        for expected in states.iter().rev().skip(1) {
+            assert!(rewind.rewind(&mut cpu).unwrap());
+            assert_eq!(&cpu.save_state(), expected);
+        }
+        assert!(rewind.is_empty());
+        assert!(!rewind.rewind(&mut cpu).unwrap());
+        assert_eq!(cpu.save_state(), states[0]);
+    }
+
+    #[test]
+    fn test_record_after_rewind() {
+        let mut cpu = running_cpu(9);
+        let mut rewind = RewindBuffer::new(RewindConfig::default());
+        for _ in 0..5 {
+            cpu.run_frame(25).unwrap();
+            rewind.record(&cpu);
+        }
+        let third = {
+            rewind.rewind(&mut cpu).unwrap();
+            rewind.rewind(&mut cpu).unwrap();
+            cpu.save_state()
+        };
+
+        cpu.run_frame(25).unwrap();
+        rewind.record(&cpu);
+        assert_eq!(rewind.len(), 3);
+        assert!(rewind.rewind(&mut cpu).unwrap());
+        assert_eq!(cpu.save_state(), third);
+    }
+
+    #[test]
+    fn test_frame_limit() {
+        let mut cpu = running_cpu(9);
+        let mut rewind = RewindBuffer::new(RewindConfig {
+            max_frames: 4,
+            ..RewindConfig::default()
+        });
+        for _ in 0..20 {
+            cpu.run_frame(25).unwrap();
+            rewind.record(&cpu);
+        }
+        assert_eq!(rewind.len(), 4);
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        cpu.set_rng(Box::new(SeededRandom::new(9)));
+        let full_size = cpu.save_state().len();
+        let mut rewind = RewindBuffer::new(RewindConfig {
+            max_frames: 1000,
+            max_bytes: full_size + 64,
+        });
+        for _ in 0..50 {
+            rewind.record(&cpu);
+        }
+        // Identical frames compress to a few bytes each
+        assert!(!rewind.is_empty());
+        assert!(rewind.memory_usage() <= full_size + 64);
+        assert!(rewind.len() < 50);
+    }
+}
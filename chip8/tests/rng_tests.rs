@@ -0,0 +1,68 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::rng::{RandomSource, SeededRandom, VipRandom};
+
+    /// Runs RND V0, 0xFF a number of times and returns the results.
+    fn random_bytes(cpu: &mut CPU, count: usize) -> Vec<u8> {
+        (0..count)
+            .map(|_| {
+                cpu.execute(0xC0FF).unwrap();
+                cpu.memory.read_reg(0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_seeded_is_reproducible() {
+        let mut first = CPU::new();
+        first.set_rng(Box::new(SeededRandom::new(1234)));
+        let mut second = CPU::new();
+        second.set_rng(Box::new(SeededRandom::new(1234)));
+        let mut other = CPU::new();
+        other.set_rng(Box::new(SeededRandom::new(4321)));
+
+        let bytes = random_bytes(&mut first, 32);
+        assert_eq!(bytes, random_bytes(&mut second, 32));
+        assert_ne!(bytes, random_bytes(&mut other, 32));
+    }
+
+    #[test]
+    fn test_mask() {
+        let mut cpu = CPU::new();
+        cpu.set_rng(Box::new(SeededRandom::new(7)));
+        for _ in 0..64 {
+            // RND V0, 0x0F
+            cpu.execute(0xC00F).unwrap();
+            assert!(cpu.memory.read_reg(0) <= 0x0F);
+        }
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        for mut rng in [
+            Box::new(SeededRandom::new(99)) as Box<dyn RandomSource>,
+            Box::new(VipRandom::new()),
+        ] {
+            rng.next_byte();
+            let state = rng.state();
+            let expected: Vec<u8> = (0..8).map(|_| rng.next_byte()).collect();
+
+            rng.set_state(state);
+            let replayed: Vec<u8> = (0..8).map(|_| rng.next_byte()).collect();
+            assert_eq!(expected, replayed);
+        }
+    }
+
+    #[test]
+    fn test_vip_depends_on_interrupts() {
+        let mut cpu = CPU::new();
+        cpu.set_rng(Box::new(VipRandom::new()));
+        let mut delayed = CPU::new();
+        delayed.set_rng(Box::new(VipRandom::new()));
+        delayed.tick_timers();
+
+        assert_ne!(random_bytes(&mut cpu, 4), random_bytes(&mut delayed, 4));
+        assert_eq!(cpu.rng().state() & 0xFF, 4);
+    }
+}
@@ -0,0 +1,161 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::frontend::Rotation;
+    use chip8::hash::{from_hex, sha1, to_hex};
+    use chip8::json::{parse, JsonValue, MAX_DEPTH};
+    use chip8::quirks::{IndexIncrement, Platform, Quirks};
+    use chip8::romdb::{local_settings_path, RomDatabase, RomDbError, RomSettings, DATABASE_FILE};
+
+    /// A program listed in `programs` by the SHA-1 of its contents.
+    const ROM: &[u8] = &[0x00, 0xE0, 0x12, 0x00];
+
+    /// An excerpt in the layout of the chip-8-database `programs.json`.
+    fn programs() -> String {
+        format!(
+            r##"[
+  {{
+    "title": "Test \"Game\"",
+    "roms": {{
+      "{}": {{
+        "file": "test.ch8",
+        "platforms": ["megachip8", "superchip"],
+        "quirkyPlatforms": {{ "superchip": {{ "shift": false, "memoryIncrementByX": true }} }},
+        "tickrate": 30,
+        "startAddress": 512,
+        "colors": {{ "pixels": ["#101010", "#e0e0e0"] }},
+        "keys": {{ "up": 5, "a": 6 }},
+        "screenRotation": 90
+      }}
+    }}
+  }},
+  {{
+    "title": "Modern",
+    "roms": {{ "0123456789abcdef0123456789abcdef01234567": {{ "platforms": ["modernChip8"] }} }}
+  }},
+  {{ "title": "Unsupported", "roms": {{ "ffffffffffffffffffffffffffffffffffffffff": {{ "platforms": ["megachip8"] }} }} }}
+]"##,
+            to_hex(&sha1(ROM))
+        )
+    }
+
+    #[test]
+    fn test_json() {
+        let value = parse(r#" {"a": [1, -2.5e1, true, null], "b": "é😀\n"} "#).unwrap();
+        let a = value.get("a").and_then(JsonValue::as_array).unwrap();
+        assert_eq!(a, &[JsonValue::Number(1.0), JsonValue::Number(-25.0), JsonValue::Bool(true), JsonValue::Null]);
+        assert_eq!(value.get("b").and_then(JsonValue::as_str), Some("\u{e9}\u{1F600}\n"));
+
+        assert_eq!(parse("[1, 2").unwrap_err().offset, 5);
+        assert!(parse("{\"a\": 1} x").is_err());
+        assert!(parse("\"unterminated").is_err());
+        assert!(parse(r#""\u+123""#).is_err());
+
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)).unwrap_err().offset, MAX_DEPTH);
+        assert!(parse(&nested(100_000)).is_err());
+    }
+
+    #[test]
+    fn test_import_chip8_database() {
+        let mut database = RomDatabase::new();
+        assert_eq!(database.import_chip8_database(&programs()).unwrap(), 3);
+
+        let settings = database.lookup_rom(ROM).unwrap();
+        assert_eq!(settings.title.as_deref(), Some("Test \"Game\""));
+        assert_eq!(settings.platform, Some(Platform::SuperChip));
+        assert_eq!(settings.cycles_per_frame, Some(30));
+        assert_eq!(settings.load_address, Some(0x200));
+        assert_eq!(settings.colors, vec![(0x10, 0x10, 0x10), (0xE0, 0xE0, 0xE0)]);
+        assert_eq!(settings.key("up"), Some(5));
+        assert_eq!(settings.key("b"), None);
+        assert_eq!(settings.rotation, Some(Rotation::Quarter));
+
+        let quirks = settings.quirks();
+        assert!(quirks.shift_uses_vy);
+        assert_eq!(quirks.index_increment, IndexIncrement::ByX);
+        assert_eq!(quirks.clip_sprites, Quirks::superchip().clip_sprites);
+
+        let hash = from_hex("0123456789abcdef0123456789abcdef01234567").unwrap();
+        let modern = database.lookup(&hash.try_into().unwrap()).unwrap();
+        assert_eq!(modern.platform, Some(Platform::CosmacVip));
+        let quirks = modern.quirks();
+        assert!(quirks.shift_uses_vy);
+        assert!(quirks.clip_sprites);
+        assert_eq!(quirks.index_increment, IndexIncrement::ByXPlusOne);
+        assert!(!quirks.jump_uses_vx);
+        assert!(!quirks.logic_resets_vf);
+        assert!(!quirks.display_wait);
+        assert_eq!(database.lookup(&[0xFF; 20]).unwrap().platform, None);
+
+        assert!(matches!(database.import_chip8_database("{}"), Err(RomDbError::Format(_))));
+        assert!(matches!(database.import_chip8_database("[{"), Err(RomDbError::Json(_))));
+    }
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let text = "# Local tweaks
+title = Space Invaders
+platform = schip
+cycles-per-frame = 12
+load-address = 0x200
+colors = 000000 #ffcc00
+key.left = 4
+rotation = 180
+quirk.clip_sprites = false
+quirk.index_increment = x+1
+";
+        let settings = RomSettings::parse(text).unwrap();
+        assert_eq!(settings.platform, Some(Platform::SuperChip));
+        assert_eq!(settings.colors, vec![(0, 0, 0), (0xFF, 0xCC, 0)]);
+        assert_eq!(settings.key("left"), Some(4));
+        assert_eq!(settings.rotation, Some(Rotation::Half));
+        assert!(!settings.quirks().clip_sprites);
+        assert_eq!(settings.quirks().index_increment, IndexIncrement::ByXPlusOne);
+        assert_eq!(RomSettings::parse(&settings.to_string()).unwrap(), settings);
+
+        match RomSettings::parse("title = x\nquirk.warp = true") {
+            Err(RomDbError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(RomSettings::parse("key.a = 10").is_err());
+        assert!(RomSettings::parse("platform").is_err());
+        assert!(RomSettings::parse("rotation = 45").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut settings = RomSettings::parse("title = Imported\ncycles-per-frame = 30\nkey.up = 5\nkey.a = 6").unwrap();
+        settings.merge(&RomSettings::parse("cycles-per-frame = 10\nkey.up = 2\nquirk.jump_uses_vx = true").unwrap());
+        assert_eq!(settings.title.as_deref(), Some("Imported"));
+        assert_eq!(settings.cycles_per_frame, Some(10));
+        assert_eq!(settings.key("up"), Some(2));
+        assert_eq!(settings.key("a"), Some(6));
+        assert_eq!(settings.quirks.jump_uses_vx, Some(true));
+    }
+
+    #[test]
+    fn test_config_dir() {
+        let dir = std::env::temp_dir().join(format!("chip8-romdb-{}", std::process::id()));
+        let hash = sha1(ROM);
+        let local = local_settings_path(&dir, &hash);
+        std::fs::create_dir_all(local.parent().unwrap()).unwrap();
+        std::fs::write(dir.join(DATABASE_FILE), programs()).unwrap();
+        std::fs::write(&local, "title = Local\nplatform = xochip\n").unwrap();
+        std::fs::write(local.with_file_name("notes.txt"), "not settings").unwrap();
+
+        let mut database = RomDatabase::new();
+        let result = database.load_config_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+
+        /* The local file overrides the imported database, field by field */
+        let settings = database.lookup_rom(ROM).unwrap();
+        assert_eq!(settings.title.as_deref(), Some("Local"));
+        assert_eq!(settings.platform, Some(Platform::XoChip));
+        assert_eq!(settings.cycles_per_frame, Some(30));
+        assert_eq!(database.len(), 3);
+
+        assert!(RomDatabase::new().load_config_dir(&dir).is_ok());
+    }
+}
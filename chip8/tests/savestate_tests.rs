@@ -0,0 +1,91 @@
+mod common;
+
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::quirks::Platform;
+    use chip8::savestate::{SaveStateError, SAVE_STATE_VERSION};
+
+    use crate::common::running_cpu;
+
+    #[test]
+    fn test_round_trip() {
+        let mut cpu = running_cpu(5);
+        cpu.run_frame(20).unwrap();
+        cpu.key_pressed(0x5);
+        let state = cpu.save_state();
+
+        for _ in 0..5 {
+            cpu.run_frame(20).unwrap();
+        }
+        let expected = cpu.save_state();
+
+        let mut restored = running_cpu(5);
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.save_state(), state);
+        for _ in 0..5 {
+            restored.run_frame(20).unwrap();
+        }
+        assert_eq!(restored.save_state(), expected);
+        assert_eq!(restored.get_gpu().video_buffer, cpu.get_gpu().video_buffer);
+    }
+
+    #[test]
+    fn test_bad_magic() {
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.load_state(b"PNG!"), Err(SaveStateError::BadMagic));
+        assert_eq!(cpu.load_state(b""), Err(SaveStateError::BadMagic));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut state = CPU::new().save_state();
+        state[4..6].copy_from_slice(&(SAVE_STATE_VERSION + 1).to_le_bytes());
+
+        let error = CPU::new().load_state(&state).unwrap_err();
+        assert_eq!(
+            error,
+            SaveStateError::UnsupportedVersion {
+                found: SAVE_STATE_VERSION + 1,
+                supported: SAVE_STATE_VERSION
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "save state version {} is not supported, this build reads version {}",
+                SAVE_STATE_VERSION + 1,
+                SAVE_STATE_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn test_corruption_leaves_cpu_untouched() {
+        let mut source = running_cpu(5);
+        source.run_frame(50).unwrap();
+        let mut state = source.save_state();
+        state[100] ^= 0xFF;
+
+        let mut cpu = running_cpu(5);
+        let before = cpu.save_state();
+        assert!(matches!(cpu.load_state(&state), Err(SaveStateError::ChecksumMismatch { .. })));
+        assert_eq!(cpu.save_state(), before);
+
+        let state = source.save_state();
+        assert_eq!(cpu.load_state(&state[..state.len() - 1]), Err(SaveStateError::Truncated));
+    }
+
+    #[test]
+    fn test_memory_size_mismatch() {
+        let state = CPU::with_platform(Platform::XoChip).save_state();
+
+        assert_eq!(
+            CPU::new().load_state(&state),
+            Err(SaveStateError::MemorySizeMismatch {
+                expected: 0x1000,
+                found: 0x10000
+            })
+        );
+    }
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::gpu;
+    use chip8::memory::BIG_FONT_ADDR;
+    use chip8::quirks::Quirks;
+
+    #[test]
+    fn test_resolution_switch() {
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.get_gpu().width(), gpu::VRAM_WIDTH);
+
+        // HIGH
+        cpu.execute(0x00FF).unwrap();
+        assert!(cpu.get_gpu().is_hires());
+        assert_eq!(cpu.get_gpu().width(), gpu::HIRES_WIDTH);
+        assert_eq!(cpu.get_gpu().height(), gpu::HIRES_HEIGHT);
+
+        // LOW
+        cpu.execute(0x00FE).unwrap();
+        assert!(!cpu.get_gpu().is_hires());
+        assert_eq!(cpu.get_gpu().height(), gpu::VRAM_HEIGHT);
+    }
+
+    #[test]
+    fn test_hires_draw_wraps_at_128() {
+        let mut cpu = CPU::new();
+        cpu.execute(0x00FF).unwrap();
+
+        cpu.memory.write_reg(0, 126); // x = 126
+        cpu.memory.write_reg(1, 70); // y = 70 wraps to 6
+        cpu.memory.i = 0x0; // Font sprite for 0
+
+        // DRW V0, V1, 1
+        cpu.execute(0xD011).unwrap();
+
+        let gpu = cpu.get_gpu();
+        assert_eq!(gpu.video_buffer[6][126], 1);
+        assert_eq!(gpu.video_buffer[6][127], 1);
+        assert_eq!(gpu.video_buffer[6][0], 1);
+        assert_eq!(gpu.video_buffer[6][1], 1);
+    }
+
+    #[test]
+    fn test_draw_large_sprite() {
+        let mut cpu = CPU::new();
+        cpu.execute(0x00FF).unwrap();
+
+        for offset in 0..32 {
+            cpu.memory.memory[0x300 + offset] = 0xFF;
+        }
+        cpu.memory.i = 0x300;
+
+        // DRW V0, V0, 0
+        cpu.execute(0xD000).unwrap();
+        assert_eq!(cpu.memory.read_reg(0xF), 0);
+
+        let gpu = cpu.get_gpu();
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(gpu.video_buffer[y][x], 1);
+            }
+            assert_eq!(gpu.video_buffer[y][16], 0);
+        }
+        assert_eq!(gpu.video_buffer[16][0], 0);
+
+        // Drawing it again erases it and reports a collision
+        cpu.execute(0xD000).unwrap();
+        assert_eq!(cpu.memory.read_reg(0xF), 1);
+        assert_eq!(cpu.get_gpu().video_buffer[0][0], 0);
+    }
+
+    #[test]
+    fn test_draw_tall_sprite_in_lores() {
+        let mut cpu = CPU::with_quirks(Quirks::superchip());
+        for offset in 0..32 {
+            cpu.memory.memory[0x300 + offset] = 0xFF;
+        }
+        cpu.memory.i = 0x300;
+
+        // DRW V0, V0, 0 reads 16 bytes and draws them as 8x16
+        cpu.execute(0xD000).unwrap();
+        let gpu = cpu.get_gpu();
+        for y in 0..16 {
+            for x in 0..8 {
+                assert_eq!(gpu.video_buffer[y][x], 1);
+            }
+            assert_eq!(gpu.video_buffer[y][8], 0);
+        }
+        assert_eq!(gpu.video_buffer[16][0], 0);
+
+        // Without the quirk it is 16x16 like in hires
+        let mut cpu = CPU::with_quirks(Quirks::xochip());
+        for offset in 0..32 {
+            cpu.memory.memory[0x300 + offset] = 0xFF;
+        }
+        cpu.memory.i = 0x300;
+        cpu.execute(0xD000).unwrap();
+        let gpu = cpu.get_gpu();
+        assert_eq!(gpu.video_buffer[15][15], 1);
+        assert_eq!(gpu.video_buffer[15][16], 0);
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let mut cpu = CPU::new();
+        cpu.get_gpu().video_buffer[0][8] = 1;
+
+        // SCD 3
+        cpu.execute(0x00C3).unwrap();
+        assert_eq!(cpu.get_gpu().video_buffer[0][8], 0);
+        assert_eq!(cpu.get_gpu().video_buffer[3][8], 1);
+
+        // SCR
+        cpu.execute(0x00FB).unwrap();
+        assert_eq!(cpu.get_gpu().video_buffer[3][12], 1);
+
+        // SCL twice
+        cpu.execute(0x00FC).unwrap();
+        cpu.execute(0x00FC).unwrap();
+        assert_eq!(cpu.get_gpu().video_buffer[3][4], 1);
+        assert_eq!(cpu.get_gpu().video_buffer[3][12], 0);
+    }
+
+    #[test]
+    fn test_exit() {
+        let mut cpu = CPU::new();
+
+        // EXIT
+        cpu.execute(0x00FD).unwrap();
+        assert!(cpu.halt);
+        assert_eq!(cpu.memory.pc, 0x200);
+    }
+
+    #[test]
+    fn test_big_font() {
+        let mut cpu = CPU::new();
+        cpu.memory.write_reg(3, 0x7);
+
+        // LD HF, V3
+        cpu.execute(0xF330).unwrap();
+        assert_eq!(cpu.memory.i, BIG_FONT_ADDR + 70);
+        assert_eq!(cpu.memory.memory[cpu.memory.i as usize], 0xFF);
+    }
+
+    #[test]
+    fn test_rpl_flags() {
+        let mut cpu = CPU::new();
+        for reg in 0..8 {
+            cpu.memory.write_reg(reg, reg * 3);
+        }
+
+        // LD R, V7
+        cpu.execute(0xF775).unwrap();
+        cpu.reset();
+        assert_eq!(cpu.memory.read_reg(5), 0);
+
+        // LD V7, R
+        cpu.execute(0xF785).unwrap();
+        for reg in 0..8 {
+            assert_eq!(cpu.memory.read_reg(reg), reg * 3);
+        }
+    }
+}
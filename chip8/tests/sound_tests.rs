@@ -0,0 +1,92 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::quirks::Platform;
+    use chip8::sound::{pattern_rate, SoundConfig, SoundState, ToneGenerator, Waveform};
+
+    fn config(waveform: Waveform) -> SoundConfig {
+        SoundConfig {
+            frequency: 1000.0,
+            waveform,
+            volume: 0.5,
+            sample_rate: 8000,
+        }
+    }
+
+    fn active() -> SoundState {
+        SoundState {
+            active: true,
+            ..SoundState::default()
+        }
+    }
+
+    #[test]
+    fn test_silent_when_inactive() {
+        let mut generator = ToneGenerator::new(SoundConfig::default());
+        let mut buffer = [1.0; 32];
+        generator.fill(&mut buffer, &SoundState::default());
+        assert!(buffer.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_square_wave() {
+        // 1000 Hz at 8000 samples per second is 8 samples per period
+        let mut generator = ToneGenerator::new(config(Waveform::Square));
+        let mut buffer = [0.0; 16];
+        generator.fill(&mut buffer, &active());
+        assert_eq!(buffer[..8], [0.5, 0.5, 0.5, 0.5, -0.5, -0.5, -0.5, -0.5]);
+        assert_eq!(buffer[..8], buffer[8..]);
+    }
+
+    #[test]
+    fn test_waveforms_stay_in_range() {
+        for waveform in [Waveform::Triangle, Waveform::Sawtooth, Waveform::Sine] {
+            let mut generator = ToneGenerator::new(config(waveform));
+            let mut buffer = [0.0; 64];
+            generator.fill(&mut buffer, &active());
+            assert!(buffer.iter().all(|sample| sample.abs() <= 0.5), "{:?}", waveform);
+            assert!(buffer.iter().any(|sample| *sample > 0.0), "{:?}", waveform);
+        }
+    }
+
+    #[test]
+    fn test_audio_pattern() {
+        assert_eq!(pattern_rate(64), 4000.0);
+        assert_eq!(pattern_rate(112), 8000.0);
+
+        // At 4000 bits per second and 8000 samples per second, every bit lasts two samples
+        let mut generator = ToneGenerator::new(config(Waveform::Square));
+        let mut pattern = [0; 16];
+        pattern[0] = 0b1010_0000;
+        let state = SoundState {
+            active: true,
+            pattern: Some(pattern),
+            pitch: 64,
+        };
+        let mut buffer = [0.0; 8];
+        generator.fill(&mut buffer, &state);
+        assert_eq!(buffer, [0.5, 0.5, -0.5, -0.5, 0.5, 0.5, -0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_cpu_sound_state() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        assert!(!cpu.buzzer_active());
+
+        // LD V0, 2 then LD ST, V0
+        cpu.execute(0x6002).unwrap();
+        cpu.execute(0xF018).unwrap();
+        assert!(cpu.buzzer_active());
+        assert_eq!(cpu.sound_state().pattern, None);
+
+        // AUDIO loads the pattern at I
+        cpu.memory.i = 0x800;
+        cpu.memory.memory[0x800] = 0xFF;
+        cpu.execute(0xF002).unwrap();
+        assert_eq!(cpu.sound_state().pattern.unwrap()[0], 0xFF);
+
+        cpu.tick_timers();
+        cpu.tick_timers();
+        assert!(!cpu.buzzer_active());
+    }
+}
@@ -0,0 +1,53 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::{CPU, TIMER_HZ};
+
+    /// A program that loads 60 into DT and spins forever.
+    fn delay_program() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.load_program(&[
+            0x60, 0x3C, /* LD V0, 60 */
+            0xF0, 0x15, /* LD DT, V0 */
+            0x12, 0x04, /* JP 0x204 */
+        ])
+        .unwrap();
+        cpu
+    }
+
+    #[test]
+    fn test_cycle_does_not_tick() {
+        let mut cpu = delay_program();
+        for _ in 0..100 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.memory.dt, 60);
+
+        cpu.tick_timers();
+        assert_eq!(cpu.memory.dt, 59);
+    }
+
+    #[test]
+    fn test_run_frame() {
+        let mut cpu = delay_program();
+
+        cpu.run_frame(10).unwrap();
+        assert_eq!(cpu.memory.pc, 0x204);
+        assert_eq!(cpu.memory.dt, 59);
+
+        // One second of frames empties the timer, whatever the clock speed
+        for _ in 1..TIMER_HZ {
+            cpu.run_frame(1000).unwrap();
+        }
+        assert_eq!(cpu.memory.dt, 0);
+    }
+
+    #[test]
+    fn test_halted_frame() {
+        let mut cpu = delay_program();
+        cpu.run_frame(2).unwrap();
+        cpu.halt = true;
+
+        cpu.run_frame(2).unwrap();
+        assert_eq!(cpu.memory.dt, 59);
+    }
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::trace::{Categories, Category, FileSink, Level, RingBufferSink, Tracer};
+
+    fn traced_cpu(level: Level, categories: Categories) -> (CPU, RingBufferSink) {
+        let ring = RingBufferSink::new(100);
+        let mut cpu = CPU::new();
+        cpu.set_tracer(Tracer::new(Box::new(ring.clone()), level, categories));
+        (cpu, ring)
+    }
+
+    #[test]
+    fn test_instruction_lines() {
+        let (mut cpu, ring) = traced_cpu(Level::Trace, Category::Execute.into());
+        /* LD V0, 0x2A; LD I, 0x300; ADD V0, V0; CALL 0x20A; ...; RET */
+        cpu.load_program(&[0x60, 0x2A, 0xA3, 0x00, 0x80, 0x04, 0x22, 0x0A, 0x00, 0x00, 0x00, 0xEE])
+            .unwrap();
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(
+            ring.lines(),
+            vec![
+                "[execute] 0200 602A LD V0, 0x2A        V0=2A",
+                "[execute] 0202 A300 LD I, 0x300        I=0300",
+                "[execute] 0204 8004 ADD V0, V0         V0=54",
+                "[execute] 0206 220A CALL 0x20A         SP=01",
+                "[execute] 020A 00EE RET                SP=00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filtering() {
+        let (mut cpu, ring) = traced_cpu(Level::Debug, Categories::ALL);
+        cpu.load_program(&[0x60, 0x01]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.key_pressed(5);
+        let records = ring.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].level, Level::Info);
+        assert_eq!(records[0].category, Category::Memory);
+        assert_eq!(records[1].to_string(), "[input] key 5 pressed, key_state: 0020");
+
+        let (mut cpu, ring) = traced_cpu(Level::Trace, Category::Memory | Category::Decode);
+        cpu.load_program(&[0xA3, 0x00, 0xF2, 0x55]).unwrap(); /* LD I, 0x300; LD [I], V2 */
+        ring.clear();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let lines = ring.lines();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("[decode] 0200 A300"));
+        assert_eq!(lines[2], "[memory] write 3 bytes at 0x0300");
+    }
+
+    #[test]
+    fn test_ring_buffer_capacity() {
+        let ring = RingBufferSink::new(3);
+        let mut cpu = CPU::new();
+        cpu.set_tracer(Tracer::new(Box::new(ring.clone()), Level::Trace, Category::Execute.into()));
+        cpu.load_program(&[0x70, 0x01, 0x12, 0x00]).unwrap(); /* ADD V0, 1; JP 0x200 */
+        for _ in 0..10 {
+            cpu.cycle().unwrap();
+        }
+        let lines = ring.lines();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with("V0=05"));
+        assert!(lines[2].ends_with("JP 0x200"));
+    }
+
+    #[test]
+    fn test_fault_and_file_sink() {
+        let path = std::env::temp_dir().join(format!("chip8-trace-{}.log", std::process::id()));
+        let mut cpu = CPU::new();
+        cpu.set_tracer(Tracer::new(
+            Box::new(FileSink::create(&path).unwrap()),
+            Level::Error,
+            Categories::ALL,
+        ));
+        cpu.load_program(&[0x00, 0xEE]).unwrap(); /* RET */
+        assert!(cpu.cycle().is_err());
+        cpu.tracer_mut().flush();
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(trace.lines().count(), 1);
+        assert!(trace.starts_with("[execute] "));
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!(Level::from_name("WARN"), Some(Level::Warn));
+        assert_eq!(Level::from_name("verbose"), None);
+        assert_eq!(
+            Categories::from_names("execute, input"),
+            Some(Category::Execute | Category::Input)
+        );
+        assert_eq!(Categories::from_names("execute,video"), None);
+        assert!(Level::Error < Level::Trace);
+    }
+}
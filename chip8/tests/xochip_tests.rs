@@ -0,0 +1,106 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::cpu::CPU;
+    use chip8::memory::{MEM_SIZE, XO_MEM_SIZE};
+    use chip8::quirks::Platform;
+
+    #[test]
+    fn test_memory_size() {
+        assert_eq!(CPU::new().memory.memory.len(), MEM_SIZE);
+        assert_eq!(CPU::with_platform(Platform::SuperChip).memory.memory.len(), MEM_SIZE);
+        assert_eq!(CPU::with_platform(Platform::XoChip).memory.memory.len(), XO_MEM_SIZE);
+    }
+
+    #[test]
+    fn test_long_load() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        cpu.load_program(&[0xF0, 0x00, 0xAB, 0xCD]).unwrap();
+
+        // LD I, LONG 0xABCD
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.memory.i, 0xABCD);
+        assert_eq!(cpu.memory.pc, 0x204);
+    }
+
+    #[test]
+    fn test_skip_over_long_load() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        cpu.load_program(&[
+            0x30, 0x00, /* SE V0, 0x00 */
+            0xF0, 0x00, 0x12, 0x34, /* LD I, LONG 0x1234 */
+        ])
+        .unwrap();
+
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.memory.pc, 0x206);
+    }
+
+    #[test]
+    fn test_register_range() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        for reg in 0..16 {
+            cpu.memory.write_reg(reg, reg + 0x10);
+        }
+        cpu.memory.i = 0x400;
+
+        // SAVE V2 - V4
+        cpu.execute(0x5242).unwrap();
+        assert_eq!(cpu.memory.memory[0x400..0x404], [0x12, 0x13, 0x14, 0x00]);
+        assert_eq!(cpu.memory.i, 0x400);
+
+        // LOAD V9 - V7 loads in descending order
+        cpu.execute(0x5973).unwrap();
+        assert_eq!(cpu.memory.read_reg(9), 0x12);
+        assert_eq!(cpu.memory.read_reg(8), 0x13);
+        assert_eq!(cpu.memory.read_reg(7), 0x14);
+    }
+
+    #[test]
+    fn test_plane_drawing() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        cpu.memory.memory[0x400..0x402].copy_from_slice(&[0x80, 0xC0]);
+        cpu.memory.i = 0x400;
+
+        // PLANE 3 draws both planes, the first byte on plane 1 and the second on plane 2
+        cpu.execute(0xF301).unwrap();
+        cpu.execute(0xD001).unwrap();
+        assert_eq!(cpu.get_gpu().video_buffer[0][0], 0x3);
+        assert_eq!(cpu.get_gpu().video_buffer[0][1], 0x2);
+
+        // PLANE 2 then CLS only clears the second plane
+        cpu.execute(0xF201).unwrap();
+        cpu.execute(0x00E0).unwrap();
+        assert_eq!(cpu.get_gpu().video_buffer[0][0], 0x1);
+        assert_eq!(cpu.get_gpu().video_buffer[0][1], 0x0);
+    }
+
+    #[test]
+    fn test_scroll_up_selected_plane() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        cpu.get_gpu().video_buffer[4][0] = 0x3;
+
+        // PLANE 2, SCU 4
+        cpu.execute(0xF201).unwrap();
+        cpu.execute(0x00D4).unwrap();
+        assert_eq!(cpu.get_gpu().video_buffer[4][0], 0x1);
+        assert_eq!(cpu.get_gpu().video_buffer[0][0], 0x2);
+    }
+
+    #[test]
+    fn test_audio() {
+        let mut cpu = CPU::with_platform(Platform::XoChip);
+        for offset in 0..16 {
+            cpu.memory.memory[0x800 + offset] = offset as u8;
+        }
+        cpu.memory.i = 0x800;
+        cpu.memory.write_reg(5, 0x70);
+
+        // AUDIO
+        cpu.execute(0xF002).unwrap();
+        // PITCH V5
+        cpu.execute(0xF53A).unwrap();
+
+        assert_eq!(cpu.memory.audio_pattern[15], 15);
+        assert_eq!(cpu.memory.pitch, 0x70);
+    }
+}
@@ -0,0 +1,83 @@
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+
+use chip8::frontend::Audio;
+use chip8::sound::{SoundConfig, SoundState, ToneGenerator};
+
+/// The number of samples per audio buffer, small enough to keep the latency under a frame.
+const BUFFER_SAMPLES: u16 = 512;
+
+/// The SDL audio callback playing the CHIP-8 buzzer.
+pub struct Buzzer {
+    /// Generates the samples.
+    generator: ToneGenerator,
+    /// What the buzzer should be playing, updated by the main loop once per frame.
+    pub state: SoundState,
+}
+
+impl AudioCallback for Buzzer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.generator.fill(out, &self.state);
+    }
+}
+
+/// Opens the default playback device and starts playing the buzzer.
+///
+/// # Arguments
+///
+/// * `context` - The SDL context.
+/// * `config` - The tone settings. The sample rate is replaced by the one the device uses.
+///
+/// # Returns
+///
+/// * `Result<AudioDevice<Buzzer>, String>` - The playing device, or the SDL error.
+pub fn open(context: &sdl2::Sdl, config: SoundConfig) -> Result<AudioDevice<Buzzer>, String> {
+    let audio_subsystem = context.audio()?;
+    let desired = AudioSpecDesired {
+        freq: Some(config.sample_rate as i32),
+        channels: Some(1),
+        samples: Some(BUFFER_SAMPLES),
+    };
+    let device = audio_subsystem.open_playback(None, &desired, |spec| Buzzer {
+        generator: ToneGenerator::new(SoundConfig {
+            sample_rate: spec.freq as u32,
+            ..config
+        }),
+        state: SoundState::default(),
+    })?;
+    device.resume();
+    Ok(device)
+}
+
+/// The SDL `Audio` backend, silent if no device could be opened.
+pub struct SdlAudio {
+    device: Option<AudioDevice<Buzzer>>,
+}
+
+impl SdlAudio {
+    /// Opens the default playback device, printing why if there is none.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The SDL context.
+    /// * `config` - The tone settings.
+    pub fn new(context: &sdl2::Sdl, config: SoundConfig) -> Self {
+        let device = match open(context, config) {
+            Ok(device) => Some(device),
+            Err(e) => {
+                println!("Could not open an audio device, sound is disabled: {}", e);
+                None
+            }
+        };
+        SdlAudio { device }
+    }
+}
+
+impl Audio for SdlAudio {
+    fn update(&mut self, state: SoundState) {
+        if let Some(device) = &mut self.device {
+            device.lock().state = state;
+        }
+    }
+}
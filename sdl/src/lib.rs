@@ -1,33 +1,286 @@
+pub mod audio;
 pub mod binaryreader;
+pub mod render;
 
 use sdl2::{
     event::Event,
     keyboard::{Keycode, Scancode},
     pixels::Color,
+    video::FullscreenType,
+    EventPump,
 };
-use std::time::Duration;
+use std::path::Path;
 
+use chip8::frontend::{Command, Display, Input, Rotation, ScaleMode, Session, SessionConfig, Viewport};
+use chip8::rewind::RewindConfig;
+use chip8::sound::SoundConfig;
 use chip8::{cpu, gpu};
 
+use crate::audio::SdlAudio;
+use crate::render::{destination, FrameTexture};
+
 /// Defines the refresh rate of the screen in Hz.
 pub const REFRESH_RATE: u32 = 60;
-/// Defines the window width in pixels.
+/// Defines the default window width in pixels.
 pub const WINDOW_WIDTH: usize = 640;
-/// Defines the window height in pixels.
+/// Defines the default window height in pixels.
 pub const WINDOW_HEIGHT: usize = 320;
-/// Defines the time per frame in nanoseconds.
-const TIME_PER_FRAME: Duration = Duration::new(0, 1_000_000_000u32 / REFRESH_RATE);
-/// The CPU clock speed in Hz.
-const CPU_CLOCK_HZ: u32 = 1500;
-/// The number of CPU cycles per frame.
-const CPU_CYCLES_PER_FRAME: u32 = CPU_CLOCK_HZ / REFRESH_RATE;
+/// The default number of window pixels per low-resolution CHIP-8 pixel.
+pub const DEFAULT_SCALE: u32 = (WINDOW_WIDTH / 64) as u32;
+/// The default colours of the pixels, indexed by their XO-CHIP bitplanes.
+pub const PALETTE: [Color; 4] = [
+    Color::RGB(0, 0, 0),
+    Color::RGB(255, 255, 255),
+    Color::RGB(170, 170, 170),
+    Color::RGB(85, 85, 85),
+];
+/// The keys of the 16-key keypad, laid out as:
+///
+/// 1 2 3 4
+/// Q W E R
+/// A S D F
+/// Z X C V
+const KEY_MAPPINGS: [(Scancode, u8); 16] = [
+    (Scancode::Num1, 0x1),
+    (Scancode::Num2, 0x2),
+    (Scancode::Num3, 0x3),
+    (Scancode::Num4, 0xC),
+    (Scancode::Q, 0x4),
+    (Scancode::W, 0x5),
+    (Scancode::E, 0x6),
+    (Scancode::R, 0xD),
+    (Scancode::A, 0x7),
+    (Scancode::S, 0x8),
+    (Scancode::D, 0x9),
+    (Scancode::F, 0xE),
+    (Scancode::Z, 0xA),
+    (Scancode::X, 0x0),
+    (Scancode::C, 0xB),
+    (Scancode::V, 0xF),
+];
+/// The keys bound to the actions of a game, such as `up` or `a`, when its settings map them
+/// to keypad keys.
+const ACTION_MAPPINGS: [(Scancode, &str); 6] = [
+    (Scancode::Up, "up"),
+    (Scancode::Down, "down"),
+    (Scancode::Left, "left"),
+    (Scancode::Right, "right"),
+    (Scancode::LCtrl, "a"),
+    (Scancode::LAlt, "b"),
+];
 
-/// The SDL context.
-pub struct SDL {
-    /// The SDL context.
-    context: sdl2::Sdl,
+/// The settings of the SDL frontend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SdlOptions {
+    /// The title of the window.
+    pub title: String,
+    /// The number of window pixels per low-resolution CHIP-8 pixel in the initial window, at
+    /// least 2 so that high-resolution pixels are visible.
+    pub scale: u32,
+    /// How the screen is scaled when the window is resized.
+    pub scale_mode: ScaleMode,
+    /// How the screen is turned.
+    pub rotation: Rotation,
+    /// Whether to start fullscreen.
+    pub fullscreen: bool,
+    /// Whether to start paused, SPACE resumes.
+    pub paused: bool,
+    /// The colours of the pixels, indexed by their XO-CHIP bitplanes.
+    pub palette: [Color; 4],
+    /// The clock speed, frame pacing and rewind history.
+    pub session: SessionConfig,
+    /// The keypad keys of the game's actions, bound to the arrows, left Ctrl (`a`) and left
+    /// Alt (`b`) in addition to the keypad layout.
+    pub actions: Vec<(String, u8)>,
+}
+
+impl Default for SdlOptions {
+    fn default() -> Self {
+        SdlOptions {
+            title: "Chip-8-VM-Rust".to_string(),
+            scale: DEFAULT_SCALE,
+            scale_mode: ScaleMode::default(),
+            rotation: Rotation::default(),
+            fullscreen: false,
+            paused: false,
+            palette: PALETTE,
+            session: SessionConfig::default(),
+            actions: Vec::new(),
+        }
+    }
+}
+
+/// The SDL `Display` backend, drawing to a window.
+pub struct SdlDisplay {
+    /// The latest frame, declared before the canvas so that it is destroyed first.
+    frame: FrameTexture,
     /// The canvas to render to.
     canvas: sdl2::render::Canvas<sdl2::video::Window>,
+    /// The colours of the pixels, indexed by their XO-CHIP bitplanes.
+    palette: [Color; 4],
+    /// How the screen is scaled to the window.
+    scale_mode: ScaleMode,
+    /// How the screen is turned.
+    rotation: Rotation,
+}
+
+impl Display for SdlDisplay {
+    fn present(&mut self, gpu: &gpu::GPU) {
+        self.render_gpu_buffer(gpu);
+        self.canvas.present();
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        if let Err(e) = window.set_fullscreen(fullscreen) {
+            self.message(&format!("Cannot switch fullscreen: {}", e));
+        }
+    }
+}
+
+impl SdlDisplay {
+    /// Renders the GPU buffer to the screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `gpu` - The GPU instance from the `chip8` crate.
+    ///
+    /// # Panics
+    /// This function will panic if the texture cannot be updated or copied.
+    ///
+    /// # Behavior
+    ///
+    /// The buffer is converted to RGBA and uploaded into a streaming texture, 64x32 pixels in
+    /// low resolution and 128x64 in high resolution. The texture is then rotated and scaled
+    /// into the `Viewport` of the current window size with a single copy, and the borders
+    /// around it are cleared to black.
+    fn render_gpu_buffer(&mut self, gpu: &gpu::GPU) {
+        self.frame.update(gpu, &self.palette);
+        let window = self.canvas.output_size().unwrap();
+        let viewport = Viewport::new(window, self.scale_mode, self.rotation);
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.canvas
+            .copy_ex(
+                self.frame.texture(),
+                self.frame.source(),
+                destination(&viewport, self.rotation),
+                self.rotation.degrees() as f64,
+                None,
+                false,
+                false,
+            )
+            .unwrap();
+    }
+}
+
+/// The SDL `Input` backend, reading the keyboard and window events.
+pub struct SdlInput {
+    event_pump: EventPump,
+    /// The keyboard keys bound to keypad keys.
+    mappings: Vec<(Scancode, u8)>,
+}
+
+impl Input for SdlInput {
+    fn poll(&mut self) -> Vec<Command> {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        events.into_iter().filter_map(|event| self.command(event)).collect()
+    }
+}
+
+impl SdlInput {
+    /// Creates the input backend, binding the game's actions on top of `KEY_MAPPINGS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `event_pump` - The SDL event pump.
+    /// * `actions` - The keypad keys of the game's actions, see `ACTION_MAPPINGS`.
+    fn new(event_pump: EventPump, actions: &[(String, u8)]) -> Self {
+        let mut mappings = KEY_MAPPINGS.to_vec();
+        for (action, key) in actions {
+            if let Some(&(scancode, _)) = ACTION_MAPPINGS.iter().find(|(_, name)| name == action) {
+                mappings.push((scancode, *key));
+            }
+        }
+        SdlInput { event_pump, mappings }
+    }
+
+    /// Returns the keypad key bound to a keyboard key.
+    fn key(&self, scancode: Scancode) -> Option<u8> {
+        self.mappings.iter().find(|&&(code, _)| code == scancode).map(|&(_, key)| key)
+    }
+
+    /// Translates an SDL event into a command.
+    ///
+    /// The keypad keys are mapped as laid out in `KEY_MAPPINGS`, plus the game's actions. ESC
+    /// or closing the window quits, SPACE pauses, M steps, P prints the registers, Return
+    /// resets, N mutes, Backspace rewinds while held, F5/F9 save and load, F6/F7 change the
+    /// slot, F2/F3 record and play a movie and F11 switches to fullscreen. Only the keypad
+    /// and rewind follow the key repeat of a held key, the other actions happen once per press.
+    fn command(&self, event: Event) -> Option<Command> {
+        match event {
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => Some(Command::Quit),
+            Event::KeyDown {
+                scancode: Some(scancode),
+                repeat,
+                ..
+            } => {
+                if let Some(key) = self.key(scancode) {
+                    return Some(Command::Key { key, pressed: true });
+                }
+                // Handle scancodes that are not mapped to a key
+                match scancode {
+                    Scancode::Backspace => Some(Command::Rewind(true)),
+                    _ if repeat => None,
+                    Scancode::Space => Some(Command::TogglePause),
+                    Scancode::M => Some(Command::Step),
+                    Scancode::P => Some(Command::PrintRegisters),
+                    Scancode::Return => Some(Command::Reset),
+                    Scancode::N => Some(Command::ToggleMute),
+                    Scancode::F5 => Some(Command::SaveState),
+                    Scancode::F9 => Some(Command::LoadState),
+                    Scancode::F2 => Some(Command::ToggleRecording),
+                    Scancode::F3 => Some(Command::TogglePlayback),
+                    Scancode::F6 => Some(Command::PreviousSlot),
+                    Scancode::F7 => Some(Command::NextSlot),
+                    Scancode::F11 => Some(Command::ToggleFullscreen),
+                    _ => None,
+                }
+            }
+            Event::KeyUp {
+                scancode: Some(scancode),
+                ..
+            } => {
+                if let Some(key) = self.key(scancode) {
+                    Some(Command::Key { key, pressed: false })
+                } else if scancode == Scancode::Backspace {
+                    Some(Command::Rewind(false))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The SDL context.
+pub struct SDL {
+    /// The SDL context, kept alive while the backends are in use.
+    _context: sdl2::Sdl,
+    display: SdlDisplay,
+    input: SdlInput,
+    audio: SdlAudio,
+    /// The run loop, save states, movies and rewind.
+    session: Session,
 }
 
 impl Drop for SDL {
@@ -37,6 +290,12 @@ impl Drop for SDL {
     }
 }
 
+impl Default for SDL {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SDL {
     ///
     /// # Examples
@@ -47,185 +306,100 @@ impl SDL {
     /// let sdl = SDL::new();
     /// ```
     pub fn new() -> Self {
+        SDL::with_options(SdlOptions::default())
+    }
+
+    /// Opens the window and audio device with the given settings.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use sdl::{SdlOptions, SDL};
+    ///
+    /// let sdl = SDL::with_options(SdlOptions { scale: 4, ..SdlOptions::default() });
+    /// ```
+    pub fn with_options(options: SdlOptions) -> Self {
         let context = sdl2::init().unwrap();
         let video_subsystem = context.video().unwrap();
 
-        let window = video_subsystem
-            .window("Chip-8-VM-Rust", WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32)
+        let lores_width = 64 * options.scale.max(2);
+        let (width, height) = if options.rotation.is_sideways() {
+            (lores_width / 2, lores_width)
+        } else {
+            (lores_width, lores_width / 2)
+        };
+        let mut window = video_subsystem
+            .window(&options.title, width, height)
             .position_centered()
+            .resizable()
             .build()
             .unwrap();
+        if options.fullscreen {
+            if let Err(e) = window.set_fullscreen(FullscreenType::Desktop) {
+                eprintln!("Cannot switch fullscreen: {}", e);
+            }
+        }
 
         let canvas = window.into_canvas().build().unwrap();
+        let frame = FrameTexture::new(&canvas);
+        let event_pump = context.event_pump().unwrap();
+        let audio = SdlAudio::new(&context, SoundConfig::default());
+        let config = options.session;
         println!(
             "CPU CLOCK: {}, REFRESH RATE: {}, CYCLES PER FRAME: {}",
-            CPU_CLOCK_HZ, REFRESH_RATE, CPU_CYCLES_PER_FRAME
+            config.cycles_per_frame * cpu::TIMER_HZ,
+            REFRESH_RATE,
+            config.cycles_per_frame
         );
-        SDL { context, canvas }
+        let mut session = Session::new(config);
+        session.set_paused(options.paused);
+        SDL {
+            _context: context,
+            display: SdlDisplay {
+                frame,
+                canvas,
+                palette: options.palette,
+                scale_mode: options.scale_mode,
+                rotation: options.rotation,
+            },
+            input: SdlInput::new(event_pump, &options.actions),
+            audio,
+            session,
+        }
     }
 
-    /// Runs the main loop of the SDL application.
-    ///
-    /// This function drives the primary loop of the SDL application, handling events,
-    /// executing CPU cycles, and rendering the GPU buffer onto the screen.
+    /// Runs the main loop of the SDL application until ESC is pressed or the window is closed.
     ///
     /// # Arguments
     ///
     /// * `cpu` - A mutable reference to the `CPU` instance from the `chip8` crate.
     ///
-    /// # Behavior
-    ///
-    /// - The function begins by initializing an `event_pump` to capture and handle SDL events.
-    /// - Inside the main `'running` loop, the function listens for `Quit` events (like closing the window)
-    ///   or the `Escape` key being pressed. Either of these will break out of the main loop.
-    /// - For each frame, the function runs the CPU for a number of cycles defined by `CPU_CYCLES_PER_FRAME`.
-    ///   However, if the CPU's `halt` flag is set, it will break out of the cycle loop early.
-    /// - After the CPU cycles are executed, the function retrieves the GPU instance from the CPU and renders
-    ///   its buffer onto the screen.
-    /// - Finally, the function sleeps for a duration defined by `TIME_PER_FRAME` before starting the next iteration
-    ///   of the main loop.
-    ///
-    /// # Panics
-    ///
-    /// This function will panic if the event pump fails to initialize.
+    /// See `chip8::frontend::Session::run` for the scheduling.
     pub fn run(&mut self, cpu: &mut chip8::cpu::CPU) {
-        let mut event_pump = self.context.event_pump().unwrap();
-        'running: loop {
-            for event in event_pump.poll_iter() {
-                match event {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        ..
-                    } => break 'running,
-                    _ => {
-                        self.handle_keyboard_input(cpu, event);
-                    }
-                }
-            }
-
-            // Poll keyboard events and send them to the CPU.
-
-            // Run the CPU for one frame.
-            for _ in 0..CPU_CYCLES_PER_FRAME {
-                if !cpu.halt {
-                    cpu.cycle();
-                }
-            }
-
-            {
-                let gpu = cpu.get_gpu();
-                // Render the GPU buffer onto the screen.
-                self.canvas.set_draw_color(Color::RGB(0, 0, 0));
-                self.canvas.clear();
-                self.render_gpu_buffer(gpu);
-                self.canvas.present();
-            }
-            ::std::thread::sleep(TIME_PER_FRAME);
-        }
+        self.session.run(cpu, &mut self.display, &mut self.input, &mut self.audio);
     }
 
-    /// Renders the GPU buffer to the screen.
+    /// Sets the running ROM, enabling the quick-save and movie hotkeys.
     ///
     /// # Arguments
     ///
-    /// * `gpu` - The GPU instance from the `chip8` crate.
-    ///
-    /// # Panics
-    /// This function will panic if the canvas fails to initialize.
-    ///
-    /// # Behavior
-    ///
-    /// First, the function calculates the ratio of the screen width to the VRAM width.
-    /// This is stored as `upscale_ratio` and is later used to render
-    /// rects of size `upscale_ratio` x `upscale_ratio` for each pixel in the GPU buffer.
-    ///
-    /// Note: The function assumes the aspect ratio is the original 2:1, other
-    /// aspect ratios will probably fail or produce distorted rendering.
-    fn render_gpu_buffer(&mut self, gpu: &mut gpu::GPU) {
-        let upscale_ratio: usize = WINDOW_WIDTH / gpu::VRAM_WIDTH;
-
-        for y in 0..gpu::VRAM_HEIGHT {
-            for x in 0..gpu::VRAM_WIDTH {
-                if gpu.video_buffer[y][x] != 0 {
-                    // Pixel is white
-                    self.canvas.set_draw_color(Color::RGB(255, 255, 255));
-                } else {
-                    // Pixel is black
-                    self.canvas.set_draw_color(Color::RGB(0, 0, 0));
-                }
-                self.canvas
-                    .fill_rect(sdl2::rect::Rect::new(
-                        (x * upscale_ratio) as i32,
-                        (y * upscale_ratio) as i32,
-                        upscale_ratio as u32,
-                        upscale_ratio as u32,
-                    ))
-                    .unwrap();
-            }
-        }
+    /// * `path` - The path to the ROM. Save states are written next to it as `<rom>.state<slot>`
+    ///   and movies as `<rom>.movie`.
+    /// * `rom` - The program, reloaded when a movie starts.
+    pub fn set_rom(&mut self, path: &Path, rom: &[u8]) {
+        self.session.set_rom(path, rom);
     }
 
-    /// Handles keyboard input.
-    ///
-    /// The keyboard mapping layout is as follows:
-    ///
-    ///
-    /// 1 2 3 4
-    /// Q W E R
-    /// A S D F
-    /// Z X C V
-    ///
-    ///
-    /// When a key is pressed, the corresponding bit in the `cpu`'s `key_pressed` register is set.
-    /// When a key is released, the corresponding bit in the `cpu`'s `key_released` register is set.
-    fn handle_keyboard_input(&mut self, cpu: &mut cpu::CPU, event: Event) {
-        let key_mappings: [(Scancode, usize); 16] = [
-            (Scancode::Num1, 0x1),
-            (Scancode::Num2, 0x2),
-            (Scancode::Num3, 0x3),
-            (Scancode::Num4, 0xC),
-            (Scancode::Q, 0x4),
-            (Scancode::W, 0x5),
-            (Scancode::E, 0x6),
-            (Scancode::R, 0xD),
-            (Scancode::A, 0x7),
-            (Scancode::S, 0x8),
-            (Scancode::D, 0x9),
-            (Scancode::F, 0xE),
-            (Scancode::Z, 0xA),
-            (Scancode::X, 0x0),
-            (Scancode::C, 0xB),
-            (Scancode::V, 0xF),
-        ];
-
-        match event {
-            Event::KeyDown {
-                scancode: Some(scancode),
-                ..
-            } => {
-                if let Some(&(_, index)) = key_mappings.iter().find(|&&(key, _)| key == scancode) {
-                    cpu.key_pressed(index);
-                } else {
-                    // Handle scancodes that are not mapped to a key
-                    match scancode {
-                        Scancode::Space => cpu.halt = !cpu.halt,
-                        Scancode::M => cpu.cycle(),
-                        Scancode::P => cpu.print_registers(),
-                        Scancode::Return => cpu.reset(),
-                        _ => {}
-                    }
-                }
-            }
-            Event::KeyUp {
-                scancode: Some(scancode),
-                ..
-            } => {
-                if let Some(&(_, index)) = key_mappings.iter().find(|&&(key, _)| key == scancode) {
-                    cpu.key_released(index);
-                }
-            }
-            _ => {}
-        }
+    /// Replaces the rewind history with an empty one with new limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The number of frames and bytes the history may hold.
+    pub fn set_rewind_config(&mut self, config: RewindConfig) {
+        let config = SessionConfig {
+            rewind: config,
+            ..*self.session.config()
+        };
+        self.session.set_config(config);
     }
 }
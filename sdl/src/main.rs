@@ -1,26 +1,268 @@
 
+use std::fs;
+use std::path::Path;
+use std::process;
 use chip8::cpu;
+use chip8::frontend::{Rotation, ScaleMode};
+use chip8::octo;
+use chip8::quirks::Platform;
+use chip8::rng::SeededRandom;
+use chip8::romdb::{default_config_dir, RomDatabase, RomSettings};
 use sdl::binaryreader::BinaryFileReader;
+use sdl::SdlOptions;
+use sdl2::pixels::Color;
 
-fn main() {
-    let mut sdl = sdl::SDL::new();
-    let mut cpu = cpu::CPU::new();
+const USAGE: &str = "Usage: sdl <rom> [options]
+
+Runs a CHIP-8 ROM, or Octo source (.8o), in a window.
 
-    let program_path = "roms/stars_snaydenov.bin";
+Options:
+    --clock <hz>            CPU clock speed, at least 60 (default 1500)
+    --scale <n>             Initial window pixels per low-resolution pixel, at least 2 (default 10)
+    --scale-mode <mode>     integer or fit, how the screen fills a resized window (default integer)
+    --rotate <degrees>      Turn the screen clockwise by 0, 90, 180 or 270 degrees (default 0)
+    --fullscreen            Start fullscreen, press F11 to switch
+    --fg <rrggbb>           Colour of lit pixels (default ffffff)
+    --bg <rrggbb>           Colour of unlit pixels (default 000000)
+    --platform <name>       vip, chip48, schip or xochip (default: the CPU defaults)
+    --bare-platform         Keep the quirks of the platform preset, without the ROM's overrides
+    --paused                Start paused, press SPACE to run
+    --load-address <addr>   Where the program is loaded and started (default 0x200)
+    --seed <n>              Seed the random generator for reproducible runs
+    --no-romdb              Ignore the per-ROM settings of the config directory
+    -h, --help              Print this help
 
-    let program = match read_chip8_rom(program_path) {
+The platform, quirks, clock, colours, keys, rotation and title of known ROMs are looked up
+by SHA-1 in the config directory, see `chip8 romdb`. The options above take precedence,
+but the quirks of the ROM still apply over a --platform preset unless --bare-platform is given.";
+
+/// The command-line settings of the SDL frontend. Unset values come from the ROM's settings.
+struct Args {
+    rom_path: String,
+    scale: Option<u32>,
+    scale_mode: ScaleMode,
+    rotation: Option<Rotation>,
+    fullscreen: bool,
+    cycles_per_frame: Option<u32>,
+    foreground: Option<Color>,
+    background: Option<Color>,
+    platform: Option<Platform>,
+    bare_platform: bool,
+    paused: bool,
+    load_address: Option<u16>,
+    seed: Option<u64>,
+    use_romdb: bool,
+}
+
+fn main() {
+    let args = parse_args(std::env::args().skip(1));
+    let program = match read_program(&args.rom_path, args.load_address.unwrap_or(0x200)) {
         Ok(data) => data,
         Err(e) => {
             println!("Error loading CHIP-8 program: {}", e);
-            return;
+            process::exit(1);
         }
     };
+    let settings = if args.use_romdb { rom_settings(&program) } else { RomSettings::default() };
 
-    cpu.load_program(&program);
+    let mut cpu = match args.platform.or(settings.platform) {
+        Some(platform) => cpu::CPU::with_platform(platform),
+        None => cpu::CPU::new(),
+    };
+    if !args.bare_platform {
+        settings.quirks.apply(&mut cpu.quirks);
+    }
+    if let Some(seed) = args.seed {
+        cpu.set_rng(Box::new(SeededRandom::new(seed)));
+    }
 
+    // Octo sources are compiled for the command-line address, so the ROM settings cannot move them
+    let load_address = match args.load_address {
+        Some(addr) => addr,
+        None if is_octo_source(&args.rom_path) => 0x200,
+        None => settings.load_address.unwrap_or(0x200),
+    };
+    if let Err(e) = cpu.load_program_at(&program, load_address) {
+        println!("Error loading CHIP-8 program: {}", e);
+        process::exit(1);
+    }
+    let mut options = SdlOptions::default();
+    if let Some(title) = &settings.title {
+        options.title = title.clone();
+    }
+    options.scale = args.scale.unwrap_or(options.scale);
+    options.scale_mode = args.scale_mode;
+    options.rotation = args.rotation.or(settings.rotation).unwrap_or_default();
+    options.fullscreen = args.fullscreen;
+    options.paused = args.paused;
+    options.session.cycles_per_frame = args
+        .cycles_per_frame
+        .or(settings.cycles_per_frame)
+        .unwrap_or(options.session.cycles_per_frame);
+    for (colour, &(r, g, b)) in options.palette.iter_mut().zip(&settings.colors) {
+        *colour = Color::RGB(r, g, b);
+    }
+    options.palette[0] = args.background.unwrap_or(options.palette[0]);
+    options.palette[1] = args.foreground.unwrap_or(options.palette[1]);
+    options.actions = settings.keys;
+
+    let mut sdl = sdl::SDL::with_options(options);
+    sdl.set_rom(Path::new(&args.rom_path), &program);
     sdl.run(&mut cpu)
 }
 
+/// Parses the command line, exiting with the usage on errors and `--help`.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Args {
+    let mut rom_path = None;
+    let mut scale = None;
+    let mut scale_mode = ScaleMode::default();
+    let mut rotation = None;
+    let mut fullscreen = false;
+    let mut cycles_per_frame = None;
+    let mut foreground = None;
+    let mut background = None;
+    let mut platform = None;
+    let mut bare_platform = false;
+    let mut paused = false;
+    let mut load_address = None;
+    let mut seed = None;
+    let mut use_romdb = true;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--clock" => match args.next().and_then(|value| value.parse::<u32>().ok()) {
+                Some(hz) if hz >= cpu::TIMER_HZ => cycles_per_frame = Some(hz / cpu::TIMER_HZ),
+                _ => usage_error(&format!("--clock expects a number of Hz, at least {}", cpu::TIMER_HZ)),
+            },
+            "--scale" => match args.next().and_then(|value| value.parse::<u32>().ok()) {
+                Some(value) if value >= 2 => scale = Some(value),
+                _ => usage_error("--scale expects a number, at least 2"),
+            },
+            "--scale-mode" => match args.next().and_then(|name| ScaleMode::from_name(&name)) {
+                Some(mode) => scale_mode = mode,
+                None => usage_error("--scale-mode expects integer or fit"),
+            },
+            "--rotate" => match args.next().and_then(|value| Rotation::from_degrees(value.parse().ok()?)) {
+                Some(value) => rotation = Some(value),
+                None => usage_error("--rotate expects 0, 90, 180 or 270"),
+            },
+            "--fullscreen" => fullscreen = true,
+            "--fg" => match args.next().and_then(|value| parse_colour(&value)) {
+                Some(colour) => foreground = Some(colour),
+                None => usage_error("--fg expects a colour as rrggbb"),
+            },
+            "--bg" => match args.next().and_then(|value| parse_colour(&value)) {
+                Some(colour) => background = Some(colour),
+                None => usage_error("--bg expects a colour as rrggbb"),
+            },
+            "--platform" => match args.next().and_then(|name| Platform::from_name(&name)) {
+                Some(value) => platform = Some(value),
+                None => usage_error("--platform expects vip, chip48, schip or xochip"),
+            },
+            "--bare-platform" => bare_platform = true,
+            "--paused" => paused = true,
+            "--no-romdb" => use_romdb = false,
+            "--load-address" => match args.next().and_then(|value| parse_number(&value)) {
+                Some(addr) => load_address = Some(addr),
+                None => usage_error("--load-address expects an address such as 0x600"),
+            },
+            "--seed" => match args.next().and_then(|value| parse_number(&value)) {
+                Some(value) => seed = Some(value),
+                None => usage_error("--seed expects a number"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option '{}'", arg)),
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    Args {
+        rom_path: rom_path.unwrap_or_else(|| usage_error("missing ROM path")),
+        scale,
+        scale_mode,
+        rotation,
+        fullscreen,
+        cycles_per_frame,
+        foreground,
+        background,
+        platform,
+        bare_platform,
+        paused,
+        load_address,
+        seed,
+        use_romdb,
+    }
+}
+
+/// Looks up the settings of a ROM in the config directory, warning if it cannot be read.
+fn rom_settings(program: &[u8]) -> RomSettings {
+    let Some(dir) = default_config_dir() else {
+        return RomSettings::default();
+    };
+    let mut database = RomDatabase::new();
+    if let Err(e) = database.load_config_dir(&dir) {
+        eprintln!("Warning: ignoring the ROM settings in {}: {}", dir.display(), e);
+        return RomSettings::default();
+    }
+    match database.lookup_rom(program) {
+        Some(settings) => {
+            println!("Using the settings of {}", settings.title.as_deref().unwrap_or("a known ROM"));
+            settings.clone()
+        }
+        None => RomSettings::default(),
+    }
+}
+
+/// Prints an error and the usage, then exits.
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+/// Parses a decimal number, or a hexadecimal one prefixed with `0x`.
+fn parse_number<T: TryFrom<u64>>(value: &str) -> Option<T> {
+    let number = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => value.parse().ok()?,
+    };
+    T::try_from(number).ok()
+}
+
+/// Parses a colour written as `rrggbb`, with an optional leading `#`.
+fn parse_colour(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+/// Returns true if the path is Octo source (`.8o`) rather than a ROM.
+fn is_octo_source(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|extension| extension == "8o")
+}
+
+/// Reads a program, compiling it first if it is Octo source (`.8o`).
+///
+/// # Arguments
+///
+/// * `path` - The path to the ROM or Octo source.
+/// * `load_address` - The address Octo source is compiled for.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, String>` - The ROM, or a description of the error.
+fn read_program(path: &str, load_address: u16) -> Result<Vec<u8>, String> {
+    if is_octo_source(path) {
+        let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let program = octo::compile_at(&source, load_address).map_err(|e| format!("{}:{}", path, e))?;
+        return Ok(program.rom);
+    }
+    read_chip8_rom(path).map_err(|e| e.to_string())
+}
 
 fn read_chip8_rom(path: &str) -> std::io::Result<Vec<u8>> {
     BinaryFileReader::read(path)
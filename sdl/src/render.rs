@@ -0,0 +1,112 @@
+use std::mem::ManuallyDrop;
+
+use chip8::frontend::{Rotation, Viewport};
+use chip8::gpu::{GPU, HIRES_HEIGHT, HIRES_WIDTH};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+/// The pixel format of the frame texture, 4 bytes per pixel in R, G, B, A order.
+pub const FRAME_FORMAT: PixelFormatEnum = PixelFormatEnum::RGBA32;
+/// The number of bytes per pixel of `FRAME_FORMAT`.
+pub const BYTES_PER_PIXEL: usize = 4;
+
+/// Converts the GPU buffer into RGBA pixels.
+///
+/// # Arguments
+///
+/// * `gpu` - The GPU to convert, in its current resolution.
+/// * `palette` - The colours of the pixels, indexed by their XO-CHIP bitplanes.
+/// * `pixels` - The destination, at least `pitch` bytes per row of the GPU.
+/// * `pitch` - The number of bytes between the starts of two rows of `pixels`.
+pub fn fill_rgba(gpu: &GPU, palette: &[Color; 4], pixels: &mut [u8], pitch: usize) {
+    let width = gpu.width();
+    for (row, line) in pixels.chunks_mut(pitch).zip(&gpu.video_buffer[..gpu.height()]) {
+        for (pixel, &value) in row[..width * BYTES_PER_PIXEL].chunks_exact_mut(BYTES_PER_PIXEL).zip(&line[..width]) {
+            // The pixel value is the combination of its bitplanes
+            let colour = palette[(value & 0x3) as usize];
+            pixel.copy_from_slice(&[colour.r, colour.g, colour.b, colour.a]);
+        }
+    }
+}
+
+/// Returns the rectangle to copy the frame to with `Canvas::copy_ex`, which rotates it around
+/// its centre, so that it covers the viewport once rotated.
+///
+/// # Arguments
+///
+/// * `viewport` - The area of the window the screen is drawn to, after the rotation.
+/// * `rotation` - How the screen is turned.
+pub fn destination(viewport: &Viewport, rotation: Rotation) -> Rect {
+    let (width, height) = if rotation.is_sideways() {
+        (viewport.height, viewport.width)
+    } else {
+        (viewport.width, viewport.height)
+    };
+    let centre_x = viewport.x as i32 + viewport.width as i32 / 2;
+    let centre_y = viewport.y as i32 + viewport.height as i32 / 2;
+    Rect::new(centre_x - width as i32 / 2, centre_y - height as i32 / 2, width, height)
+}
+
+/// A streaming texture holding the latest frame, uploaded once per frame and scaled to the
+/// window with a single copy.
+///
+/// The texture is sized for the high resolution, and low-resolution frames use its top-left
+/// quarter, so switching modes never reallocates it.
+///
+/// The texture is destroyed when this is dropped, which must happen before the canvas that
+/// created it is dropped.
+pub struct FrameTexture {
+    texture: ManuallyDrop<Texture>,
+    /// The size in pixels of the frame in the texture.
+    size: (u32, u32),
+}
+
+impl FrameTexture {
+    /// Creates the texture for a canvas.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the renderer cannot create a streaming RGBA texture.
+    pub fn new(canvas: &Canvas<Window>) -> Self {
+        let texture = canvas
+            .create_texture_streaming(FRAME_FORMAT, HIRES_WIDTH as u32, HIRES_HEIGHT as u32)
+            .unwrap();
+        FrameTexture {
+            texture: ManuallyDrop::new(texture),
+            size: (0, 0),
+        }
+    }
+
+    /// Uploads the GPU buffer into the texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `gpu` - The GPU to upload, in its current resolution.
+    /// * `palette` - The colours of the pixels, indexed by their XO-CHIP bitplanes.
+    pub fn update(&mut self, gpu: &GPU, palette: &[Color; 4]) {
+        self.size = (gpu.width() as u32, gpu.height() as u32);
+        let source = self.source();
+        self.texture
+            .with_lock(source, |pixels, pitch| fill_rgba(gpu, palette, pixels, pitch))
+            .unwrap();
+    }
+
+    /// Returns the texture, to be copied from `source`.
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Returns the part of the texture holding the frame.
+    pub fn source(&self) -> Rect {
+        Rect::new(0, 0, self.size.0, self.size.1)
+    }
+}
+
+impl Drop for FrameTexture {
+    fn drop(&mut self) {
+        // SAFETY: the texture is not used again, and its canvas outlives it as documented above
+        unsafe { ManuallyDrop::take(&mut self.texture).destroy() }
+    }
+}
@@ -0,0 +1,149 @@
+use chip8::frontend::Command;
+
+/// The number of frames a key stays pressed after its last byte was read.
+///
+/// Terminals only report key presses, repeated while the key is held, so a key is released
+/// when it has not repeated for this long. It covers the usual auto-repeat delay.
+pub const KEY_HOLD_FRAMES: u8 = 30;
+
+/// The keys of the 16-key keypad, laid out as in the `sdl` frontend:
+///
+/// 1 2 3 4
+/// Q W E R
+/// A S D F
+/// Z X C V
+const KEY_MAPPINGS: [(u8, u8); 16] = [
+    (b'1', 0x1),
+    (b'2', 0x2),
+    (b'3', 0x3),
+    (b'4', 0xC),
+    (b'q', 0x4),
+    (b'w', 0x5),
+    (b'e', 0x6),
+    (b'r', 0xD),
+    (b'a', 0x7),
+    (b's', 0x8),
+    (b'd', 0x9),
+    (b'f', 0xE),
+    (b'z', 0xA),
+    (b'x', 0x0),
+    (b'c', 0xB),
+    (b'v', 0xF),
+];
+
+/// The function keys, by the escape sequence following ESC.
+const FUNCTION_KEYS: [(&[u8], Command); 6] = [
+    (b"OQ", Command::ToggleRecording),   /* F2 */
+    (b"OR", Command::TogglePlayback),    /* F3 */
+    (b"[15~", Command::SaveState),       /* F5 */
+    (b"[17~", Command::PreviousSlot),    /* F6 */
+    (b"[18~", Command::NextSlot),        /* F7 */
+    (b"[20~", Command::LoadState),       /* F9 */
+];
+
+/// Turns the bytes read from a raw-mode terminal into commands, synthesizing the key
+/// releases that terminals do not report.
+#[derive(Clone, Debug, Default)]
+pub struct KeyDecoder {
+    /// The frames left before each keypad key is released, 0 if it is up.
+    keys: [u8; 16],
+    /// The frames left before rewinding stops, 0 if it is not held.
+    rewind: u8,
+}
+
+impl KeyDecoder {
+    /// Creates a decoder with every key up.
+    pub fn new() -> Self {
+        KeyDecoder::default()
+    }
+
+    /// Decodes the bytes read during one frame, which may be empty.
+    ///
+    /// It must be called once per frame so that held keys are released in time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use chip8::frontend::Command;
+    /// use term::input::KeyDecoder;
+    ///
+    /// let mut decoder = KeyDecoder::new();
+    /// assert_eq!(decoder.feed(b"w"), vec![Command::Key { key: 5, pressed: true }]);
+    /// assert_eq!(decoder.feed(b"\x1b[15~"), vec![Command::SaveState]);
+    /// ```
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Command> {
+        let mut commands = Vec::new();
+        let mut refreshed = [false; 16];
+        let mut rewind_refreshed = false;
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let byte = bytes[pos];
+            pos += 1;
+            let lower = byte.to_ascii_lowercase();
+            if let Some(&(_, key)) = KEY_MAPPINGS.iter().find(|&&(code, _)| code == lower) {
+                if self.keys[key as usize] == 0 {
+                    commands.push(Command::Key { key, pressed: true });
+                }
+                self.keys[key as usize] = KEY_HOLD_FRAMES;
+                refreshed[key as usize] = true;
+                continue;
+            }
+            match lower {
+                0x1B if pos == bytes.len() => commands.push(Command::Quit),
+                0x1B => pos += self.escape_sequence(&bytes[pos..], &mut commands),
+                0x03 => commands.push(Command::Quit), /* Ctrl-C */
+                b' ' => commands.push(Command::TogglePause),
+                b'm' => commands.push(Command::Step),
+                b'p' => commands.push(Command::PrintRegisters),
+                b'\r' | b'\n' => commands.push(Command::Reset),
+                b'n' => commands.push(Command::ToggleMute),
+                0x7F | 0x08 => {
+                    if self.rewind == 0 {
+                        commands.push(Command::Rewind(true));
+                    }
+                    self.rewind = KEY_HOLD_FRAMES;
+                    rewind_refreshed = true;
+                }
+                _ => {}
+            }
+        }
+
+        for (key, frames) in self.keys.iter_mut().enumerate() {
+            if *frames > 0 && !refreshed[key] {
+                *frames -= 1;
+                if *frames == 0 {
+                    commands.push(Command::Key { key: key as u8, pressed: false });
+                }
+            }
+        }
+        if self.rewind > 0 && !rewind_refreshed {
+            self.rewind -= 1;
+            if self.rewind == 0 {
+                commands.push(Command::Rewind(false));
+            }
+        }
+        commands
+    }
+
+    /// Decodes the escape sequence following an ESC byte.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of bytes of the sequence, skipped even if it is unknown.
+    fn escape_sequence(&self, bytes: &[u8], commands: &mut Vec<Command>) -> usize {
+        if let Some(&(sequence, command)) = FUNCTION_KEYS.iter().find(|(sequence, _)| bytes.starts_with(sequence)) {
+            commands.push(command);
+            return sequence.len();
+        }
+        match bytes.first() {
+            /* A CSI sequence ends with a byte from 0x40 to 0x7E */
+            Some(b'[') => bytes[1..]
+                .iter()
+                .position(|byte| (0x40..=0x7E).contains(byte))
+                .map_or(bytes.len(), |end| end + 2),
+            /* An SS3 sequence is followed by a single byte */
+            Some(b'O') => bytes.len().min(2),
+            _ => 0,
+        }
+    }
+}
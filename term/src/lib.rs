@@ -0,0 +1,161 @@
+pub mod input;
+pub mod render;
+
+use std::io::{self, Read, Write};
+use std::mem::MaybeUninit;
+
+use chip8::frontend::{Audio, Command, Display, Input};
+use chip8::gpu::GPU;
+use chip8::sound::SoundState;
+
+use crate::input::KeyDecoder;
+use crate::render::{render, RenderMode};
+
+/// Puts the terminal in raw mode on an alternate screen, and restores it when dropped.
+///
+/// Raw mode disables line buffering, echo and signal keys, so that key presses, including
+/// Ctrl-C, are read as soon as they are typed, and makes reads from stdin non-blocking.
+pub struct RawTerminal {
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    /// Switches stdin to raw mode and stdout to the alternate screen, hiding the cursor.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if stdin is not a terminal.
+    pub fn enter() -> io::Result<Self> {
+        let mut termios = MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: tcgetattr fully initializes the struct when it succeeds
+        let original = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios.assume_init()
+        };
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        /* Reads return immediately, with whatever bytes are available */
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: raw is a valid termios obtained from tcgetattr
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        stdout.flush()?;
+        Ok(RawTerminal { original })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        // SAFETY: original was obtained from tcgetattr
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// The terminal `Display` backend, redrawing the lines that changed and a status line.
+pub struct TermDisplay {
+    mode: RenderMode,
+    /// The lines drawn by the last frame, to skip those that did not change.
+    previous: Vec<String>,
+    /// The last message, shown below the display.
+    status: String,
+    /// Whether the status line must be redrawn.
+    status_changed: bool,
+}
+
+impl TermDisplay {
+    /// Creates a display drawing in the given mode.
+    pub fn new(mode: RenderMode) -> Self {
+        TermDisplay {
+            mode,
+            previous: Vec::new(),
+            status: String::new(),
+            status_changed: true,
+        }
+    }
+}
+
+impl Display for TermDisplay {
+    fn present(&mut self, gpu: &GPU) {
+        let lines = render(gpu, self.mode);
+        let mut frame = String::new();
+        if lines.len() != self.previous.len() {
+            /* The resolution changed */
+            frame.push_str("\x1b[2J");
+            self.previous.clear();
+            self.status_changed = true;
+        }
+        for (row, line) in lines.iter().enumerate() {
+            if self.previous.get(row) != Some(line) {
+                frame.push_str(&format!("\x1b[{};1H{}", row + 1, line));
+            }
+        }
+        if self.status_changed {
+            frame.push_str(&format!("\x1b[{};1H\x1b[2K{}", lines.len() + 2, self.status));
+            self.status_changed = false;
+        }
+        self.previous = lines;
+
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(frame.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    fn message(&mut self, text: &str) {
+        self.status = text.to_string();
+        self.status_changed = true;
+    }
+}
+
+/// The terminal `Input` backend, reading the keys from stdin in raw mode.
+#[derive(Default)]
+pub struct TermInput {
+    decoder: KeyDecoder,
+}
+
+impl TermInput {
+    /// Creates an input with every key up. The terminal must be in raw mode.
+    pub fn new() -> Self {
+        TermInput::default()
+    }
+}
+
+impl Input for TermInput {
+    fn poll(&mut self) -> Vec<Command> {
+        let mut bytes = Vec::new();
+        let mut buffer = [0; 64];
+        let mut stdin = io::stdin().lock();
+        while let Ok(count @ 1..) = stdin.read(&mut buffer) {
+            bytes.extend_from_slice(&buffer[..count]);
+        }
+        self.decoder.feed(&bytes)
+    }
+}
+
+/// An `Audio` backend ringing the terminal bell each time the buzzer starts.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BellAudio {
+    active: bool,
+}
+
+impl Audio for BellAudio {
+    fn update(&mut self, state: SoundState) {
+        if state.active && !self.active {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x07");
+            let _ = stdout.flush();
+        }
+        self.active = state.active;
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use chip8::cpu::CPU;
+use chip8::frontend::{Audio, NullAudio, Session, SessionConfig};
+use chip8::octo;
+use chip8::quirks::Platform;
+use term::render::RenderMode;
+use term::{BellAudio, RawTerminal, TermDisplay, TermInput};
+
+const USAGE: &str = "Usage: term <rom> [--braille] [--platform <name>] [--bell]
+
+    --braille          Draw 2x4 pixels per character with braille patterns instead of half blocks
+    --platform <name>  vip, chip48, schip or xochip (default: the CPU defaults)
+    --bell             Ring the terminal bell when the buzzer starts
+
+Keys: 1234/QWER/ASDF/ZXCV keypad, ESC quit, SPACE pause, M step, P registers, Enter reset,
+N mute, Backspace rewind, F5/F9 save/load, F6/F7 slot, F2 record, F3 play";
+
+fn main() {
+    let mut rom_path = None;
+    let mut mode = RenderMode::HalfBlock;
+    let mut platform = None;
+    let mut bell = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--braille" => mode = RenderMode::Braille,
+            "--platform" => match args.next().and_then(|name| Platform::from_name(&name)) {
+                Some(value) => platform = Some(value),
+                None => usage_error("--platform expects vip, chip48, schip or xochip"),
+            },
+            "--bell" => bell = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => usage_error(&format!("unexpected argument '{}'", arg)),
+        }
+    }
+    let rom_path = rom_path.unwrap_or_else(|| usage_error("missing ROM path"));
+
+    let program = match read_program(&rom_path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Error loading CHIP-8 program: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut cpu = platform.map_or_else(CPU::new, CPU::with_platform);
+    if let Err(e) = cpu.load_program(&program) {
+        eprintln!("Error loading CHIP-8 program: {}", e);
+        process::exit(1);
+    }
+
+    let mut session = Session::new(SessionConfig::default());
+    session.set_rom(Path::new(&rom_path), &program);
+    let mut display = TermDisplay::new(mode);
+    let mut input = TermInput::new();
+    let mut audio: Box<dyn Audio> = if bell { Box::new(BellAudio::default()) } else { Box::new(NullAudio) };
+
+    let terminal = match RawTerminal::enter() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            eprintln!("Cannot use the terminal: {}", e);
+            process::exit(1);
+        }
+    };
+    session.run(&mut cpu, &mut display, &mut input, audio.as_mut());
+    drop(terminal);
+}
+
+/// Prints an error and the usage, then exits.
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    process::exit(1);
+}
+
+/// Reads a program, compiling it first if it is Octo source (`.8o`).
+///
+/// # Arguments
+///
+/// * `path` - The path to the ROM or Octo source.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, String>` - The ROM, or a description of the error.
+fn read_program(path: &str) -> Result<Vec<u8>, String> {
+    if Path::new(path).extension().is_some_and(|extension| extension == "8o") {
+        let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let program = octo::compile(&source).map_err(|e| format!("{}:{}", path, e))?;
+        return Ok(program.rom);
+    }
+    fs::read(path).map_err(|e| e.to_string())
+}
@@ -0,0 +1,106 @@
+use std::fmt::Write;
+
+use chip8::gpu::GPU;
+
+/// The colours of the pixels, indexed by their XO-CHIP bitplanes, as in the `sdl` frontend.
+pub const PALETTE: [(u8, u8, u8); 4] = [(0, 0, 0), (255, 255, 255), (170, 170, 170), (85, 85, 85)];
+
+/// How pixels are packed into terminal cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    /// One column and two rows per cell, drawn as an upper half block in colour.
+    HalfBlock,
+    /// Two columns and four rows per cell, drawn as a monochrome braille pattern.
+    Braille,
+}
+
+impl RenderMode {
+    /// Returns the number of pixel columns and rows drawn by one cell.
+    pub fn cell_size(self) -> (usize, usize) {
+        match self {
+            RenderMode::HalfBlock => (1, 2),
+            RenderMode::Braille => (2, 4),
+        }
+    }
+
+    /// Returns the number of columns and lines needed to draw a display.
+    pub fn text_size(self, gpu: &GPU) -> (usize, usize) {
+        let (cell_width, cell_height) = self.cell_size();
+        (gpu.width().div_ceil(cell_width), gpu.height().div_ceil(cell_height))
+    }
+}
+
+/// Renders the display as lines of text, one per row of cells.
+///
+/// Half-block lines contain ANSI 24-bit colour sequences and end with a reset.
+///
+/// # Arguments
+///
+/// * `gpu` - The display to draw.
+/// * `mode` - How pixels are packed into cells.
+pub fn render(gpu: &GPU, mode: RenderMode) -> Vec<String> {
+    match mode {
+        RenderMode::HalfBlock => render_half_blocks(gpu),
+        RenderMode::Braille => render_braille(gpu),
+    }
+}
+
+/// Returns the colour index of a pixel, 0 outside the display.
+fn pixel(gpu: &GPU, x: usize, y: usize) -> usize {
+    if x < gpu.width() && y < gpu.height() {
+        (gpu.video_buffer[y][x] & 0x3) as usize
+    } else {
+        0
+    }
+}
+
+/// Draws every cell as `▀`, the foreground colouring the upper pixel and the background
+/// the lower one. The colours are only set when they change along a line.
+fn render_half_blocks(gpu: &GPU) -> Vec<String> {
+    (0..gpu.height().div_ceil(2))
+        .map(|row| {
+            let mut line = String::new();
+            let mut current = None;
+            for x in 0..gpu.width() {
+                let colours = (pixel(gpu, x, row * 2), pixel(gpu, x, row * 2 + 1));
+                if current != Some(colours) {
+                    let (top, bottom) = (PALETTE[colours.0], PALETTE[colours.1]);
+                    write!(
+                        line,
+                        "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                        top.0, top.1, top.2, bottom.0, bottom.1, bottom.2
+                    )
+                    .unwrap();
+                    current = Some(colours);
+                }
+                line.push('▀');
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect()
+}
+
+/// The braille dot of each pixel of a 2x4 cell, indexed by row then column.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Draws every 2x4 block of pixels as a braille pattern, with a dot for each lit pixel.
+fn render_braille(gpu: &GPU) -> Vec<String> {
+    (0..gpu.height().div_ceil(4))
+        .map(|row| {
+            (0..gpu.width().div_ceil(2))
+                .map(|col| {
+                    let mut dots = 0;
+                    for (dy, row_dots) in BRAILLE_DOTS.iter().enumerate() {
+                        for (dx, dot) in row_dots.iter().enumerate() {
+                            if pixel(gpu, col * 2 + dx, row * 4 + dy) != 0 {
+                                dots |= dot;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + dots).unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+pub mod tests {
+    use chip8::frontend::Command;
+    use chip8::gpu::GPU;
+    use term::input::{KeyDecoder, KEY_HOLD_FRAMES};
+    use term::render::{render, RenderMode};
+
+    /// Lights the pixels of a GPU listed as (x, y) with a colour index.
+    fn gpu_with(pixels: &[(usize, usize, u8)]) -> GPU {
+        let mut gpu = GPU::new();
+        for &(x, y, colour) in pixels {
+            gpu.video_buffer[y][x] = colour;
+        }
+        gpu
+    }
+
+    #[test]
+    fn test_braille() {
+        let gpu = gpu_with(&[(0, 0, 1), (1, 3, 1), (2, 0, 1), (2, 1, 1), (63, 31, 1)]);
+        let lines = render(&gpu, RenderMode::Braille);
+        assert_eq!(lines.len(), 8);
+        assert!(lines.iter().all(|line| line.chars().count() == 32));
+        assert!(lines[0].starts_with("\u{2881}\u{2803}\u{2800}"));
+        assert!(lines[7].ends_with('\u{2880}'));
+
+        let mut gpu = GPU::new();
+        gpu.set_hires(true);
+        assert_eq!(RenderMode::Braille.text_size(&gpu), (64, 16));
+        assert_eq!(RenderMode::HalfBlock.text_size(&gpu), (128, 32));
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        let gpu = gpu_with(&[(0, 0, 1), (1, 0, 1), (2, 1, 3)]);
+        let lines = render(&gpu, RenderMode::HalfBlock);
+        assert_eq!(lines.len(), 16);
+        assert!(lines[0].starts_with(concat!(
+            "\x1b[38;2;255;255;255;48;2;0;0;0m▀▀",
+            "\x1b[38;2;0;0;0;48;2;85;85;85m▀",
+            "\x1b[38;2;0;0;0;48;2;0;0;0m▀▀"
+        )));
+        assert!(lines[0].ends_with("▀\x1b[0m"));
+        assert_eq!(lines[1], format!("\x1b[38;2;0;0;0;48;2;0;0;0m{}\x1b[0m", "▀".repeat(64)));
+    }
+
+    #[test]
+    fn test_key_hold_and_release() {
+        let mut decoder = KeyDecoder::new();
+        assert_eq!(decoder.feed(b"Q"), vec![Command::Key { key: 4, pressed: true }]);
+        /* Auto-repeat keeps the key down without pressing it again */
+        for _ in 0..KEY_HOLD_FRAMES - 1 {
+            assert_eq!(decoder.feed(b""), vec![]);
+        }
+        assert_eq!(decoder.feed(b"q"), vec![]);
+        for _ in 0..KEY_HOLD_FRAMES - 1 {
+            assert_eq!(decoder.feed(b""), vec![]);
+        }
+        assert_eq!(decoder.feed(b""), vec![Command::Key { key: 4, pressed: false }]);
+
+        assert_eq!(decoder.feed(b"\x7f"), vec![Command::Rewind(true)]);
+        for _ in 0..KEY_HOLD_FRAMES - 1 {
+            decoder.feed(b"");
+        }
+        assert_eq!(decoder.feed(b""), vec![Command::Rewind(false)]);
+    }
+
+    #[test]
+    fn test_commands_and_escapes() {
+        let mut decoder = KeyDecoder::new();
+        assert_eq!(
+            decoder.feed(b" mp\rn\x1bOQ\x1b[20~\x1b[A\x1b[17~"),
+            vec![
+                Command::TogglePause,
+                Command::Step,
+                Command::PrintRegisters,
+                Command::Reset,
+                Command::ToggleMute,
+                Command::ToggleRecording,
+                Command::LoadState,
+                Command::PreviousSlot,
+            ]
+        );
+        assert_eq!(decoder.feed(b"\x1b"), vec![Command::Quit]);
+        assert_eq!(decoder.feed(b"\x03"), vec![Command::Quit]);
+    }
+}